@@ -0,0 +1,724 @@
+//! Parsers for the Anchor events emitted by the Boop program.
+//!
+//! Codama does not generate event parsers, so this module is maintained by
+//! hand alongside the generated code.
+
+#[cfg(feature = "shared-data")]
+use std::sync::Arc;
+
+use solana_pubkey::{pubkey, Pubkey};
+#[cfg(feature = "shared-data")]
+use yellowstone_vixen_core::InstructionUpdateOutput;
+
+use crate::{
+    deserialize_checked,
+    instructions_parser::{BoopProgramIx, InstructionParser},
+    types::{
+        AuthorityTransferCancelledEvent, AuthorityTransferCompletedEvent,
+        AuthorityTransferInitiatedEvent, BondingCurveDeployedEvent,
+        BondingCurveDeployedFallbackEvent, BondingCurveVaultClosedEvent, ConfigUpdatedEvent,
+        LiquidityDepositedIntoRaydiumEvent, OperatorsAddedEvent, OperatorsRemovedEvent,
+        PausedToggledEvent, RaydiumLiquidityLockedEvent, RaydiumPoolCreatedEvent,
+        RaydiumRandomPoolCreatedEvent, SwapSolForTokensOnRaydiumEvent,
+        SwapTokensForSolOnRaydiumEvent, TokenBoughtEvent, TokenCreatedEvent,
+        TokenCreatedFallbackEvent, TokenGraduatedEvent, TokenSoldEvent, TradingFeesCollectedEvent,
+        TradingFeesSplitEvent,
+    },
+    ID,
+};
+
+/// The PDA signing the self-invoked instructions `emit_cpi!` records events
+/// with, derived from the `__event_authority` seed.
+pub const EVENT_AUTHORITY: Pubkey = pubkey!("BLzirnVYhBpWsVXGScuBXHCsWffs7wf32dUkwnM3GvNo");
+
+/// Boop Events
+#[derive(Debug)]
+#[cfg_attr(feature = "tracing", derive(strum_macros::Display))]
+#[allow(clippy::large_enum_variant)]
+pub enum BoopProgramEvent {
+    AuthorityTransferCancelled(AuthorityTransferCancelledEvent),
+    AuthorityTransferCompleted(AuthorityTransferCompletedEvent),
+    AuthorityTransferInitiated(AuthorityTransferInitiatedEvent),
+    BondingCurveDeployed(BondingCurveDeployedEvent),
+    BondingCurveDeployedFallback(BondingCurveDeployedFallbackEvent),
+    BondingCurveVaultClosed(BondingCurveVaultClosedEvent),
+    ConfigUpdated(ConfigUpdatedEvent),
+    LiquidityDepositedIntoRaydium(LiquidityDepositedIntoRaydiumEvent),
+    OperatorsAdded(OperatorsAddedEvent),
+    OperatorsRemoved(OperatorsRemovedEvent),
+    PausedToggled(PausedToggledEvent),
+    RaydiumLiquidityLocked(RaydiumLiquidityLockedEvent),
+    RaydiumPoolCreated(RaydiumPoolCreatedEvent),
+    RaydiumRandomPoolCreated(RaydiumRandomPoolCreatedEvent),
+    SwapSolForTokensOnRaydium(SwapSolForTokensOnRaydiumEvent),
+    SwapTokensForSolOnRaydium(SwapTokensForSolOnRaydiumEvent),
+    TokenBought(TokenBoughtEvent),
+    TokenCreated(TokenCreatedEvent),
+    TokenCreatedFallback(TokenCreatedFallbackEvent),
+    TokenGraduated(TokenGraduatedEvent),
+    TokenSold(TokenSoldEvent),
+    TradingFeesCollected(TradingFeesCollectedEvent),
+    TradingFeesSplit(TradingFeesSplitEvent),
+}
+
+impl BoopProgramEvent {
+    /// Deserialize an Anchor event record, consisting of the 8-byte event
+    /// discriminator followed by the Borsh-serialized event.
+    pub fn try_unpack(data: &[u8]) -> yellowstone_vixen_core::ParseResult<Self> {
+        let event_discriminator: [u8; 8] = data
+            .get(0..8)
+            .ok_or(yellowstone_vixen_core::ParseError::from(
+                "Event data too short".to_owned(),
+            ))?
+            .try_into()?;
+        let event_data = &data[8..];
+        match event_discriminator {
+            [192, 121, 140, 224, 229, 96, 13, 143] => {
+                Ok(BoopProgramEvent::AuthorityTransferCancelled(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [163, 132, 217, 128, 243, 92, 90, 249] => {
+                Ok(BoopProgramEvent::AuthorityTransferCompleted(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [121, 246, 95, 155, 229, 109, 148, 205] => {
+                Ok(BoopProgramEvent::AuthorityTransferInitiated(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [225, 80, 178, 34, 217, 39, 184, 148] => Ok(BoopProgramEvent::BondingCurveDeployed(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [106, 252, 243, 115, 199, 159, 247, 31] => {
+                Ok(BoopProgramEvent::BondingCurveDeployedFallback(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [185, 36, 156, 82, 189, 164, 207, 79] => Ok(BoopProgramEvent::BondingCurveVaultClosed(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [245, 158, 129, 99, 60, 100, 214, 220] => Ok(BoopProgramEvent::ConfigUpdated(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [236, 50, 97, 27, 198, 101, 248, 20] => {
+                Ok(BoopProgramEvent::LiquidityDepositedIntoRaydium(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [247, 58, 112, 56, 203, 186, 112, 152] => Ok(BoopProgramEvent::OperatorsAdded(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [44, 72, 75, 70, 151, 42, 53, 89] => Ok(BoopProgramEvent::OperatorsRemoved(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [143, 222, 228, 224, 6, 230, 64, 176] => Ok(BoopProgramEvent::PausedToggled(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [172, 189, 8, 241, 137, 175, 59, 100] => Ok(BoopProgramEvent::RaydiumLiquidityLocked(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [170, 178, 21, 215, 84, 222, 34, 101] => Ok(BoopProgramEvent::RaydiumPoolCreated(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [152, 251, 128, 152, 158, 235, 83, 53] => {
+                Ok(BoopProgramEvent::RaydiumRandomPoolCreated(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [247, 1, 8, 166, 221, 116, 113, 98] => Ok(BoopProgramEvent::SwapSolForTokensOnRaydium(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [76, 249, 221, 162, 65, 70, 118, 32] => {
+                Ok(BoopProgramEvent::SwapTokensForSolOnRaydium(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [71, 89, 222, 124, 215, 192, 230, 138] => Ok(BoopProgramEvent::TokenBought(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [96, 122, 113, 138, 50, 227, 149, 57] => Ok(BoopProgramEvent::TokenCreated(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [157, 202, 35, 92, 165, 163, 39, 56] => Ok(BoopProgramEvent::TokenCreatedFallback(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [73, 116, 111, 26, 92, 217, 146, 141] => Ok(BoopProgramEvent::TokenGraduated(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [204, 239, 182, 77, 241, 51, 77, 66] => Ok(BoopProgramEvent::TokenSold(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [225, 63, 26, 55, 134, 243, 210, 203] => Ok(BoopProgramEvent::TradingFeesCollected(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [113, 60, 159, 17, 253, 174, 135, 122] => Ok(BoopProgramEvent::TradingFeesSplit(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            _ => Err(yellowstone_vixen_core::ParseError::from(
+                "Invalid Event discriminator".to_owned(),
+            )),
+        }
+    }
+
+    /// Decode the events the program logged with `sol_log_data` in the given
+    /// log lines, skipping records that are not known events.
+    pub fn from_logs<'a, I>(log_messages: I) -> Vec<Self>
+    where
+        I: IntoIterator<Item = &'a String>,
+        I::IntoIter: 'a,
+    {
+        yellowstone_vixen_core::logs::program_data(log_messages, ID.to_bytes().into())
+            .filter_map(|data| {
+                let event = Self::try_unpack(&data);
+
+                #[cfg(feature = "tracing")]
+                if let Err(e) = &event {
+                    tracing::debug!(
+                        name: "incorrectly_parsed_event",
+                        name = "log_event",
+                        program = ID.to_string(),
+                        event = "deserialization_error",
+                        discriminator = ?data.get(..8),
+                        error = ?e
+                    );
+                }
+
+                event.ok()
+            })
+            .collect()
+    }
+
+    /// Deserialize an event recorded with `emit_cpi!` from the data of the
+    /// self-invoked instruction following its
+    /// [`EVENT_IX_TAG`](yellowstone_vixen_core::anchor::EVENT_IX_TAG),
+    /// checking that the instruction was signed by [`EVENT_AUTHORITY`].
+    pub fn try_unpack_cpi(
+        accounts: &[yellowstone_vixen_core::Pubkey],
+        data: &[u8],
+    ) -> yellowstone_vixen_core::ParseResult<Self> {
+        if !accounts
+            .first()
+            .is_some_and(|a| a.equals_ref(EVENT_AUTHORITY))
+        {
+            return Err(yellowstone_vixen_core::ParseError::from(
+                "Event instruction not signed by the event authority".to_owned(),
+            ));
+        }
+
+        Self::try_unpack(data)
+    }
+}
+
+/// Parses the events an instruction logs with `sol_log_data`, as emitted by
+/// Anchor's `emit!` macro.
+#[derive(Debug, Copy, Clone)]
+pub struct LogEventParser;
+
+impl yellowstone_vixen_core::Parser for LogEventParser {
+    type Input = yellowstone_vixen_core::instruction::InstructionUpdate;
+    #[cfg(not(feature = "shared-data"))]
+    type Output = Vec<BoopProgramEvent>;
+    #[cfg(feature = "shared-data")]
+    type Output = InstructionUpdateOutput<Vec<BoopProgramEvent>>;
+
+    fn id(&self) -> std::borrow::Cow<str> { "Boop::LogEventParser".into() }
+
+    fn prefilter(&self) -> yellowstone_vixen_core::Prefilter {
+        yellowstone_vixen_core::Prefilter::builder()
+            .transaction_accounts([ID])
+            .build()
+            .unwrap()
+    }
+
+    async fn parse(
+        &self,
+        ix_update: &yellowstone_vixen_core::instruction::InstructionUpdate,
+    ) -> yellowstone_vixen_core::ParseResult<Self::Output> {
+        if !ix_update.program.equals_ref(ID) {
+            return Err(yellowstone_vixen_core::ParseError::Filtered);
+        }
+
+        let events = BoopProgramEvent::from_logs(ix_update.own_log_messages());
+
+        if events.is_empty() {
+            return Err(yellowstone_vixen_core::ParseError::Filtered);
+        }
+
+        #[cfg(not(feature = "shared-data"))]
+        return Ok(events);
+
+        #[cfg(feature = "shared-data")]
+        Ok(InstructionUpdateOutput {
+            parsed_ix: events,
+            shared_data: Arc::clone(&ix_update.shared),
+        })
+    }
+}
+
+impl yellowstone_vixen_core::ProgramParser for LogEventParser {
+    #[inline]
+    fn program_id(&self) -> yellowstone_vixen_core::Pubkey { ID.to_bytes().into() }
+}
+
+/// Boop Instructions, along with the events the program records with
+/// `emit_cpi!`
+#[derive(Debug)]
+#[cfg_attr(feature = "tracing", derive(strum_macros::Display))]
+#[allow(clippy::large_enum_variant)]
+pub enum BoopProgramIxOrEvent {
+    Ix(BoopProgramIx),
+    CpiEvent(BoopProgramEvent),
+}
+
+/// Parses the self-invoked instructions `emit_cpi!` records events with, and
+/// any other instruction with the generated [`InstructionParser`].
+#[derive(Debug, Copy, Clone)]
+pub struct InstructionEventParser;
+
+impl yellowstone_vixen_core::Parser for InstructionEventParser {
+    type Input = yellowstone_vixen_core::instruction::InstructionUpdate;
+    #[cfg(not(feature = "shared-data"))]
+    type Output = BoopProgramIxOrEvent;
+    #[cfg(feature = "shared-data")]
+    type Output = InstructionUpdateOutput<BoopProgramIxOrEvent>;
+
+    fn id(&self) -> std::borrow::Cow<str> { "Boop::InstructionEventParser".into() }
+
+    fn prefilter(&self) -> yellowstone_vixen_core::Prefilter {
+        yellowstone_vixen_core::Parser::prefilter(&InstructionParser)
+    }
+
+    async fn parse(
+        &self,
+        ix_update: &yellowstone_vixen_core::instruction::InstructionUpdate,
+    ) -> yellowstone_vixen_core::ParseResult<Self::Output> {
+        let event_data = match ix_update
+            .data
+            .strip_prefix(&yellowstone_vixen_core::anchor::EVENT_IX_TAG)
+        {
+            Some(data) if ix_update.program.equals_ref(ID) => data,
+            _ => {
+                let ix =
+                    yellowstone_vixen_core::Parser::parse(&InstructionParser, ix_update).await?;
+
+                #[cfg(not(feature = "shared-data"))]
+                return Ok(BoopProgramIxOrEvent::Ix(ix));
+
+                #[cfg(feature = "shared-data")]
+                return Ok(InstructionUpdateOutput {
+                    parsed_ix: BoopProgramIxOrEvent::Ix(ix.parsed_ix),
+                    shared_data: ix.shared_data,
+                });
+            },
+        };
+
+        let event = BoopProgramEvent::try_unpack_cpi(&ix_update.accounts, event_data);
+
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &event {
+            tracing::debug!(
+                name: "incorrectly_parsed_event",
+                name = "cpi_event",
+                program = ID.to_string(),
+                event = "deserialization_error",
+                discriminator = ?event_data.get(..8),
+                error = ?e
+            );
+        }
+
+        #[cfg(not(feature = "shared-data"))]
+        return Ok(BoopProgramIxOrEvent::CpiEvent(event?));
+
+        #[cfg(feature = "shared-data")]
+        Ok(InstructionUpdateOutput {
+            parsed_ix: BoopProgramIxOrEvent::CpiEvent(event?),
+            shared_data: Arc::clone(&ix_update.shared),
+        })
+    }
+}
+
+impl yellowstone_vixen_core::ProgramParser for InstructionEventParser {
+    #[inline]
+    fn program_id(&self) -> yellowstone_vixen_core::Pubkey { ID.to_bytes().into() }
+}
+
+/// Protobuf messages for the Boop events, in the
+/// `vixen.parser.boop` package.
+///
+/// Codama only renders messages for the instructions, accounts and types of
+/// the program, so these are derived by hand. They are not part of
+/// [`DESCRIPTOR_SET`](crate::proto_def::DESCRIPTOR_SET).
+// #[cfg(feature = "proto")]
+pub mod proto_def {
+    #![allow(clippy::large_enum_variant)]
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramEvent {
+        #[prost(
+            oneof = "program_event::EventOneof",
+            tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, \
+                    22, 23"
+        )]
+        pub event_oneof: ::core::option::Option<program_event::EventOneof>,
+    }
+
+    /// Nested message and enum types in `ProgramEvent`.
+    pub mod program_event {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum EventOneof {
+            #[prost(message, tag = "1")]
+            AuthorityTransferCancelled(crate::proto_def::AuthorityTransferCancelledEvent),
+            #[prost(message, tag = "2")]
+            AuthorityTransferCompleted(crate::proto_def::AuthorityTransferCompletedEvent),
+            #[prost(message, tag = "3")]
+            AuthorityTransferInitiated(crate::proto_def::AuthorityTransferInitiatedEvent),
+            #[prost(message, tag = "4")]
+            BondingCurveDeployed(crate::proto_def::BondingCurveDeployedEvent),
+            #[prost(message, tag = "5")]
+            BondingCurveDeployedFallback(crate::proto_def::BondingCurveDeployedFallbackEvent),
+            #[prost(message, tag = "6")]
+            BondingCurveVaultClosed(crate::proto_def::BondingCurveVaultClosedEvent),
+            #[prost(message, tag = "7")]
+            ConfigUpdated(crate::proto_def::ConfigUpdatedEvent),
+            #[prost(message, tag = "8")]
+            LiquidityDepositedIntoRaydium(crate::proto_def::LiquidityDepositedIntoRaydiumEvent),
+            #[prost(message, tag = "9")]
+            OperatorsAdded(crate::proto_def::OperatorsAddedEvent),
+            #[prost(message, tag = "10")]
+            OperatorsRemoved(crate::proto_def::OperatorsRemovedEvent),
+            #[prost(message, tag = "11")]
+            PausedToggled(crate::proto_def::PausedToggledEvent),
+            #[prost(message, tag = "12")]
+            RaydiumLiquidityLocked(crate::proto_def::RaydiumLiquidityLockedEvent),
+            #[prost(message, tag = "13")]
+            RaydiumPoolCreated(crate::proto_def::RaydiumPoolCreatedEvent),
+            #[prost(message, tag = "14")]
+            RaydiumRandomPoolCreated(crate::proto_def::RaydiumRandomPoolCreatedEvent),
+            #[prost(message, tag = "15")]
+            SwapSolForTokensOnRaydium(crate::proto_def::SwapSolForTokensOnRaydiumEvent),
+            #[prost(message, tag = "16")]
+            SwapTokensForSolOnRaydium(crate::proto_def::SwapTokensForSolOnRaydiumEvent),
+            #[prost(message, tag = "17")]
+            TokenBought(crate::proto_def::TokenBoughtEvent),
+            #[prost(message, tag = "18")]
+            TokenCreated(crate::proto_def::TokenCreatedEvent),
+            #[prost(message, tag = "19")]
+            TokenCreatedFallback(crate::proto_def::TokenCreatedFallbackEvent),
+            #[prost(message, tag = "20")]
+            TokenGraduated(crate::proto_def::TokenGraduatedEvent),
+            #[prost(message, tag = "21")]
+            TokenSold(crate::proto_def::TokenSoldEvent),
+            #[prost(message, tag = "22")]
+            TradingFeesCollected(crate::proto_def::TradingFeesCollectedEvent),
+            #[prost(message, tag = "23")]
+            TradingFeesSplit(crate::proto_def::TradingFeesSplitEvent),
+        }
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramEvents {
+        #[prost(message, repeated, tag = "1")]
+        pub events: ::prost::alloc::vec::Vec<ProgramEvent>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramIxOrEvent {
+        #[prost(oneof = "program_ix_or_event::IxOrEventOneof", tags = "1, 2")]
+        pub ix_or_event_oneof: ::core::option::Option<program_ix_or_event::IxOrEventOneof>,
+    }
+
+    /// Nested message and enum types in `ProgramIxOrEvent`.
+    pub mod program_ix_or_event {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum IxOrEventOneof {
+            #[prost(message, tag = "1")]
+            Ix(crate::proto_def::ProgramIxs),
+            #[prost(message, tag = "2")]
+            CpiEvent(super::ProgramEvent),
+        }
+    }
+
+    macro_rules! impl_name {
+        ($($msg:ident),*) => {
+            $(impl ::prost::Name for $msg {
+                const NAME: &'static str = stringify!($msg);
+                const PACKAGE: &'static str = "vixen.parser.boop";
+            })*
+        };
+    }
+
+    impl_name!(ProgramEvent, ProgramEvents, ProgramIxOrEvent);
+}
+
+// #[cfg(feature = "proto")]
+mod proto_parser {
+    use yellowstone_vixen_core::proto::ParseProto;
+
+    use super::{
+        proto_def, BoopProgramEvent, BoopProgramIxOrEvent, InstructionEventParser, LogEventParser,
+    };
+    use crate::proto_helpers::proto_types_parsers::IntoProto;
+
+    impl IntoProto<proto_def::ProgramEvent> for BoopProgramEvent {
+        fn into_proto(self) -> proto_def::ProgramEvent {
+            match self {
+                BoopProgramEvent::AuthorityTransferCancelled(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::AuthorityTransferCancelled(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::AuthorityTransferCompleted(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::AuthorityTransferCompleted(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::AuthorityTransferInitiated(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::AuthorityTransferInitiated(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::BondingCurveDeployed(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::BondingCurveDeployed(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::BondingCurveDeployedFallback(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::BondingCurveDeployedFallback(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::BondingCurveVaultClosed(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::BondingCurveVaultClosed(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::ConfigUpdated(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::ConfigUpdated(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::LiquidityDepositedIntoRaydium(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::LiquidityDepositedIntoRaydium(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::OperatorsAdded(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::OperatorsAdded(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::OperatorsRemoved(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::OperatorsRemoved(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::PausedToggled(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::PausedToggled(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::RaydiumLiquidityLocked(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::RaydiumLiquidityLocked(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::RaydiumPoolCreated(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::RaydiumPoolCreated(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::RaydiumRandomPoolCreated(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::RaydiumRandomPoolCreated(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::SwapSolForTokensOnRaydium(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::SwapSolForTokensOnRaydium(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::SwapTokensForSolOnRaydium(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::SwapTokensForSolOnRaydium(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                BoopProgramEvent::TokenBought(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::TokenBought(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::TokenCreated(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::TokenCreated(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::TokenCreatedFallback(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::TokenCreatedFallback(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::TokenGraduated(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::TokenGraduated(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::TokenSold(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::TokenSold(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::TradingFeesCollected(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::TradingFeesCollected(
+                        event.into_proto(),
+                    )),
+                },
+                BoopProgramEvent::TradingFeesSplit(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::TradingFeesSplit(
+                        event.into_proto(),
+                    )),
+                },
+            }
+        }
+    }
+
+    impl ParseProto for LogEventParser {
+        type Message = proto_def::ProgramEvents;
+
+        fn output_into_message(value: Self::Output) -> Self::Message {
+            #[cfg(not(feature = "shared-data"))]
+            let events = value;
+
+            #[cfg(feature = "shared-data")]
+            let events = value.parsed_ix;
+
+            proto_def::ProgramEvents {
+                events: events.into_iter().map(IntoProto::into_proto).collect(),
+            }
+        }
+    }
+
+    impl IntoProto<proto_def::ProgramIxOrEvent> for BoopProgramIxOrEvent {
+        fn into_proto(self) -> proto_def::ProgramIxOrEvent {
+            match self {
+                BoopProgramIxOrEvent::Ix(ix) => proto_def::ProgramIxOrEvent {
+                    ix_or_event_oneof: Some(proto_def::program_ix_or_event::IxOrEventOneof::Ix(
+                        ix.into_proto(),
+                    )),
+                },
+                BoopProgramIxOrEvent::CpiEvent(event) => proto_def::ProgramIxOrEvent {
+                    ix_or_event_oneof: Some(
+                        proto_def::program_ix_or_event::IxOrEventOneof::CpiEvent(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+            }
+        }
+    }
+
+    impl ParseProto for InstructionEventParser {
+        type Message = proto_def::ProgramIxOrEvent;
+
+        fn output_into_message(value: Self::Output) -> Self::Message {
+            #[cfg(not(feature = "shared-data"))]
+            return value.into_proto();
+
+            #[cfg(feature = "shared-data")]
+            value.parsed_ix.into_proto()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yellowstone_vixen_core::anchor::EVENT_IX_TAG;
+
+    use super::*;
+
+    // The base58 instruction data of an `emit_cpi!` self-invocation
+    const CPI_EVENT: &str = "JFDzKqM9t6Em15LqVrKLtv3rexN9aXMK69v8THE6fDGwd7mAdNj3qBBjWhPMFeBuiMnvU4C5Uz39\
+         7m8hgs3rRQhDptU6JjKB3cXoUgRBFyt8LTuVoPks9rGRRLJrwn1fYsei1pp1tSbZ5JN7PpYSiSLc\
+         j2TLjCFfj14wzQ6c6UZY4vxdwWfww7YCZM";
+
+    #[test]
+    fn test_unpack_cpi_event() {
+        let data = bs58::decode(CPI_EVENT).into_vec().unwrap();
+        assert_eq!(data[..8], EVENT_IX_TAG);
+
+        let event =
+            BoopProgramEvent::try_unpack_cpi(&[EVENT_AUTHORITY.to_bytes().into()], &data[8..]);
+        let Ok(BoopProgramEvent::TokenBought(event)) = event else {
+            panic!("Invalid event");
+        };
+
+        assert_eq!(
+            event.mint.to_string(),
+            "6B8ix29sTWnSkXGgfPkiztrkDXTwLSKk4HVcFbLkx8gG"
+        );
+        assert_eq!(
+            event.buyer.to_string(),
+            "DmRt5SexaumPPLeYDxS727cVV6iTa69jKhhd6PL2pANB"
+        );
+        assert_eq!(event.recipient, event.buyer);
+        assert_eq!(event.amount_in, 1_000_000_000);
+        assert_eq!(event.amount_out, 34_482_758_620_689);
+        assert_eq!(event.swap_fee, 10_000_000);
+
+        assert!(BoopProgramEvent::try_unpack_cpi(&[ID.to_bytes().into()], &data[8..]).is_err());
+    }
+
+    #[test]
+    fn test_events_from_logs() {
+        // The base64 `Program data:` record of an `emit!` event
+        const LOG_EVENT: &str =
+            "R1nefNfA5opM4vm13/QIetzRt/\
+             DP+GiPSoQ0RdR3yHW41315FRxaiQDKmjsAAAAAERaUpFwfAACAlpgAAAAAAL2t7mvxND2GvwP4Gd9i6hhV9Rm/\
+             fBT+c6+CsXPA1DJYva3ua/E0PYa/A/gZ32LqGFX1Gb98FP5zr4Kxc8DUMlg=";
+        // A record with an unknown discriminator
+        const UNKNOWN_EVENT: &str =
+            "AQIDBAUGBwgAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYn";
+
+        let logs = [
+            format!("Program {ID} invoke [1]"),
+            format!("Program data: {LOG_EVENT}"),
+            format!("Program data: {UNKNOWN_EVENT}"),
+            format!("Program {ID} success"),
+        ];
+
+        let events = BoopProgramEvent::from_logs(&logs);
+        let [BoopProgramEvent::TokenBought(event)] = events.as_slice() else {
+            panic!("Invalid events: {events:?}");
+        };
+
+        assert_eq!(
+            event.mint.to_string(),
+            "6B8ix29sTWnSkXGgfPkiztrkDXTwLSKk4HVcFbLkx8gG"
+        );
+        assert_eq!(event.amount_in, 1_000_000_000);
+        assert_eq!(event.amount_out, 34_482_758_620_689);
+        assert_eq!(event.swap_fee, 10_000_000);
+        assert_eq!(event.buyer, event.recipient);
+    }
+}
@@ -0,0 +1,290 @@
+//! Benchmarks for decomposing transaction updates into instruction updates.
+//!
+//! The workload is generated to match the shape of mainnet traffic: mostly
+//! small transactions with a compute budget preamble and a single program
+//! call, a large share of aggregator swaps using address lookup tables and
+//! deep CPI trees, and a tail of heavy multi-hop transactions with long logs.
+//! No recorded mainnet fixtures are included: a representative sample is too
+//! large to check in, and generating the workload keeps the benchmark
+//! reproducible without depending on binary captures.
+//!
+//! Each transaction is dispatched to a varying number of instruction
+//! pipelines, comparing decomposing it once per pipeline against decomposing
+//! it once and sharing the result between pipelines.
+
+use std::{hint::black_box, sync::Arc};
+
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion, Throughput};
+use yellowstone_grpc_proto::{
+    geyser::SubscribeUpdateTransactionInfo,
+    prelude::MessageHeader,
+    solana::storage::confirmed_block::{
+        CompiledInstruction, InnerInstruction, InnerInstructions, Message,
+        MessageAddressTableLookup, TokenBalance, Transaction, TransactionStatusMeta,
+    },
+};
+use yellowstone_vixen_core::{instruction::InstructionUpdate, KeyBytes, TransactionUpdate};
+
+const WORKLOAD_SIZE: usize = 1_000;
+
+/// A small deterministic PRNG so the workload is identical between runs.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn range(&mut self, lo: usize, hi: usize) -> usize {
+        lo + usize::try_from(self.next() % (hi - lo) as u64).unwrap()
+    }
+
+    fn bytes(&mut self, len: usize) -> Vec<u8> {
+        (0..len).map(|_| self.next().to_le_bytes()[0]).collect()
+    }
+}
+
+/// The shape of a generated transaction.
+struct Shape {
+    static_keys: usize,
+    lookup_keys: usize,
+    outer: usize,
+    inner_per_outer: (usize, usize),
+    accounts_per_ix: (usize, usize),
+    data_len: (usize, usize),
+    extra_log_lines: usize,
+}
+
+const SIMPLE: Shape = Shape {
+    static_keys: 8,
+    lookup_keys: 0,
+    outer: 3,
+    inner_per_outer: (0, 3),
+    accounts_per_ix: (1, 8),
+    data_len: (4, 40),
+    extra_log_lines: 4,
+};
+
+const SWAP: Shape = Shape {
+    static_keys: 16,
+    lookup_keys: 24,
+    outer: 4,
+    inner_per_outer: (2, 16),
+    accounts_per_ix: (4, 24),
+    data_len: (8, 120),
+    extra_log_lines: 20,
+};
+
+const HEAVY: Shape = Shape {
+    static_keys: 24,
+    lookup_keys: 64,
+    outer: 8,
+    inner_per_outer: (8, 40),
+    accounts_per_ix: (8, 40),
+    data_len: (16, 400),
+    extra_log_lines: 80,
+};
+
+fn key(rng: &mut XorShift) -> Vec<u8> { rng.bytes(32) }
+
+fn generate(rng: &mut XorShift, shape: &Shape) -> TransactionUpdate {
+    let num_keys = shape.static_keys + shape.lookup_keys;
+    let account_keys: Vec<_> = (0..shape.static_keys).map(|_| key(rng)).collect();
+    let loaded_writable: Vec<_> = (0..shape.lookup_keys / 2).map(|_| key(rng)).collect();
+    let loaded_readonly: Vec<_> = (shape.lookup_keys / 2..shape.lookup_keys)
+        .map(|_| key(rng))
+        .collect();
+    let address_table_lookups = if shape.lookup_keys == 0 {
+        vec![]
+    } else {
+        vec![MessageAddressTableLookup {
+            account_key: key(rng),
+            writable_indexes: (0..loaded_writable.len())
+                .map(|i| u8::try_from(i).unwrap())
+                .collect(),
+            readonly_indexes: (0..loaded_readonly.len())
+                .map(|i| u8::try_from(i + 128).unwrap())
+                .collect(),
+        }]
+    };
+
+    let ix = |rng: &mut XorShift| {
+        let accounts = (0..rng.range(shape.accounts_per_ix.0, shape.accounts_per_ix.1))
+            .map(|_| u8::try_from(rng.range(0, num_keys)).unwrap())
+            .collect::<Vec<_>>();
+        let data_len = rng.range(shape.data_len.0, shape.data_len.1);
+        let data = rng.bytes(data_len);
+        let program_id_index = u32::try_from(rng.range(1, shape.static_keys)).unwrap();
+
+        (program_id_index, accounts, data)
+    };
+
+    let instructions: Vec<_> = (0..shape.outer)
+        .map(|_| {
+            let (program_id_index, accounts, data) = ix(rng);
+            CompiledInstruction {
+                program_id_index,
+                accounts,
+                data,
+            }
+        })
+        .collect();
+    let inner_instructions: Vec<_> = (0..shape.outer)
+        .map(|index| InnerInstructions {
+            index: u32::try_from(index).unwrap(),
+            instructions: (0..rng.range(shape.inner_per_outer.0, shape.inner_per_outer.1))
+                .map(|_| {
+                    let (program_id_index, accounts, data) = ix(rng);
+                    InnerInstruction {
+                        program_id_index,
+                        accounts,
+                        data,
+                        stack_height: Some(u32::try_from(rng.range(2, 5)).unwrap()),
+                    }
+                })
+                .collect(),
+        })
+        .collect();
+
+    let program = |i: u32| KeyBytes::<32>::try_from(&*account_keys[i as usize]).unwrap();
+    let mut log_messages = vec![];
+    for ix in &instructions {
+        let p = program(ix.program_id_index);
+        log_messages.push(format!("Program {p} invoke [1]"));
+        log_messages.extend(
+            (0..shape.extra_log_lines / shape.outer)
+                .map(|i| format!("Program log: Instruction: step {i} of the program")),
+        );
+        log_messages.push(format!(
+            "Program {p} consumed 21000 of 200000 compute units"
+        ));
+        log_messages.push(format!("Program {p} success"));
+    }
+
+    let token_balances = |rng: &mut XorShift| {
+        (0..rng.range(0, 6))
+            .map(|i| TokenBalance {
+                account_index: u32::try_from(i).unwrap(),
+                mint: KeyBytes::<32>::try_from(&*key(rng)).unwrap().to_string(),
+                ..TokenBalance::default()
+            })
+            .collect::<Vec<_>>()
+    };
+
+    TransactionUpdate {
+        transaction: Some(SubscribeUpdateTransactionInfo {
+            signature: rng.bytes(64),
+            is_vote: false,
+            transaction: Some(Transaction {
+                signatures: vec![rng.bytes(64)],
+                message: Some(Message {
+                    header: Some(MessageHeader {
+                        num_required_signatures: 1,
+                        num_readonly_signed_accounts: 0,
+                        num_readonly_unsigned_accounts: 3,
+                    }),
+                    account_keys: account_keys.clone(),
+                    recent_blockhash: rng.bytes(32),
+                    instructions,
+                    versioned: shape.lookup_keys > 0,
+                    address_table_lookups,
+                }),
+            }),
+            meta: Some(TransactionStatusMeta {
+                fee: 5000,
+                pre_balances: vec![1_000_000_000; num_keys],
+                post_balances: vec![999_995_000; num_keys],
+                inner_instructions,
+                log_messages,
+                pre_token_balances: token_balances(rng),
+                post_token_balances: token_balances(rng),
+                loaded_writable_addresses: loaded_writable,
+                loaded_readonly_addresses: loaded_readonly,
+                compute_units_consumed: Some(84_000),
+                ..TransactionStatusMeta::default()
+            }),
+            index: rng.next() % 4000,
+        }),
+        slot: 300_000_000,
+    }
+}
+
+fn workload() -> Vec<TransactionUpdate> {
+    let mut rng = XorShift(0x5eed_cafe_f00d_d00d);
+
+    (0..WORKLOAD_SIZE)
+        .map(|_| {
+            let shape = match rng.range(0, 100) {
+                0..65 => &SIMPLE,
+                65..90 => &SWAP,
+                _ => &HEAVY,
+            };
+
+            generate(&mut rng, shape)
+        })
+        .collect()
+}
+
+/// Touch every instruction the way a parser would, so both variants do the
+/// same amount of downstream work.
+fn visit(ixs: &[InstructionUpdate]) -> usize {
+    ixs.iter()
+        .flat_map(InstructionUpdate::visit_all)
+        .map(|(_, ix)| ix.data.first().copied().map_or(0, usize::from) + ix.accounts.len())
+        .sum()
+}
+
+fn bench_decompose(c: &mut Criterion) {
+    let txns = workload();
+    let mut group = c.benchmark_group("decompose");
+    group.throughput(Throughput::Elements(txns.len() as u64));
+
+    for pipelines in [1, 4, 12] {
+        group.bench_with_input(
+            BenchmarkId::new("per_pipeline", pipelines),
+            &pipelines,
+            |b, &n| {
+                b.iter(|| {
+                    for txn in &txns {
+                        for _ in 0..n {
+                            let ixs = InstructionUpdate::parse_from_txn(black_box(txn)).unwrap();
+                            black_box(visit(&ixs));
+                        }
+                    }
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("shared", pipelines),
+            &pipelines,
+            |b, &n| {
+                // The runtime takes ownership of the update, so the instructions
+                // can borrow from it without copying it first
+                b.iter_batched(
+                    || txns.iter().cloned().map(Arc::new).collect::<Vec<_>>(),
+                    |txns| {
+                        for txn in &txns {
+                            let ixs =
+                                InstructionUpdate::parse_from_shared_txn(black_box(txn)).unwrap();
+                            for _ in 0..n {
+                                black_box(visit(&ixs));
+                            }
+                        }
+
+                        txns
+                    },
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_decompose);
+criterion_main!(benches);
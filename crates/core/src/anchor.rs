@@ -0,0 +1,8 @@
+//! Constants for decoding data emitted by programs built with Anchor.
+
+/// The instruction discriminator Anchor prefixes to the self-invoked
+/// instructions `emit_cpi!` uses to record events.
+///
+/// The remaining instruction data is the 8-byte event discriminator
+/// followed by the Borsh-serialized event.
+pub const EVENT_IX_TAG: [u8; 8] = [228, 69, 165, 46, 81, 203, 154, 29];
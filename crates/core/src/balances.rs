@@ -0,0 +1,383 @@
+//! Helpers for computing the SOL and token balance changes of a transaction.
+
+use std::num::ParseIntError;
+
+use yellowstone_grpc_proto::{
+    geyser::SubscribeUpdateTransactionInfo,
+    solana::storage::confirmed_block::{
+        Message, TokenBalance, Transaction, TransactionStatusMeta, UiTokenAmount,
+    },
+};
+
+use crate::{
+    instruction::{AccountKeyError, InstructionShared, Missing, ParseError},
+    KeyFromStrError, Pubkey, TransactionUpdate,
+};
+
+/// Errors that can occur when computing balance changes.
+#[derive(Debug, thiserror::Error)]
+pub enum BalanceError {
+    /// A balance referenced an invalid account.
+    #[error("Invalid account for balance")]
+    AccountKey(#[from] AccountKeyError),
+    /// A token balance contained an invalid mint, owner or program ID.
+    #[error("Invalid pubkey {0:?} in token balance")]
+    Pubkey(String, #[source] KeyFromStrError),
+    /// A token balance contained an invalid raw amount.
+    #[error("Invalid token amount {0:?}")]
+    Amount(String, #[source] ParseIntError),
+    /// A token balance contained an out-of-range number of decimals.
+    #[error("Invalid token decimals {0}")]
+    Decimals(u32),
+}
+
+/// The change in the SOL balance of an account over a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SolBalanceChange {
+    /// The account whose balance changed.
+    pub account: Pubkey,
+    /// The balance of the account in lamports before the transaction.
+    pub pre: u64,
+    /// The balance of the account in lamports after the transaction.
+    pub post: u64,
+}
+
+impl SolBalanceChange {
+    /// The net change in lamports.
+    #[inline]
+    #[must_use]
+    pub fn delta(&self) -> i128 { i128::from(self.post) - i128::from(self.pre) }
+}
+
+/// The change in the balance of a token account over a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TokenBalanceChange {
+    /// The token account whose balance changed.
+    pub account: Pubkey,
+    /// The mint of the token account.
+    pub mint: Pubkey,
+    /// The owner of the token account, if reported by the node.
+    pub owner: Option<Pubkey>,
+    /// The token program owning the token account, if reported by the node.
+    pub program_id: Option<Pubkey>,
+    /// The number of decimals of the mint.
+    pub decimals: u8,
+    /// The raw balance of the account before the transaction, or `None` if
+    /// it was created by the transaction.
+    pub pre: Option<u64>,
+    /// The raw balance of the account after the transaction, or `None` if it
+    /// was closed by the transaction.
+    pub post: Option<u64>,
+}
+
+impl TokenBalanceChange {
+    /// The net change in raw token units.  Missing balances count as zero.
+    #[inline]
+    #[must_use]
+    pub fn delta(&self) -> i128 {
+        i128::from(self.post.unwrap_or(0)) - i128::from(self.pre.unwrap_or(0))
+    }
+
+    /// Returns true if the token account was created by the transaction.
+    #[inline]
+    #[must_use]
+    pub fn is_created(&self) -> bool { self.pre.is_none() }
+
+    /// Returns true if the token account was closed by the transaction.
+    #[inline]
+    #[must_use]
+    pub fn is_closed(&self) -> bool { self.post.is_none() }
+
+    /// The balance before the transaction, adjusted for the mint decimals.
+    #[inline]
+    #[must_use]
+    pub fn ui_pre(&self) -> Option<f64> { self.pre.map(|a| self.ui_amount(a.into())) }
+
+    /// The balance after the transaction, adjusted for the mint decimals.
+    #[inline]
+    #[must_use]
+    pub fn ui_post(&self) -> Option<f64> { self.post.map(|a| self.ui_amount(a.into())) }
+
+    /// The net change, adjusted for the mint decimals.
+    #[inline]
+    #[must_use]
+    pub fn ui_delta(&self) -> f64 { self.ui_amount(self.delta()) }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn ui_amount(&self, raw: i128) -> f64 { raw as f64 / 10_f64.powi(self.decimals.into()) }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Keys<'a>([&'a [Vec<u8>]; 3]);
+
+impl Keys<'_> {
+    fn get(&self, idx: usize) -> Result<Pubkey, AccountKeyError> {
+        let mut i = idx;
+        for k in self.0 {
+            match k.get(i) {
+                Some(k) => return Ok(k.as_slice().try_into()?),
+                None => i -= k.len(),
+            }
+        }
+
+        Err(AccountKeyError::InvalidIndex(idx))
+    }
+}
+
+/// The balances of the accounts of a transaction before and after its
+/// execution, from which the balance changes of each account are computed.
+///
+/// This only borrows the transaction data, so constructing it is free and
+/// changes are only computed when requested.
+#[derive(Debug, Clone, Copy)]
+pub struct BalanceChanges<'a> {
+    keys: Keys<'a>,
+    pre_balances: &'a [u64],
+    post_balances: &'a [u64],
+    pre_token_balances: &'a [TokenBalance],
+    post_token_balances: &'a [TokenBalance],
+}
+
+impl<'a> BalanceChanges<'a> {
+    /// Get the balances of the transaction an instruction belongs to.
+    #[must_use]
+    pub fn from_shared(shared: &'a InstructionShared) -> Self {
+        let accounts = shared.accounts();
+
+        Self {
+            keys: Keys([
+                accounts.static_keys,
+                accounts.dynamic_rw,
+                accounts.dynamic_ro,
+            ]),
+            pre_balances: shared.pre_balances(),
+            post_balances: shared.post_balances(),
+            pre_token_balances: shared.pre_token_balances(),
+            post_token_balances: shared.post_token_balances(),
+        }
+    }
+
+    /// Get the balances of a transaction update.
+    ///
+    /// # Errors
+    /// Returns an error if the transaction update is missing its message or
+    /// status metadata.
+    pub fn from_txn(txn: &'a TransactionUpdate) -> Result<Self, ParseError> {
+        let SubscribeUpdateTransactionInfo {
+            transaction, meta, ..
+        } = txn.transaction.as_ref().ok_or(Missing::TransactionInfo)?;
+        let Transaction { message, .. } = transaction.as_ref().ok_or(Missing::Transaction)?;
+        let Message { account_keys, .. } = message.as_ref().ok_or(Missing::TransactionMessage)?;
+        let TransactionStatusMeta {
+            pre_balances,
+            post_balances,
+            pre_token_balances,
+            post_token_balances,
+            loaded_writable_addresses,
+            loaded_readonly_addresses,
+            ..
+        } = meta.as_ref().ok_or(Missing::TransactionMeta)?;
+
+        Ok(Self {
+            keys: Keys([
+                account_keys,
+                loaded_writable_addresses,
+                loaded_readonly_addresses,
+            ]),
+            pre_balances,
+            post_balances,
+            pre_token_balances,
+            post_token_balances,
+        })
+    }
+
+    /// Compute the change in the SOL balance of every account whose balance
+    /// changed, in transaction order.
+    ///
+    /// # Errors
+    /// Returns an error if the balances do not line up with the account keys
+    /// of the transaction.
+    pub fn sol(&self) -> Result<Vec<SolBalanceChange>, BalanceError> {
+        self.pre_balances
+            .iter()
+            .zip(self.post_balances)
+            .enumerate()
+            .filter(|(_, (pre, post))| pre != post)
+            .map(|(i, (&pre, &post))| {
+                Ok(SolBalanceChange {
+                    account: self.keys.get(i)?,
+                    pre,
+                    post,
+                })
+            })
+            .collect()
+    }
+
+    /// Compute the change in the balance of every token account whose
+    /// balance changed, including token accounts created or closed by the
+    /// transaction.
+    ///
+    /// # Errors
+    /// Returns an error if a token balance references an invalid account or
+    /// contains invalid data.
+    pub fn tokens(&self) -> Result<Vec<TokenBalanceChange>, BalanceError> {
+        let find = |balances: &'a [TokenBalance], idx: u32| {
+            balances.iter().find(|b| b.account_index == idx)
+        };
+
+        let updated = self.post_token_balances.iter().map(|post| {
+            (
+                find(self.pre_token_balances, post.account_index),
+                Some(post),
+            )
+        });
+        let closed = self
+            .pre_token_balances
+            .iter()
+            .filter(|pre| find(self.post_token_balances, pre.account_index).is_none())
+            .map(|pre| (Some(pre), None));
+
+        updated
+            .chain(closed)
+            .filter_map(|(pre, post)| self.token_change(pre, post).transpose())
+            .collect()
+    }
+
+    fn token_change(
+        &self,
+        pre: Option<&TokenBalance>,
+        post: Option<&TokenBalance>,
+    ) -> Result<Option<TokenBalanceChange>, BalanceError> {
+        let Some(balance) = post.or(pre) else {
+            return Ok(None);
+        };
+        let TokenBalance {
+            account_index,
+            ref mint,
+            ref ui_token_amount,
+            ref owner,
+            ref program_id,
+        } = *balance;
+
+        let pre = pre.map(raw_amount).transpose()?;
+        let post = post.map(raw_amount).transpose()?;
+        if pre == post {
+            return Ok(None);
+        }
+
+        let decimals = ui_token_amount.as_ref().map_or(0, |a| a.decimals);
+
+        Ok(Some(TokenBalanceChange {
+            account: self
+                .keys
+                .get(account_index.try_into().map_err(AccountKeyError::from)?)?,
+            mint: parse_pubkey(mint)?,
+            owner: parse_opt_pubkey(owner)?,
+            program_id: parse_opt_pubkey(program_id)?,
+            decimals: decimals
+                .try_into()
+                .map_err(|_| BalanceError::Decimals(decimals))?,
+            pre,
+            post,
+        }))
+    }
+}
+
+fn parse_pubkey(s: &str) -> Result<Pubkey, BalanceError> {
+    s.parse().map_err(|e| BalanceError::Pubkey(s.to_owned(), e))
+}
+
+fn parse_opt_pubkey(s: &str) -> Result<Option<Pubkey>, BalanceError> {
+    Some(s)
+        .filter(|s| !s.is_empty())
+        .map(parse_pubkey)
+        .transpose()
+}
+
+fn raw_amount(balance: &TokenBalance) -> Result<u64, BalanceError> {
+    let Some(UiTokenAmount { amount, .. }) = &balance.ui_token_amount else {
+        return Ok(0);
+    };
+
+    amount
+        .parse()
+        .map_err(|e| BalanceError::Amount(amount.clone(), e))
+}
+
+impl InstructionShared {
+    /// Get the balances of this transaction, from which its SOL and token
+    /// balance changes can be computed.
+    #[inline]
+    #[must_use]
+    pub fn balance_changes(&self) -> BalanceChanges<'_> { BalanceChanges::from_shared(self) }
+}
+
+#[cfg(test)]
+mod tests {
+    use yellowstone_grpc_proto::{
+        geyser::SubscribeUpdateTransactionInfo,
+        solana::storage::confirmed_block::{
+            Message, TokenBalance, Transaction, TransactionStatusMeta, UiTokenAmount,
+        },
+    };
+
+    use super::{BalanceChanges, SolBalanceChange};
+    use crate::{KeyBytes, TransactionUpdate};
+
+    fn token(account_index: u32, amount: u64) -> TokenBalance {
+        TokenBalance {
+            account_index,
+            mint: KeyBytes::<32>([9; 32]).to_string(),
+            ui_token_amount: Some(UiTokenAmount {
+                decimals: 2,
+                amount: amount.to_string(),
+                ..UiTokenAmount::default()
+            }),
+            owner: KeyBytes::<32>([0; 32]).to_string(),
+            program_id: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_balance_changes() {
+        let txn = TransactionUpdate {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                transaction: Some(Transaction {
+                    message: Some(Message {
+                        account_keys: (0..3).map(|b| vec![b; 32]).collect(),
+                        ..Message::default()
+                    }),
+                    ..Transaction::default()
+                }),
+                meta: Some(TransactionStatusMeta {
+                    pre_balances: vec![100, 5, 5, 7],
+                    post_balances: vec![90, 5, 0, 9],
+                    pre_token_balances: vec![token(1, 150), token(2, 10)],
+                    post_token_balances: vec![token(1, 100), token(3, 25)],
+                    loaded_readonly_addresses: vec![vec![3; 32]],
+                    ..TransactionStatusMeta::default()
+                }),
+                ..SubscribeUpdateTransactionInfo::default()
+            }),
+            slot: 1,
+        };
+        let balances = BalanceChanges::from_txn(&txn).unwrap();
+
+        let sol = balances.sol().unwrap();
+        assert_eq!(
+            sol.iter().map(SolBalanceChange::delta).collect::<Vec<_>>(),
+            [-10, -5, 2]
+        );
+        assert_eq!(sol[2].account, KeyBytes([3; 32]));
+
+        let tokens = balances.tokens().unwrap();
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[0].account, KeyBytes([1; 32]));
+        assert_eq!(tokens[0].delta(), -50);
+        assert!((tokens[0].ui_delta() + 0.5).abs() < f64::EPSILON);
+        assert_eq!(tokens[0].owner, Some(KeyBytes([0; 32])));
+        assert_eq!(tokens[0].program_id, None);
+        assert!(tokens[1].is_created() && tokens[1].delta() == 25);
+        assert!(tokens[2].is_closed() && tokens[2].delta() == -10);
+    }
+}
@@ -1,17 +1,30 @@
 //! Helpers for parsing transaction updates into instructions.
 
-use std::{collections::VecDeque, sync::Arc};
+use std::{
+    collections::VecDeque,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::{Deref, Range},
+    sync::Arc,
+};
 
+use base64::Engine;
+use serde::{Deserialize, Serialize};
 use yellowstone_grpc_proto::{
     geyser::SubscribeUpdateTransactionInfo,
     prelude::MessageHeader,
     solana::storage::confirmed_block::{
-        CompiledInstruction, InnerInstruction, InnerInstructions, Message, Reward, TokenBalance,
-        Transaction, TransactionError, TransactionStatusMeta,
+        CompiledInstruction, InnerInstruction, InnerInstructions, Message,
+        MessageAddressTableLookup, ReturnData as RawReturnData, Reward, TokenBalance, Transaction,
+        TransactionError, TransactionStatusMeta,
     },
 };
 
-use crate::{Pubkey, TransactionUpdate};
+use crate::{
+    logs::{self, InstructionLogs, LogLine},
+    txn_error::TxnError,
+    ProgramErrorParser, Pubkey, Signature, TransactionUpdate,
+};
 
 /// Errors that can occur when parsing a transaction update into instructions.
 #[derive(Debug, Clone, Copy, thiserror::Error)]
@@ -25,6 +38,9 @@ pub enum ParseError {
     /// An error occurred while parsing an account key.
     #[error("Invalid account key in transaction data")]
     AccountKey(#[from] AccountKeyError),
+    /// The transaction signature was not 64 bytes long.
+    #[error("Invalid transaction signature")]
+    Signature(#[source] std::array::TryFromSliceError),
 }
 
 /// A required field that was missing from the transaction update.
@@ -61,40 +77,201 @@ impl From<Missing> for ParseError {
 }
 
 /// Shared data between all instructions in a transaction.
+///
+/// The lists of a transaction, such as its logs and balances, are borrowed
+/// from the transaction update the instructions were parsed from rather than
+/// copied out of it.
 #[derive(Debug, Default)]
 pub struct InstructionShared {
     /// The slot in which the transaction was processed.
     pub slot: u64,
     /// The signature of the transaction.
-    pub signature: Vec<u8>,
+    pub signature: Signature,
     /// Whether the transaction is a vote transaction.
     pub is_vote: bool,
     /// The index of the transaction in the block.
     pub txn_index: u64,
-    /// If the transaction failed, the error that occurred.
-    pub err: Option<TransactionError>,
+    /// The decoded form of [`err`](Self::err), or `None` if the transaction
+    /// succeeded or its error could not be decoded.
+    pub txn_error: Option<TxnError>,
     /// The fee paid by the transaction in lamports.
     pub fee: u64,
+    /// Whether the runtime truncated the log messages, in which case
+    /// instructions invoked after the truncation have no attributed logs.
+    pub logs_truncated: bool,
+    /// The number of compute units consumed by the transaction.
+    pub compute_units_consumed: Option<u64>,
+    /// The signer and writable flags and the origin of every account in
+    /// [`accounts`](Self::accounts), in transaction order.
+    ///
+    /// If the loaded addresses of the transaction do not line up with its
+    /// address table lookups, the loaded accounts are resolved with an
+    /// [`AccountSource::UnknownLookupTable`] origin.
+    pub account_metas: Vec<AccountMeta>,
+    /// The header of the transaction.
+    pub message_header: MessageHeader,
+    /// The return data of the transaction, as last set by a program via
+    /// `set_return_data`.
+    pub return_data: Option<ReturnData>,
+    /// The transaction update the instructions were parsed from.
+    txn: Arc<TransactionUpdate>,
+}
+
+impl InstructionShared {
+    /// The transaction update the instructions were parsed from.
+    #[inline]
+    #[must_use]
+    pub fn transaction(&self) -> &TransactionUpdate { &self.txn }
+
+    #[inline]
+    fn message(&self) -> Option<&Message> {
+        self.txn
+            .transaction
+            .as_ref()?
+            .transaction
+            .as_ref()?
+            .message
+            .as_ref()
+    }
+
+    #[inline]
+    fn meta(&self) -> Option<&TransactionStatusMeta> {
+        self.txn.transaction.as_ref()?.meta.as_ref()
+    }
+
+    /// If the transaction failed, the error that occurred.
+    #[inline]
+    #[must_use]
+    pub fn err(&self) -> Option<&TransactionError> { self.meta()?.err.as_ref() }
+
     /// The balances of the accounts before the transaction.
-    pub pre_balances: Vec<u64>,
+    #[inline]
+    #[must_use]
+    pub fn pre_balances(&self) -> &[u64] { self.meta().map_or(&[], |m| &m.pre_balances) }
+
     /// The balances of the accounts after the transaction.
-    pub post_balances: Vec<u64>,
+    #[inline]
+    #[must_use]
+    pub fn post_balances(&self) -> &[u64] { self.meta().map_or(&[], |m| &m.post_balances) }
+
     /// The token balances of the accounts before the transaction.
-    pub pre_token_balances: Vec<TokenBalance>,
+    #[inline]
+    #[must_use]
+    pub fn pre_token_balances(&self) -> &[TokenBalance] {
+        self.meta().map_or(&[], |m| &m.pre_token_balances)
+    }
+
     /// The token balances of the accounts after the transaction.
-    pub post_token_balances: Vec<TokenBalance>,
+    #[inline]
+    #[must_use]
+    pub fn post_token_balances(&self) -> &[TokenBalance] {
+        self.meta().map_or(&[], |m| &m.post_token_balances)
+    }
+
     /// The log messages produced during execution of the transaction.
-    pub log_messages: Vec<String>,
+    #[inline]
+    #[must_use]
+    pub fn log_messages(&self) -> &[String] { self.meta().map_or(&[], |m| &m.log_messages) }
+
     /// The rewards produced during execution of the transaction.
-    pub rewards: Vec<Reward>,
-    /// The number of compute units consumed by the transaction.
-    pub compute_units_consumed: Option<u64>,
+    #[inline]
+    #[must_use]
+    pub fn rewards(&self) -> &[Reward] { self.meta().map_or(&[], |m| &m.rewards) }
+
     /// The recent blockhash submitted with the transaction.
-    pub recent_blockhash: Vec<u8>,
+    #[inline]
+    #[must_use]
+    pub fn recent_blockhash(&self) -> &[u8] { self.message().map_or(&[], |m| &m.recent_blockhash) }
+
     /// The keys of the accounts involved in the transaction.
-    pub accounts: AccountKeys,
-    /// The header of the transaction.
-    pub message_header: MessageHeader,
+    #[must_use]
+    pub fn accounts(&self) -> AccountKeys<'_> {
+        let message = self.message();
+        let meta = self.meta();
+
+        AccountKeys {
+            static_keys: message.map_or(&[], |m| &m.account_keys),
+            dynamic_rw: meta.map_or(&[], |m| &m.loaded_writable_addresses),
+            dynamic_ro: meta.map_or(&[], |m| &m.loaded_readonly_addresses),
+            address_table_lookups: message.map_or(&[], |m| &m.address_table_lookups),
+        }
+    }
+
+    /// The account paying the transaction fee, which is always the first
+    /// account of the transaction.
+    #[inline]
+    #[must_use]
+    pub fn fee_payer(&self) -> Option<Pubkey> { self.accounts().get(0).ok() }
+
+    /// Iterate over the accounts that signed the transaction.
+    #[inline]
+    pub fn signers(&self) -> impl Iterator<Item = Pubkey> + '_ {
+        self.account_metas
+            .iter()
+            .filter(|m| m.is_signer)
+            .map(|m| m.pubkey)
+    }
+
+    /// Resolve the custom error this transaction failed with using the given
+    /// parser.
+    ///
+    /// Returns `None` unless the transaction failed with a custom error
+    /// raised by the parser's program.
+    #[must_use]
+    pub fn program_error<P: ProgramErrorParser>(&self, parser: &P) -> Option<P::Error> {
+        let (program, code) = self.txn_error.as_ref()?.custom_error()?;
+
+        if program? != parser.program_id() {
+            return None;
+        }
+
+        parser.parse_error(code)
+    }
+}
+
+/// Data returned by a program through `set_return_data`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReturnData {
+    /// The program that set the return data.
+    pub program_id: Pubkey,
+    /// The raw return data payload.
+    pub data: Vec<u8>,
+}
+
+impl TryFrom<RawReturnData> for ReturnData {
+    type Error = AccountKeyError;
+
+    fn try_from(value: RawReturnData) -> Result<Self, Self::Error> {
+        let RawReturnData { program_id, data } = value;
+
+        Ok(Self {
+            program_id: program_id.as_slice().try_into()?,
+            data,
+        })
+    }
+}
+
+impl ReturnData {
+    /// Parse a `Program return: <program_id> <base64 data>` log line.
+    ///
+    /// Returns `None` if the line is not a return data log or is malformed.
+    #[must_use]
+    pub fn from_log(line: &str) -> Option<Self> {
+        let LogLine::Return { program, data } = LogLine::parse(line) else {
+            return None;
+        };
+
+        Self::from_base64(program, data)
+    }
+
+    fn from_base64(program_id: Pubkey, data: &str) -> Option<Self> {
+        Some(Self {
+            program_id,
+            data: base64::engine::general_purpose::STANDARD
+                .decode(data)
+                .ok()?,
+        })
+    }
 }
 
 /// A parsed instruction from a transaction update.
@@ -103,24 +280,352 @@ pub struct InstructionUpdate {
     /// The program ID of the instruction.
     pub program: Pubkey,
     /// The accounts passed to the instruction.
-    pub accounts: Vec<Pubkey>,
+    pub accounts: SharedSlice<Pubkey>,
+    /// The transaction-level indices of the accounts passed to the
+    /// instruction.
+    pub account_indices: SharedSlice<u8>,
     /// The serialized binary instruction payload.
-    pub data: Vec<u8>,
+    pub data: SharedSlice<u8>,
     /// Shared data between all instructions in this transaction.
     pub shared: Arc<InstructionShared>,
     /// Inner instructions invoked by this instruction.
     pub inner: Vec<InstructionUpdate>,
+    /// The position of this instruction within its transaction.
+    pub path: InstructionPath,
+    /// The log output attributed to this instruction, or `None` if it
+    /// produced no `invoke` log line (such as a precompile) or the logs were
+    /// truncated before it was invoked.
+    pub logs: Option<InstructionLogs>,
+    /// The return data set by this instruction, if any.
+    ///
+    /// This is attributed from the `Program return:` lines in the transaction
+    /// logs, and is therefore unavailable if the logs were truncated before
+    /// this instruction set it.
+    pub return_data: Option<ReturnData>,
+}
+
+/// An immutable view into a buffer shared by every instruction of a
+/// transaction.
+///
+/// The accounts of all instructions in a transaction are resolved into a
+/// single buffer, and their account indices and data are borrowed from the
+/// transaction update itself, so building the instructions of a transaction
+/// does not allocate per instruction and cloning an instruction's fields is
+/// cheap.
+pub struct SharedSlice<T: 'static> {
+    buf: Arc<dyn Buffer<T>>,
+    /// The position of the viewed slice within `buf`
+    pos: (usize, usize),
+    range: Range<usize>,
+}
+
+/// Storage for the slices viewed by [`SharedSlice`]s.
+trait Buffer<T>: Send + Sync {
+    /// Get the slice at the given position.
+    fn slice(&self, pos: (usize, usize)) -> &[T];
+}
+
+impl<T: Send + Sync> Buffer<T> for Vec<T> {
+    #[inline]
+    fn slice(&self, _: (usize, usize)) -> &[T] { self }
+}
+
+/// A field of the compiled instructions of a transaction update, with
+/// positions of the form `(0, <outer_index>)` for outer instructions and
+/// `(<inner_group> + 1, <index>)` for inner instructions.
+struct CompiledField {
+    txn: Arc<TransactionUpdate>,
+    data: bool,
+}
+
+impl Buffer<u8> for CompiledField {
+    fn slice(&self, (group, index): (usize, usize)) -> &[u8] {
+        let info = self.txn.transaction.as_ref();
+        let (accounts, data) = if group == 0 {
+            info.and_then(|i| {
+                i.transaction
+                    .as_ref()?
+                    .message
+                    .as_ref()?
+                    .instructions
+                    .get(index)
+            })
+            .map(|i| (&*i.accounts, &*i.data))
+        } else {
+            info.and_then(|i| {
+                i.meta
+                    .as_ref()?
+                    .inner_instructions
+                    .get(group - 1)?
+                    .instructions
+                    .get(index)
+            })
+            .map(|i| (&*i.accounts, &*i.data))
+        }
+        .unwrap_or_default();
+
+        if self.data {
+            data
+        } else {
+            accounts
+        }
+    }
+}
+
+impl<T> SharedSlice<T> {
+    #[inline]
+    fn new(buf: &Arc<dyn Buffer<T>>, pos: (usize, usize), range: Range<usize>) -> Self {
+        debug_assert!(range.end <= buf.slice(pos).len());
+
+        Self {
+            buf: Arc::clone(buf),
+            pos,
+            range,
+        }
+    }
+
+    /// Get the contents of this view as a slice.
+    #[inline]
+    #[must_use]
+    pub fn as_slice(&self) -> &[T] { &self.buf.slice(self.pos)[self.range.clone()] }
+}
+
+impl<T> Clone for SharedSlice<T> {
+    #[inline]
+    fn clone(&self) -> Self { Self::new(&self.buf, self.pos, self.range.clone()) }
+}
+
+impl<T: Send + Sync> Default for SharedSlice<T> {
+    #[inline]
+    fn default() -> Self { Vec::new().into() }
+}
+
+impl<T> Deref for SharedSlice<T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] { self.as_slice() }
+}
+
+impl<T> AsRef<[T]> for SharedSlice<T> {
+    #[inline]
+    fn as_ref(&self) -> &[T] { self.as_slice() }
+}
+
+impl<T> std::borrow::Borrow<[T]> for SharedSlice<T> {
+    #[inline]
+    fn borrow(&self) -> &[T] { self.as_slice() }
+}
+
+impl<T: fmt::Debug> fmt::Debug for SharedSlice<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { self.as_slice().fmt(f) }
+}
+
+impl<T: PartialEq> PartialEq for SharedSlice<T> {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool { self.as_slice() == other.as_slice() }
+}
+
+impl<T: Eq> Eq for SharedSlice<T> {}
+
+impl<T: PartialEq> PartialEq<[T]> for SharedSlice<T> {
+    #[inline]
+    fn eq(&self, other: &[T]) -> bool { self.as_slice() == other }
+}
+
+impl<T: Hash> Hash for SharedSlice<T> {
+    #[inline]
+    fn hash<H: Hasher>(&self, state: &mut H) { self.as_slice().hash(state); }
+}
+
+impl<T: Send + Sync> From<Vec<T>> for SharedSlice<T> {
+    fn from(value: Vec<T>) -> Self {
+        let range = 0..value.len();
+
+        Self {
+            buf: Arc::new(value),
+            pos: (0, 0),
+            range,
+        }
+    }
+}
+
+impl<T: Send + Sync> FromIterator<T> for SharedSlice<T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        iter.into_iter().collect::<Vec<_>>().into()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SharedSlice<T> {
+    type IntoIter = std::slice::Iter<'a, T>;
+    type Item = &'a T;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter { self.as_slice().iter() }
+}
+
+/// The buffers viewed by the instructions of a transaction: the resolved
+/// accounts of every instruction, in the order the instructions are built,
+/// and the transaction update holding their account indices and data.
+struct InstructionBuffers {
+    accounts: Arc<dyn Buffer<Pubkey>>,
+    account_indices: Arc<dyn Buffer<u8>>,
+    data: Arc<dyn Buffer<u8>>,
+    ranges: std::vec::IntoIter<Range<usize>>,
+}
+
+/// The views into [`InstructionBuffers`] for a single instruction.
+struct InstructionParts {
+    accounts: SharedSlice<Pubkey>,
+    account_indices: SharedSlice<u8>,
+    data: SharedSlice<u8>,
+}
+
+impl InstructionBuffers {
+    fn new<'a>(
+        txn: &Arc<TransactionUpdate>,
+        keys: AccountKeys,
+        instructions: impl Iterator<Item = &'a [u8]> + Clone,
+    ) -> Result<Self, AccountKeyError> {
+        let num_accounts = instructions.clone().map(<[u8]>::len).sum();
+        let mut accounts = Vec::with_capacity(num_accounts);
+        let mut ranges = vec![];
+
+        for indices in instructions {
+            let start = accounts.len();
+            for &i in indices {
+                accounts.push(keys.get(i)?);
+            }
+            ranges.push(start..accounts.len());
+        }
+
+        Ok(Self {
+            accounts: Arc::new(accounts),
+            account_indices: Arc::new(CompiledField {
+                txn: Arc::clone(txn),
+                data: false,
+            }),
+            data: Arc::new(CompiledField {
+                txn: Arc::clone(txn),
+                data: true,
+            }),
+            ranges: ranges.into_iter(),
+        })
+    }
+
+    /// Get the views for the next instruction, at the given position in the
+    /// transaction.  Instructions must be requested in the same order they
+    /// were passed to [`Self::new`].
+    fn next(&mut self, pos: (usize, usize)) -> InstructionParts {
+        let accounts = self.ranges.next().unwrap_or_default();
+        let account_indices = 0..self.account_indices.slice(pos).len();
+        let data = 0..self.data.slice(pos).len();
+
+        InstructionParts {
+            accounts: SharedSlice::new(&self.accounts, (0, 0), accounts),
+            account_indices: SharedSlice::new(&self.account_indices, pos, account_indices),
+            data: SharedSlice::new(&self.data, pos, data),
+        }
+    }
+}
+
+/// The position of an instruction within its transaction.
+///
+/// Paths are displayed as `<outer_index>` for outer instructions and
+/// `<outer_index>.<inner_index>` for inner instructions, which together with
+/// the transaction signature uniquely identifies an instruction.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+pub struct InstructionPath {
+    /// The index of the outer instruction this instruction belongs to.
+    pub outer_index: usize,
+    /// The position of this instruction among all inner instructions of its
+    /// outer instruction, or `None` if this is an outer instruction.
+    pub inner_index: Option<usize>,
+    /// The invocation depth of this instruction, starting at 1 for outer
+    /// instructions, if it was reported with the transaction.
+    pub stack_height: Option<u32>,
+    /// The `inner_index` of the inner instruction that invoked this one, or
+    /// `None` if it was invoked by its outer instruction or is itself an
+    /// outer instruction.
+    pub parent: Option<usize>,
+}
+
+impl InstructionPath {
+    /// Create the path of an outer instruction.
+    #[inline]
+    #[must_use]
+    pub fn outer(outer_index: usize) -> Self {
+        Self {
+            outer_index,
+            inner_index: None,
+            stack_height: Some(1),
+            parent: None,
+        }
+    }
+
+    /// Returns true if this is the path of an outer instruction.
+    #[inline]
+    #[must_use]
+    pub fn is_outer(&self) -> bool { self.inner_index.is_none() }
+}
+
+impl fmt::Display for InstructionPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.inner_index {
+            Some(inner) => write!(f, "{}.{inner}", self.outer_index),
+            None => write!(f, "{}", self.outer_index),
+        }
+    }
 }
 
 /// The keys of the accounts involved in a transaction.
-#[derive(Debug, Default)]
-pub struct AccountKeys {
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AccountKeys<'a> {
     /// Account keys submitted directly with the transaction.
-    pub static_keys: Vec<Vec<u8>>,
+    pub static_keys: &'a [Vec<u8>],
     /// Resolved writable account keys.
-    pub dynamic_rw: Vec<Vec<u8>>,
+    pub dynamic_rw: &'a [Vec<u8>],
     /// Resolved readonly account keys.
-    pub dynamic_ro: Vec<Vec<u8>>,
+    pub dynamic_ro: &'a [Vec<u8>],
+    /// The address lookup tables used to resolve `dynamic_rw` and
+    /// `dynamic_ro`.
+    pub address_table_lookups: &'a [MessageAddressTableLookup],
+}
+
+/// A resolved transaction account and its permissions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AccountMeta {
+    /// The account pubkey.
+    pub pubkey: Pubkey,
+    /// Whether the account signed the transaction.
+    pub is_signer: bool,
+    /// Whether the transaction message requested write access to the account.
+    ///
+    /// Note that the runtime may still demote some of these accounts (such as
+    /// invoked programs or sysvars) to readonly during execution.
+    pub is_writable: bool,
+    /// Where the account key was loaded from.
+    pub source: AccountSource,
+}
+
+/// The origin of a transaction account key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountSource {
+    /// The key was submitted directly with the transaction message.
+    Static,
+    /// The key was resolved from an address lookup table.
+    LookupTable {
+        /// The address of the lookup table.
+        table: Pubkey,
+        /// The index of the key within the lookup table.
+        index: u8,
+    },
+    /// The key was resolved from an address lookup table, but the address
+    /// table lookups of the transaction do not identify which one.
+    UnknownLookupTable,
 }
 
 /// Errors that can occur when parsing an account key.
@@ -135,9 +640,12 @@ pub enum AccountKeyError {
     /// The referenced account key was invalid.
     #[error("Invalid account key data")]
     InvalidKey(#[from] std::array::TryFromSliceError),
+    /// The resolved account keys did not match the address table lookups.
+    #[error("Loaded account keys do not match the address table lookups")]
+    LookupTableMismatch,
 }
 
-impl AccountKeys {
+impl AccountKeys<'_> {
     /// Get an Account pubkey by index within the Transaction.
     ///
     /// # Errors
@@ -148,7 +656,7 @@ impl AccountKeys {
             .try_into()
             .map_err(|e| AccountKeyError::IndexConvert(e.into()))?;
         let mut i = idx;
-        [&self.static_keys, &self.dynamic_rw, &self.dynamic_ro]
+        [self.static_keys, self.dynamic_rw, self.dynamic_ro]
             .into_iter()
             .find_map(|k| {
                 k.get(i).map_or_else(
@@ -161,132 +669,413 @@ impl AccountKeys {
             })
             .unwrap_or(Err(AccountKeyError::InvalidIndex(idx)))
     }
+
+    /// Resolve the signer and writable flags and the origin of every account
+    /// key in the transaction, following the message header and address
+    /// table lookups.
+    ///
+    /// # Errors
+    /// Returns an error if any key is invalid or the resolved keys do not
+    /// line up with the address table lookups.
+    pub fn resolve_metas(
+        &self,
+        header: &MessageHeader,
+    ) -> Result<Vec<AccountMeta>, AccountKeyError> {
+        if !self.lookups_match() {
+            return Err(AccountKeyError::LookupTableMismatch);
+        }
+
+        self.resolve_metas_with(header, self.address_table_lookups)
+    }
+
+    /// Whether the loaded keys line up with the address table lookups.
+    fn lookups_match(&self) -> bool {
+        let count = |indexes: fn(&MessageAddressTableLookup) -> usize| {
+            self.address_table_lookups
+                .iter()
+                .map(indexes)
+                .sum::<usize>()
+        };
+
+        count(|l| l.writable_indexes.len()) == self.dynamic_rw.len()
+            && count(|l| l.readonly_indexes.len()) == self.dynamic_ro.len()
+    }
+
+    /// Resolve the account metadata using the given address table lookups,
+    /// marking the origin of loaded keys not covered by them as unknown.
+    fn resolve_metas_with(
+        &self,
+        header: &MessageHeader,
+        address_table_lookups: &[MessageAddressTableLookup],
+    ) -> Result<Vec<AccountMeta>, AccountKeyError> {
+        let &MessageHeader {
+            num_required_signatures,
+            num_readonly_signed_accounts,
+            num_readonly_unsigned_accounts,
+        } = header;
+        let num_signed = usize::try_from(num_required_signatures)?;
+        let num_writable_signed =
+            num_signed.saturating_sub(usize::try_from(num_readonly_signed_accounts)?);
+        let num_writable_unsigned = self
+            .static_keys
+            .len()
+            .saturating_sub(usize::try_from(num_readonly_unsigned_accounts)?);
+
+        let writable_sources = address_table_lookups
+            .iter()
+            .flat_map(|l| l.writable_indexes.iter().map(move |&i| Some((l, i))))
+            .chain(std::iter::repeat(None));
+        let readonly_sources = address_table_lookups
+            .iter()
+            .flat_map(|l| l.readonly_indexes.iter().map(move |&i| Some((l, i))))
+            .chain(std::iter::repeat(None));
+
+        let static_metas = self.static_keys.iter().enumerate().map(|(i, k)| {
+            let is_signer = i < num_signed;
+
+            Ok(AccountMeta {
+                pubkey: k.as_slice().try_into()?,
+                is_signer,
+                is_writable: if is_signer {
+                    i < num_writable_signed
+                } else {
+                    i < num_writable_unsigned
+                },
+                source: AccountSource::Static,
+            })
+        });
+        let loaded_metas = self
+            .dynamic_rw
+            .iter()
+            .zip(writable_sources)
+            .map(|k| (k, true))
+            .chain(
+                self.dynamic_ro
+                    .iter()
+                    .zip(readonly_sources)
+                    .map(|k| (k, false)),
+            )
+            .map(|((k, lookup), is_writable)| {
+                let source = match lookup {
+                    Some((lookup, index)) => AccountSource::LookupTable {
+                        table: lookup.account_key.as_slice().try_into()?,
+                        index,
+                    },
+                    None => AccountSource::UnknownLookupTable,
+                };
+
+                Ok(AccountMeta {
+                    pubkey: k.as_slice().try_into()?,
+                    is_signer: false,
+                    is_writable,
+                    source,
+                })
+            });
+
+        static_metas.chain(loaded_metas).collect()
+    }
 }
 
 impl InstructionUpdate {
     /// Parse a transaction update into a list of instructions.
     ///
+    /// This copies the transaction update, which the instructions hold on to.
+    /// Use [`Self::parse_from_shared_txn`] to parse an update without copying
+    /// it.
+    ///
     /// # Errors
     /// Returns an error if the transaction update received is in an unparseable
     /// form.
+    #[inline]
     pub fn parse_from_txn(txn: &TransactionUpdate) -> Result<Vec<Self>, ParseError> {
-        let TransactionUpdate { transaction, slot } = txn.clone();
+        Self::parse_from_shared_txn(&Arc::new(txn.clone()))
+    }
+
+    /// Parse a transaction update into a list of instructions, borrowing the
+    /// instruction data, logs and balances from the update.
+    ///
+    /// # Errors
+    /// Returns an error if the transaction update received is in an unparseable
+    /// form.
+    pub fn parse_from_shared_txn(txn: &Arc<TransactionUpdate>) -> Result<Vec<Self>, ParseError> {
+        let &TransactionUpdate {
+            ref transaction,
+            slot,
+        } = &**txn;
         let SubscribeUpdateTransactionInfo {
             signature,
             is_vote,
             transaction,
             meta,
             index,
-        } = transaction.ok_or(Missing::TransactionInfo)?;
+        } = transaction.as_ref().ok_or(Missing::TransactionInfo)?;
         let Transaction {
             signatures: _,
             message,
-        } = transaction.ok_or(Missing::Transaction)?;
+        } = transaction.as_ref().ok_or(Missing::Transaction)?;
         let TransactionStatusMeta {
             err,
             fee,
-            pre_balances,
-            post_balances,
             inner_instructions,
-            inner_instructions_none: _,
+            inner_instructions_none,
             log_messages,
-            log_messages_none: _,
-            pre_token_balances,
-            post_token_balances,
-            rewards,
             loaded_writable_addresses,
             loaded_readonly_addresses,
-            // TODO: how is this decoded
-            return_data: _,
-            return_data_none: _,
+            return_data,
             compute_units_consumed,
-            cost_units: _,
-        } = meta.ok_or(Missing::TransactionMeta)?;
+            ..
+        } = meta.as_ref().ok_or(Missing::TransactionMeta)?;
         let Message {
             header,
             account_keys,
-            recent_blockhash,
             instructions,
-            versioned: _,
-            address_table_lookups: _,
-        } = message.ok_or(Missing::TransactionMessage)?;
+            address_table_lookups,
+            ..
+        } = message.as_ref().ok_or(Missing::TransactionMessage)?;
+
+        let accounts = AccountKeys {
+            static_keys: account_keys,
+            dynamic_rw: loaded_writable_addresses,
+            dynamic_ro: loaded_readonly_addresses,
+            address_table_lookups,
+        };
+        let message_header = header.ok_or(Missing::TransactionMessageHeader)?;
+        let account_metas = Self::resolve_metas(accounts, &message_header, signature)?;
+
+        // Resolve the accounts of every instruction into a shared buffer up
+        // front rather than allocating for each instruction
+        let mut buffers = InstructionBuffers::new(
+            txn,
+            accounts,
+            instructions.iter().map(|i| i.accounts.as_slice()).chain(
+                inner_instructions
+                    .iter()
+                    .flat_map(|i| i.instructions.iter().map(|i| i.accounts.as_slice())),
+            ),
+        )?;
 
+        let logs_truncated = log_messages.iter().any(|l| l == logs::LOG_TRUNCATED);
+        // Inner instructions are only known to be complete if recorded
+        let recorded_inner = (!inner_instructions_none).then_some(inner_instructions.as_slice());
+        let txn_error = err.as_ref().and_then(|e| {
+            Self::decode_error(
+                e,
+                log_messages,
+                logs_truncated,
+                instructions,
+                recorded_inner,
+                accounts,
+            )
+        });
         let shared = Arc::new(InstructionShared {
             slot,
-            signature,
-            is_vote,
-            txn_index: index,
-            err,
-            fee,
-            pre_balances,
-            post_balances,
-            pre_token_balances,
-            post_token_balances,
-            log_messages,
-            rewards,
-            compute_units_consumed,
-            recent_blockhash,
-            accounts: AccountKeys {
-                static_keys: account_keys,
-                dynamic_rw: loaded_writable_addresses,
-                dynamic_ro: loaded_readonly_addresses,
-            },
-            message_header: header.ok_or(Missing::TransactionMessageHeader)?,
+            signature: signature
+                .as_slice()
+                .try_into()
+                .map_err(ParseError::Signature)?,
+            is_vote: *is_vote,
+            txn_index: *index,
+            txn_error,
+            fee: *fee,
+            logs_truncated,
+            compute_units_consumed: *compute_units_consumed,
+            account_metas,
+            message_header,
+            return_data: Self::resolve_return_data(return_data.as_ref(), signature),
+            txn: Arc::clone(txn),
         });
 
         let mut outer = instructions
-            .into_iter()
-            .map(|i| Self::parse_one(Arc::clone(&shared), i))
+            .iter()
+            .enumerate()
+            .map(|(i, ins)| Self::parse_one(Arc::clone(&shared), i, ins, buffers.next((0, i))))
             .collect::<Result<Vec<_>, _>>()?;
 
-        Self::parse_inner(&shared, inner_instructions, &mut outer)?;
+        Self::parse_inner(&shared, inner_instructions, &mut buffers, &mut outer)?;
+        Self::attribute_logs(log_messages, &mut outer);
 
         Ok(outer)
     }
 
+    /// Resolve the account metadata of a transaction, marking the origin of
+    /// its loaded accounts as unknown rather than failing if only the address
+    /// table lookups are inconsistent.
+    fn resolve_metas(
+        accounts: AccountKeys,
+        header: &MessageHeader,
+        signature: &[u8],
+    ) -> Result<Vec<AccountMeta>, AccountKeyError> {
+        if accounts.lookups_match() {
+            return accounts.resolve_metas_with(header, accounts.address_table_lookups);
+        }
+
+        tracing::warn!(
+            signature = bs58::encode(signature).into_string(),
+            "Loaded addresses do not match the address table lookups, leaving their lookup tables \
+             unresolved"
+        );
+        accounts.resolve_metas_with(header, &[])
+    }
+
+    /// Resolve the return data of a transaction, dropping it rather than
+    /// failing if its program ID is malformed.
+    fn resolve_return_data(raw: Option<&RawReturnData>, signature: &[u8]) -> Option<ReturnData> {
+        raw?.clone()
+            .try_into()
+            .inspect_err(|e| {
+                tracing::warn!(
+                    signature = bs58::encode(signature).into_string(),
+                    err = %e,
+                    "Ignoring transaction return data with a malformed program ID"
+                );
+            })
+            .ok()
+    }
+
+    /// Decode a transaction error, resolving the program that raised it if
+    /// an instruction failed.
+    fn decode_error(
+        err: &TransactionError,
+        log_messages: &[String],
+        logs_truncated: bool,
+        instructions: &[CompiledInstruction],
+        inner_instructions: Option<&[InnerInstructions]>,
+        accounts: AccountKeys,
+    ) -> Option<TxnError> {
+        let mut txn_error = TxnError::decode(&err.err).ok()?;
+
+        if let TxnError::Instruction(failure) = &mut txn_error {
+            // Execution stops at the first failure, so the first failed
+            // invocation in the logs is the innermost failing program
+            failure.program = log_messages
+                .iter()
+                .find_map(|l| match LogLine::parse(l) {
+                    LogLine::Failed { program, .. } => Some(program),
+                    _ => None,
+                })
+                .or_else(|| {
+                    // The failure may have been truncated from the logs
+                    if logs_truncated {
+                        return None;
+                    }
+
+                    // Otherwise the outer program failed unless it invoked
+                    // another program, which may have raised the error
+                    let index = u32::from(failure.index);
+                    let invoked = inner_instructions?
+                        .iter()
+                        .any(|i| i.index == index && !i.instructions.is_empty());
+
+                    if invoked {
+                        return None;
+                    }
+
+                    let ins = instructions.get(usize::from(failure.index))?;
+                    accounts.get(ins.program_id_index).ok()
+                });
+        }
+
+        Some(txn_error)
+    }
+
+    /// Attribute the transaction log messages, and the return data reported
+    /// in them, to each instruction invocation.
+    fn attribute_logs(log_messages: &[String], outer: &mut [Self]) {
+        let instructions: Vec<_> = outer
+            .iter()
+            .flat_map(Self::visit_all)
+            .map(|(p, i)| (i.program, p.stack_height))
+            .collect();
+        let mut attributed = logs::attribute(log_messages, &instructions).into_iter();
+
+        for ix in outer {
+            ix.assign_logs(&mut attributed);
+        }
+    }
+
+    fn assign_logs<'a>(&mut self, attributed: &mut impl Iterator<Item = logs::Attributed<'a>>) {
+        let logs::Attributed { logs, return_data } = attributed.next().unwrap_or_default();
+        self.logs = logs;
+        self.return_data =
+            return_data.and_then(|(program, data)| ReturnData::from_base64(program, data));
+
+        for ix in &mut self.inner {
+            ix.assign_logs(attributed);
+        }
+    }
+
     fn parse_inner(
         shared: &Arc<InstructionShared>,
-        inner_instructions: Vec<InnerInstructions>,
+        inner_instructions: &[InnerInstructions],
+        buffers: &mut InstructionBuffers,
         outer: &mut [Self],
     ) -> Result<(), ParseError> {
-        for insn in inner_instructions {
-            let InnerInstructions {
+        for (group, insn) in inner_instructions.iter().enumerate() {
+            let &InnerInstructions {
                 index,
-                instructions,
+                ref instructions,
             } = insn;
 
-            let Some(outer) = index.try_into().ok().and_then(|i: usize| outer.get_mut(i)) else {
+            let Some((outer_index, outer)) = index
+                .try_into()
+                .ok()
+                .and_then(|i: usize| Some((i, outer.get_mut(i)?)))
+            else {
                 return Err(ParseError::InvalidInnerInstructionIndex(index));
             };
 
             let mut inner = instructions
-                .into_iter()
-                .map(|i| Self::parse_one_inner(Arc::clone(shared), i))
+                .iter()
+                .enumerate()
+                .map(|(i, ins)| {
+                    let parts = buffers.next((group + 1, i));
+                    Self::parse_one_inner(Arc::clone(shared), outer_index, i, ins, parts)
+                })
+                .map(|i| i.map(Some))
                 .collect::<Result<Vec<_>, _>>()?;
 
-            if let Some(mut i) = inner.len().checked_sub(1) {
-                while i > 0 {
-                    let parent_idx = i - 1;
-                    let Some(height) = inner[parent_idx].1 else {
-                        continue;
-                    };
-                    while inner
-                        .get(i)
-                        .and_then(|&(_, h)| h)
-                        .is_some_and(|h| h > height)
-                    {
-                        let (child, _) = inner.remove(i);
-                        inner[parent_idx].0.inner.push(child);
-                    }
-                    i -= 1;
+            // Resolve the invoking instruction of each inner instruction from
+            // the stack heights, which are listed in invocation order
+            let mut stack: Vec<(usize, u32)> = vec![];
+            for ix in inner.iter_mut().flatten() {
+                let Some(height) = ix.path.stack_height else {
+                    stack.clear();
+                    continue;
+                };
+
+                while stack.last().is_some_and(|&(_, h)| h >= height) {
+                    stack.pop();
+                }
+
+                ix.path.parent = stack.last().map(|&(i, _)| i);
+                stack.push((
+                    ix.path.inner_index.unwrap_or_else(|| unreachable!()),
+                    height,
+                ));
+            }
+
+            // Children always follow their parent, so the tree can be built
+            // back-to-front by moving each instruction into its parent
+            let mut roots = vec![];
+            for i in (0..inner.len()).rev() {
+                let mut ix = inner[i].take().unwrap_or_else(|| unreachable!());
+                ix.inner.reverse();
+
+                match ix.path.parent {
+                    Some(p) => inner[p]
+                        .as_mut()
+                        .unwrap_or_else(|| unreachable!())
+                        .inner
+                        .push(ix),
+                    None => roots.push(ix),
                 }
             }
+            roots.reverse();
 
-            let inner: Vec<_> = inner.into_iter().map(|(i, _)| i).collect();
             if outer.inner.is_empty() {
-                outer.inner = inner;
+                outer.inner = roots;
             } else {
-                outer.inner.extend(inner);
+                outer.inner.extend(roots);
             }
         }
 
@@ -296,53 +1085,104 @@ impl InstructionUpdate {
     #[inline]
     fn parse_one(
         shared: Arc<InstructionShared>,
-        ins: CompiledInstruction,
+        outer_index: usize,
+        ins: &CompiledInstruction,
+        parts: InstructionParts,
     ) -> Result<Self, ParseError> {
-        let CompiledInstruction {
-            program_id_index,
-            ref accounts,
-            data,
-        } = ins;
-        Self::parse_from_parts(shared, program_id_index, accounts, data)
+        let path = InstructionPath::outer(outer_index);
+        Self::parse_from_parts(shared, path, ins.program_id_index, parts)
     }
 
     fn parse_one_inner(
         shared: Arc<InstructionShared>,
-        ins: InnerInstruction,
-    ) -> Result<(Self, Option<u32>), ParseError> {
-        let InnerInstruction {
-            program_id_index,
-            ref accounts,
-            data,
-            stack_height,
-        } = ins;
-        Self::parse_from_parts(shared, program_id_index, accounts, data).map(|i| (i, stack_height))
+        outer_index: usize,
+        inner_index: usize,
+        ins: &InnerInstruction,
+        parts: InstructionParts,
+    ) -> Result<Self, ParseError> {
+        let path = InstructionPath {
+            outer_index,
+            inner_index: Some(inner_index),
+            stack_height: ins.stack_height,
+            parent: None,
+        };
+        Self::parse_from_parts(shared, path, ins.program_id_index, parts)
     }
 
     fn parse_from_parts(
         shared: Arc<InstructionShared>,
+        path: InstructionPath,
         program_id_index: u32,
-        accounts: &[u8],
-        data: Vec<u8>,
+        parts: InstructionParts,
     ) -> Result<Self, ParseError> {
+        let InstructionParts {
+            accounts,
+            account_indices,
+            data,
+        } = parts;
+
         Ok(Self {
-            program: shared.accounts.get(program_id_index)?,
-            accounts: accounts
-                .iter()
-                .map(|&i| shared.accounts.get(i))
-                .collect::<Result<_, _>>()?,
+            program: shared.accounts().get(program_id_index)?,
+            accounts,
+            account_indices,
             data,
             shared,
             inner: vec![],
+            path,
+            logs: None,
+            return_data: None,
         })
     }
 
-    /// Iterate over all inner instructions stored in this instruction.
+    /// Get the log lines emitted while executing this instruction, including
+    /// the output of its inner instructions.
+    #[must_use]
+    pub fn log_messages(&self) -> &[String] {
+        self.logs
+            .as_ref()
+            .map_or(&[], |l| l.lines(self.shared.log_messages()))
+    }
+
+    /// Iterate over the log lines emitted by this instruction itself,
+    /// excluding the output of its inner instructions.
+    pub fn own_log_messages(&self) -> impl Iterator<Item = &String> + '_ {
+        self.logs
+            .iter()
+            .flat_map(|l| l.own_lines(self.shared.log_messages()))
+    }
+
+    /// The number of compute units consumed by this instruction, including its
+    /// inner instructions, as reported in the logs.
+    #[inline]
+    #[must_use]
+    pub fn compute_units_consumed(&self) -> Option<u64> {
+        self.logs.as_ref()?.compute_units_consumed
+    }
+
+    /// A unique identifier for this instruction of the form
+    /// `<signature>:<path>`, suitable for idempotent writes.
+    #[must_use]
+    pub fn event_id(&self) -> String { format!("{}:{}", self.shared.signature, self.path) }
+
+    /// Iterate over the resolved metadata of the accounts passed to this
+    /// instruction, in the same order as `accounts`.
+    ///
+    /// The flags reflect the transaction-level permissions of each account.
+    #[inline]
+    pub fn account_metas(&self) -> impl Iterator<Item = &AccountMeta> + '_ {
+        self.account_indices
+            .iter()
+            .filter_map(|&i| self.shared.account_metas.get(usize::from(i)))
+    }
+
+    /// Iterate over this instruction and all inner instructions stored in it,
+    /// along with their paths.
     #[inline]
     pub fn visit_all(&self) -> VisitAll<'_> { VisitAll::new(self) }
 }
 
-/// An iterator over all inner instructions stored in an instruction update.
+/// A pre-order iterator over an instruction update and all inner instructions
+/// stored in it, yielding the path of each instruction alongside it.
 #[derive(Debug)]
 #[must_use = "This type does nothing unless iterated"]
 pub struct VisitAll<'a>(VisitAllState<'a>);
@@ -359,7 +1199,7 @@ impl<'a> VisitAll<'a> {
 }
 
 impl<'a> Iterator for VisitAll<'a> {
-    type Item = &'a InstructionUpdate;
+    type Item = (InstructionPath, &'a InstructionUpdate);
 
     fn next(&mut self) -> Option<Self::Item> {
         match &mut self.0 {
@@ -367,7 +1207,7 @@ impl<'a> Iterator for VisitAll<'a> {
                 let mut d = VecDeque::new();
                 d.push_back(i.inner.iter());
                 self.0 = VisitAllState::Started(d);
-                Some(i)
+                Some((i.path, i))
             },
             VisitAllState::Started(d) => loop {
                 let Some(ix) = d.back_mut()?.next() else {
@@ -375,8 +1215,402 @@ impl<'a> Iterator for VisitAll<'a> {
                     continue;
                 };
                 d.push_back(ix.inner.iter());
-                break Some(ix);
+                break Some((ix.path, ix));
             },
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::KeyBytes;
+
+    fn key(b: u8) -> Vec<u8> { vec![b; 32] }
+
+    fn txn(
+        instructions: Vec<CompiledInstruction>,
+        inner_instructions: Vec<InnerInstructions>,
+        log_messages: Vec<String>,
+    ) -> TransactionUpdate {
+        TransactionUpdate {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: vec![1; 64],
+                transaction: Some(Transaction {
+                    signatures: vec![vec![1; 64]],
+                    message: Some(Message {
+                        header: Some(MessageHeader {
+                            num_required_signatures: 1,
+                            num_readonly_signed_accounts: 0,
+                            num_readonly_unsigned_accounts: 3,
+                        }),
+                        account_keys: (0..4).map(key).collect(),
+                        instructions,
+                        ..Message::default()
+                    }),
+                }),
+                meta: Some(TransactionStatusMeta {
+                    inner_instructions,
+                    log_messages,
+                    ..TransactionStatusMeta::default()
+                }),
+                ..SubscribeUpdateTransactionInfo::default()
+            }),
+            slot: 1,
+        }
+    }
+
+    fn inner(program_id_index: u32, stack_height: u32) -> InnerInstruction {
+        InnerInstruction {
+            program_id_index,
+            accounts: vec![0],
+            data: vec![],
+            stack_height: Some(stack_height),
+        }
+    }
+
+    #[test]
+    fn test_inner_instruction_paths() {
+        let outer = CompiledInstruction {
+            program_id_index: 1,
+            accounts: vec![0],
+            data: vec![],
+        };
+        let ixs = InstructionUpdate::parse_from_txn(&txn(
+            vec![outer.clone(), outer],
+            vec![InnerInstructions {
+                index: 1,
+                instructions: vec![
+                    inner(2, 2),
+                    inner(3, 3),
+                    inner(3, 3),
+                    inner(2, 2),
+                    inner(3, 3),
+                ],
+            }],
+            vec![],
+        ))
+        .unwrap();
+
+        let paths: Vec<_> = ixs
+            .iter()
+            .flat_map(InstructionUpdate::visit_all)
+            .map(|(p, _)| (p.to_string(), p.parent))
+            .collect();
+
+        assert_eq!(paths, [
+            ("0".to_owned(), None),
+            ("1".to_owned(), None),
+            ("1.0".to_owned(), None),
+            ("1.1".to_owned(), Some(0)),
+            ("1.2".to_owned(), Some(0)),
+            ("1.3".to_owned(), None),
+            ("1.4".to_owned(), Some(3)),
+        ]);
+        assert_eq!(ixs[1].inner.len(), 2);
+        assert_eq!(ixs[1].inner[0].inner.len(), 2);
+        assert_eq!(ixs[1].inner[1].inner[0].path.stack_height, Some(3));
+        assert_eq!(
+            ixs[1].inner[1].inner[0].event_id(),
+            format!("{}:1.4", bs58::encode([1; 64]).into_string())
+        );
+    }
+
+    #[test]
+    fn test_instruction_parts() {
+        let update = Arc::new(txn(
+            vec![
+                CompiledInstruction {
+                    program_id_index: 1,
+                    accounts: vec![0, 2],
+                    data: vec![1, 2, 3],
+                },
+                CompiledInstruction {
+                    program_id_index: 2,
+                    accounts: vec![],
+                    data: vec![4],
+                },
+            ],
+            vec![InnerInstructions {
+                index: 0,
+                instructions: vec![InnerInstruction {
+                    program_id_index: 3,
+                    accounts: vec![3, 1],
+                    data: vec![5, 6],
+                    stack_height: Some(2),
+                }],
+            }],
+            vec![],
+        ));
+        let ixs = InstructionUpdate::parse_from_shared_txn(&update).unwrap();
+
+        assert_eq!(*ixs[0].accounts, [KeyBytes([0; 32]), KeyBytes([2; 32])]);
+        assert_eq!(*ixs[0].account_indices, [0, 2]);
+        assert_eq!(*ixs[0].data, [1, 2, 3]);
+        assert!(ixs[1].accounts.is_empty());
+        assert_eq!(*ixs[1].data, [4]);
+
+        let inner = &ixs[0].inner[0];
+        assert_eq!(inner.program, KeyBytes([3; 32]));
+        assert_eq!(*inner.accounts, [KeyBytes([3; 32]), KeyBytes([1; 32])]);
+        assert_eq!(*inner.data, [5, 6]);
+
+        // Data is borrowed from the transaction update rather than copied
+        let info = update.transaction.as_ref().unwrap();
+        let message = info.transaction.as_ref().unwrap().message.as_ref().unwrap();
+        let meta = info.meta.as_ref().unwrap();
+        assert_eq!(ixs[0].data.as_ptr(), message.instructions[0].data.as_ptr());
+        assert_eq!(
+            inner.account_indices.as_ptr(),
+            meta.inner_instructions[0].instructions[0].accounts.as_ptr()
+        );
+        assert_eq!(
+            ixs[0].shared.log_messages().as_ptr(),
+            meta.log_messages.as_ptr()
+        );
+
+        assert!(InstructionUpdate::parse_from_txn(&txn(
+            vec![CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![4],
+                data: vec![],
+            }],
+            vec![],
+            vec![],
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn test_attribute_logs() {
+        let [p1, p2, p3] = [1, 2, 3].map(|b| KeyBytes::<32>([b; 32]).to_string());
+        let outer = |program_id_index| CompiledInstruction {
+            program_id_index,
+            accounts: vec![0],
+            data: vec![],
+        };
+        let lines = [
+            format!("Program {p1} invoke [1]"),
+            "Program log: outer".to_owned(),
+            format!("Program {p2} invoke [2]"),
+            "Program log: inner".to_owned(),
+            format!("Program return: {p2} AQI="),
+            format!("Program {p2} consumed 100 of 1000 compute units"),
+            format!("Program {p2} success"),
+            format!("Program {p1} consumed 300 of 1200 compute units"),
+            format!("Program {p1} success"),
+            format!("Program {p1} invoke [1]"),
+            "Log truncated".to_owned(),
+        ];
+        let ixs = InstructionUpdate::parse_from_txn(&txn(
+            vec![outer(3), outer(1), outer(1)],
+            vec![InnerInstructions {
+                index: 1,
+                instructions: vec![inner(2, 2)],
+            }],
+            lines.to_vec(),
+        ))
+        .unwrap();
+
+        assert!(ixs[0].shared.logs_truncated);
+        assert!(ixs[0].logs.is_none(), "precompile {p3} should have no logs");
+
+        let logs = ixs[1].logs.as_ref().unwrap();
+        assert_eq!(logs.range, 0..9);
+        assert_eq!(logs.result, Some(logs::InvokeResult::Success));
+        assert_eq!(ixs[1].compute_units_consumed(), Some(300));
+        assert_eq!(ixs[1].own_log_messages().collect::<Vec<_>>(), [
+            &lines[0], &lines[1], &lines[7], &lines[8]
+        ]);
+        assert!(ixs[1].return_data.is_none());
+
+        let inner = &ixs[1].inner[0];
+        assert_eq!(inner.log_messages(), &lines[2..7]);
+        assert_eq!(inner.compute_units_consumed(), Some(100));
+        assert_eq!(inner.return_data.as_ref().unwrap().data, [1, 2]);
+
+        let logs = ixs[2].logs.as_ref().unwrap();
+        assert!(logs.truncated);
+        assert_eq!(logs.result, None);
+        assert_eq!(logs.range, 9..11);
+    }
+
+    #[test]
+    fn test_resolve_metas() {
+        let keys = AccountKeys {
+            static_keys: &(0..5).map(key).collect::<Vec<_>>(),
+            dynamic_rw: &[key(5)],
+            dynamic_ro: &[key(6), key(7)],
+            address_table_lookups: &[
+                MessageAddressTableLookup {
+                    account_key: key(10),
+                    writable_indexes: vec![3],
+                    readonly_indexes: vec![0],
+                },
+                MessageAddressTableLookup {
+                    account_key: key(11),
+                    writable_indexes: vec![],
+                    readonly_indexes: vec![9],
+                },
+            ],
+        };
+        let header = MessageHeader {
+            num_required_signatures: 2,
+            num_readonly_signed_accounts: 1,
+            num_readonly_unsigned_accounts: 2,
+        };
+
+        let metas = keys.resolve_metas(&header).unwrap();
+        let flags: Vec<_> = metas.iter().map(|m| (m.is_signer, m.is_writable)).collect();
+
+        assert_eq!(flags, [
+            (true, true),
+            (true, false),
+            (false, true),
+            (false, false),
+            (false, false),
+            (false, true),
+            (false, false),
+            (false, false),
+        ]);
+        assert_eq!(metas[5].source, AccountSource::LookupTable {
+            table: KeyBytes([10; 32]),
+            index: 3,
+        });
+        assert_eq!(metas[7].source, AccountSource::LookupTable {
+            table: KeyBytes([11; 32]),
+            index: 9,
+        });
+    }
+
+    #[test]
+    fn test_resolve_metas_lookup_mismatch() {
+        let keys = AccountKeys {
+            static_keys: &[key(0)],
+            dynamic_rw: &[key(1)],
+            ..AccountKeys::default()
+        };
+
+        assert!(matches!(
+            keys.resolve_metas(&MessageHeader::default()),
+            Err(AccountKeyError::LookupTableMismatch)
+        ));
+    }
+
+    #[test]
+    fn test_parse_lookup_mismatch() {
+        let mut txn = txn(
+            vec![CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![0, 4],
+                data: vec![],
+            }],
+            vec![],
+            vec![],
+        );
+        let info = txn.transaction.as_mut().unwrap();
+        info.meta.as_mut().unwrap().loaded_writable_addresses = vec![key(4)];
+
+        let ixs = InstructionUpdate::parse_from_txn(&txn).unwrap();
+
+        assert_eq!(*ixs[0].accounts, [KeyBytes([0; 32]), KeyBytes([4; 32])]);
+        assert_eq!(ixs[0].shared.fee_payer(), Some(KeyBytes([0; 32])));
+
+        // Static accounts keep their flags, only the lookup table is unknown
+        let metas: Vec<_> = ixs[0].account_metas().collect();
+        assert_eq!(metas, [
+            &AccountMeta {
+                pubkey: KeyBytes([0; 32]),
+                is_signer: true,
+                is_writable: true,
+                source: AccountSource::Static,
+            },
+            &AccountMeta {
+                pubkey: KeyBytes([4; 32]),
+                is_signer: false,
+                is_writable: true,
+                source: AccountSource::UnknownLookupTable,
+            },
+        ]);
+        assert_eq!(ixs[0].shared.signers().collect::<Vec<_>>(), [KeyBytes(
+            [0; 32]
+        )]);
+    }
+
+    #[test]
+    fn test_parse_malformed_return_data() {
+        let mut txn = txn(
+            vec![CompiledInstruction {
+                program_id_index: 1,
+                accounts: vec![0],
+                data: vec![],
+            }],
+            vec![],
+            vec![],
+        );
+        let meta = txn.transaction.as_mut().unwrap().meta.as_mut().unwrap();
+        meta.return_data = Some(RawReturnData {
+            program_id: vec![1; 31],
+            data: vec![1, 2],
+        });
+
+        let ixs = InstructionUpdate::parse_from_txn(&txn).unwrap();
+        assert!(ixs[0].shared.return_data.is_none());
+
+        txn.transaction
+            .as_mut()
+            .unwrap()
+            .meta
+            .as_mut()
+            .unwrap()
+            .return_data = Some(RawReturnData {
+            program_id: vec![1; 32],
+            data: vec![1, 2],
+        });
+
+        let ixs = InstructionUpdate::parse_from_txn(&txn).unwrap();
+        assert_eq!(
+            ixs[0].shared.return_data,
+            Some(ReturnData {
+                program_id: KeyBytes([1; 32]),
+                data: vec![1, 2],
+            })
+        );
+    }
+
+    #[test]
+    fn test_return_data_from_log() {
+        let id = "11111111111111111111111111111111";
+
+        assert_eq!(
+            ReturnData::from_log(&format!("Program return: {id} AQID")),
+            Some(ReturnData {
+                program_id: id.parse().unwrap(),
+                data: vec![1, 2, 3],
+            })
+        );
+        assert_eq!(
+            ReturnData::from_log(&format!("Program return: {id} not base64!")),
+            None
+        );
+        assert_eq!(ReturnData::from_log("Program log: AQID"), None);
+    }
+
+    #[test]
+    fn test_return_data_try_from() {
+        let raw = RawReturnData {
+            program_id: vec![7; 32],
+            data: vec![1, 2],
+        };
+        assert_eq!(ReturnData::try_from(raw).unwrap(), ReturnData {
+            program_id: KeyBytes([7; 32]),
+            data: vec![1, 2],
+        });
+
+        let raw = RawReturnData {
+            program_id: vec![7; 31],
+            data: vec![],
+        };
+        assert!(ReturnData::try_from(raw).is_err());
+    }
+}
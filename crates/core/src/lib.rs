@@ -17,27 +17,41 @@
 
 use std::{
     borrow::Cow,
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::{self, Debug},
     future::Future,
     str::FromStr,
     sync::Arc,
 };
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use yellowstone_grpc_proto::geyser::{
-    self, SubscribeRequest, SubscribeRequestFilterAccounts, SubscribeRequestFilterBlocksMeta,
-    SubscribeRequestFilterSlots, SubscribeRequestFilterTransactions, SubscribeUpdateAccount,
-    SubscribeUpdateBlockMeta, SubscribeUpdateSlot, SubscribeUpdateTransaction,
+    self, SubscribeRequest, SubscribeRequestAccountsDataSlice, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterBlocks,
+    SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterEntry, SubscribeRequestFilterSlots,
+    SubscribeRequestFilterTransactions, SubscribeUpdateAccount, SubscribeUpdateBlock,
+    SubscribeUpdateBlockMeta, SubscribeUpdateEntry, SubscribeUpdateSlot,
+    SubscribeUpdateTransaction, SubscribeUpdateTransactionStatus,
 };
 
 pub extern crate bs58;
 #[cfg(feature = "proto")]
 pub extern crate yellowstone_vixen_proto;
 
+pub mod anchor;
+pub mod balances;
 pub mod instruction;
+pub mod logs;
+pub mod parser_ext;
 #[cfg(feature = "proto")]
 pub mod proto;
+pub mod router;
+#[cfg(test)]
+mod test_util;
+pub mod transaction;
+pub mod txn_error;
+
+pub use parser_ext::ParserExt;
 
 type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
 
@@ -60,20 +74,87 @@ impl<T: Into<BoxedError>> From<T> for ParseError {
 /// The result of parsing an update.
 pub type ParseResult<T> = Result<T, ParseError>;
 
-/// An account update from Yellowstone.
-pub type AccountUpdate = SubscribeUpdateAccount;
+/// An account update from Yellowstone, along with the data slices its
+/// account data was requested with.
+///
+/// This dereferences to the received [`SubscribeUpdateAccount`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccountUpdate {
+    /// The account update as received from Yellowstone.
+    pub update: SubscribeUpdateAccount,
+    /// The merged data slices requested for the subscription, or empty if the
+    /// full account data was requested.  See [`DataSlice`] for how the
+    /// received data is laid out.
+    pub data_slices: Arc<[DataSlice]>,
+}
+
+impl AccountUpdate {
+    /// Get the bytes of a range of the account data, or `None` if the account
+    /// is too short to contain it or the range was not requested.
+    ///
+    /// **NOTE:** When slices requested by different parsers overlap, each
+    /// merged slice is only delivered if the account contains all of it.
+    #[must_use]
+    pub fn data_slice(&self, slice: DataSlice) -> Option<&[u8]> {
+        let data = &self.update.account.as_ref()?.data;
+
+        if self.data_slices.is_empty() {
+            return slice.get(data);
+        }
+
+        // The received data is the concatenation of the merged slices
+        let mut start = 0;
+        for merged in &*self.data_slices {
+            if merged.offset <= slice.offset && slice.end() <= merged.end() {
+                return DataSlice::new(start + slice.offset - merged.offset, slice.length)
+                    .get(data);
+            }
+
+            start += merged.length;
+        }
+
+        None
+    }
+}
+
+impl From<SubscribeUpdateAccount> for AccountUpdate {
+    #[inline]
+    fn from(update: SubscribeUpdateAccount) -> Self {
+        Self {
+            update,
+            data_slices: Arc::default(),
+        }
+    }
+}
+
+impl std::ops::Deref for AccountUpdate {
+    type Target = SubscribeUpdateAccount;
+
+    #[inline]
+    fn deref(&self) -> &SubscribeUpdateAccount { &self.update }
+}
+
 /// A transaction update from Yellowstone.
 pub type TransactionUpdate = SubscribeUpdateTransaction;
 /// A block meta update from Yellowstone.
 pub type BlockMetaUpdate = SubscribeUpdateBlockMeta;
 /// A slot update from Yellowstone.
 pub type SlotUpdate = SubscribeUpdateSlot;
+/// A full block update from Yellowstone.
+pub type BlockUpdate = SubscribeUpdateBlock;
+/// An entry update from Yellowstone.
+pub type EntryUpdate = SubscribeUpdateEntry;
+/// A transaction status update from Yellowstone.
+pub type TransactionStatusUpdate = SubscribeUpdateTransactionStatus;
 
 /// Generic output type for instruction parsers that wraps shared data for all instructions
 /// in the given transaction.
 ///
 /// This is the recommended structure for an `Parser::Output` associated type, for the case that the parser
 /// wants to expose the `InstructionShared` data to the `Handler`s
+///
+/// Any instruction parser can be adapted to produce this output with
+/// [`ParserExt::with_shared`].
 #[derive(Debug)]
 pub struct InstructionUpdateOutput<T> {
     /// The parsed instruction.
@@ -106,6 +187,18 @@ pub trait Parser {
     /// to values parseable by this parser.
     fn prefilter(&self) -> Prefilter;
 
+    /// A key identifying the state affected by the given update, such as the
+    /// address of a pool.  When the runtime is configured to order updates
+    /// by parser key, updates given the same key are processed in the order
+    /// they were received.
+    ///
+    /// Returns `None` by default, leaving the update unordered.
+    #[inline]
+    fn order_key(&self, value: &Self::Input) -> Option<Pubkey> {
+        let _ = value;
+        None
+    }
+
     /// Parse the given update into a parsed value.
     fn parse(&self, value: &Self::Input) -> impl Future<Output = ParseResult<Self::Output>> + Send;
 }
@@ -116,6 +209,17 @@ pub trait ProgramParser: Parser {
     fn program_id(&self) -> Pubkey;
 }
 
+/// A program parser that can decode the custom error codes returned by its
+/// program.
+pub trait ProgramErrorParser: ProgramParser {
+    /// The error type of the program.
+    type Error: std::error::Error;
+
+    /// Decode a custom error code returned by the program, returning `None`
+    /// if the code is not recognized.
+    fn parse_error(&self, code: u32) -> Option<Self::Error>;
+}
+
 /// Helper trait for getting the ID of a parser.
 pub trait ParserId {
     /// Get the ID of this parser, see [`Parser::id`].
@@ -148,6 +252,47 @@ impl<T: Parser> GetPrefilter for T {
     fn prefilter(&self) -> Prefilter { Parser::prefilter(self) }
 }
 
+/// Helper trait for getting the transaction signature of an update.
+pub trait GetSignature {
+    /// Get the signature of the transaction this update refers to, or `None`
+    /// if it has none or it is malformed.
+    fn signature(&self) -> Option<Signature>;
+}
+
+impl GetSignature for AccountUpdate {
+    #[inline]
+    fn signature(&self) -> Option<Signature> {
+        self.account
+            .as_ref()?
+            .txn_signature
+            .as_deref()?
+            .try_into()
+            .ok()
+    }
+}
+
+impl GetSignature for TransactionUpdate {
+    #[inline]
+    fn signature(&self) -> Option<Signature> {
+        self.transaction
+            .as_ref()?
+            .signature
+            .as_slice()
+            .try_into()
+            .ok()
+    }
+}
+
+impl GetSignature for TransactionStatusUpdate {
+    #[inline]
+    fn signature(&self) -> Option<Signature> { self.signature.as_slice().try_into().ok() }
+}
+
+impl GetSignature for instruction::InstructionUpdate {
+    #[inline]
+    fn signature(&self) -> Option<Signature> { Some(self.shared.signature) }
+}
+
 // TODO: why are so many fields on the prefilters and prefilter builder optional???
 /// A prefilter for narrowing down the updates that a parser will receive.
 #[derive(Debug, Default, Clone)]
@@ -160,6 +305,12 @@ pub struct Prefilter {
     pub block_meta: Option<BlockMetaPrefilter>,
     /// Filters for slot updates.
     pub slot: Option<SlotPrefilter>,
+    /// Filters for full block updates.
+    pub block: Option<BlockPrefilter>,
+    /// Filters for entry updates.
+    pub entry: Option<EntryPrefilter>,
+    /// Filters for transaction status updates.
+    pub transaction_status: Option<TransactionPrefilter>,
 }
 
 fn merge_opt<T, F: FnOnce(&mut T, T)>(lhs: &mut Option<T>, rhs: Option<T>, f: F) {
@@ -183,11 +334,21 @@ impl Prefilter {
             transaction,
             block_meta,
             slot,
+            block,
+            entry,
+            transaction_status,
         } = self;
         merge_opt(account, other.account, AccountPrefilter::merge);
         merge_opt(transaction, other.transaction, TransactionPrefilter::merge);
         merge_opt(block_meta, other.block_meta, BlockMetaPrefilter::merge);
         merge_opt(slot, other.slot, SlotPrefilter::merge);
+        merge_opt(block, other.block, BlockPrefilter::merge);
+        merge_opt(entry, other.entry, EntryPrefilter::merge);
+        merge_opt(
+            transaction_status,
+            other.transaction_status,
+            TransactionPrefilter::merge,
+        );
     }
 }
 
@@ -211,20 +372,155 @@ pub struct AccountPrefilter {
     pub accounts: HashSet<Pubkey>,
     /// The owners that this prefilter will match.
     pub owners: HashSet<Pubkey>,
+    /// Alternative sets of data filters.  An account matches if it satisfies
+    /// every filter of at least one set.  If empty, account data is not
+    /// filtered.
+    pub filters: Vec<Vec<AccountFilter>>,
+    /// The ranges of account data to retrieve.  If empty, the full account
+    /// data is retrieved.
+    pub data_slices: Vec<DataSlice>,
 }
 
 impl AccountPrefilter {
     /// Merge another account prefilter into this one, producing a prefilter
     /// that describes the union of the two.
     pub fn merge(&mut self, other: AccountPrefilter) {
-        let Self { accounts, owners } = self;
+        let Self {
+            accounts,
+            owners,
+            filters,
+            data_slices,
+        } = self;
         accounts.extend(other.accounts);
         owners.extend(other.owners);
+
+        // Likewise a prefilter without data slices needs the full account data
+        if data_slices.is_empty() || other.data_slices.is_empty() {
+            data_slices.clear();
+        } else {
+            for slice in other.data_slices {
+                if !data_slices.contains(&slice) {
+                    data_slices.push(slice);
+                }
+            }
+        }
+
+        // A prefilter without data filters already matches anything the
+        // other one could
+        if filters.is_empty() || other.filters.is_empty() {
+            filters.clear();
+        } else {
+            for set in other.filters {
+                if !filters.contains(&set) {
+                    filters.push(set);
+                }
+            }
+        }
+    }
+}
+
+/// A range of account data requested by an account prefilter.
+///
+/// Account data is requested once for the whole subscription, so the slices
+/// requested by every parser are merged where they touch or overlap.  Parsers
+/// receive the bytes of the merged slices concatenated in order, as sent by
+/// Yellowstone, and can use [`AccountUpdate::data_slice`] to get the bytes of
+/// a slice they requested.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct DataSlice {
+    /// The offset of the first byte of the slice.
+    pub offset: u64,
+    /// The length of the slice in bytes.
+    pub length: u64,
+}
+
+impl DataSlice {
+    /// Create a new data slice.
+    #[inline]
+    #[must_use]
+    pub const fn new(offset: u64, length: u64) -> Self { Self { offset, length } }
+
+    /// Get the offset one past the last byte of the slice.
+    #[inline]
+    #[must_use]
+    pub const fn end(&self) -> u64 { self.offset.saturating_add(self.length) }
+
+    /// Get the bytes covered by this slice from full account data, or `None`
+    /// if the account is too short to contain the slice.
+    #[must_use]
+    pub fn get<'a>(&self, data: &'a [u8]) -> Option<&'a [u8]> {
+        let start = usize::try_from(self.offset).ok()?;
+        let end = usize::try_from(self.end()).ok()?;
+        data.get(start..end)
+    }
+
+    /// Sort the given slices and merge any overlapping or adjacent slices,
+    /// producing the list of slices requested from Yellowstone.
+    #[must_use]
+    pub fn merge_all<I: IntoIterator<Item = Self>>(it: I) -> Vec<Self> {
+        let mut slices: Vec<Self> = it.into_iter().filter(|s| s.length > 0).collect();
+        slices.sort_unstable();
+
+        let mut merged: Vec<Self> = Vec::with_capacity(slices.len());
+        for slice in slices {
+            match merged.last_mut() {
+                Some(last) if slice.offset <= last.end() => {
+                    last.length = last.length.max(slice.end() - last.offset);
+                },
+                _ => merged.push(slice),
+            }
+        }
+
+        merged
+    }
+
+    /// Cut the given merged slices out of full account data, the same way
+    /// Yellowstone does: the bytes of every slice contained in the account
+    /// are concatenated in order.
+    #[must_use]
+    pub fn slice(slices: &[Self], data: &[u8]) -> Vec<u8> {
+        slices
+            .iter()
+            .filter_map(|s| s.get(data))
+            .flatten()
+            .copied()
+            .collect()
     }
 }
 
+/// A predicate on the data or balance of an account.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum AccountFilter {
+    /// Match accounts whose data contains the given bytes at the given offset.
+    Memcmp {
+        /// The offset into the account data to compare at.
+        offset: u64,
+        /// The bytes to compare against.
+        bytes: Vec<u8>,
+    },
+    /// Match accounts whose data is exactly this many bytes long.
+    DataSize(u64),
+    /// Match SPL token accounts in a valid, initialized state.
+    TokenAccountState,
+    /// Match accounts whose lamport balance satisfies the given comparison.
+    Lamports(LamportsCmp),
+}
+
+/// A comparison against the lamport balance of an account.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LamportsCmp {
+    /// The balance is equal to the given value.
+    Eq(u64),
+    /// The balance is not equal to the given value.
+    Ne(u64),
+    /// The balance is less than the given value.
+    Lt(u64),
+    /// The balance is greater than the given value.
+    Gt(u64),
+}
+
 /// A prefilter for matching transactions.
-#[derive(Debug, Default, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct TransactionPrefilter {
     /// The transaction **must** include at least **ONE** of these accounts. Otherwise, the transaction
     ///  won't be retrieved.
@@ -233,19 +529,83 @@ pub struct TransactionPrefilter {
     ///  That means if any of the accounts are not included in the transaction, the transaction
     ///  won't be retrieved.
     pub accounts_required: HashSet<Pubkey>,
+    /// The transaction **must not** include any of these accounts.
+    pub accounts_exclude: HashSet<Pubkey>,
+    /// Whether failed transactions are matched.  Defaults to
+    /// [`FilterMode::Exclude`], or [`FilterMode::Include`] when built for
+    /// transaction statuses.
+    pub failed: FilterMode,
+    /// Whether vote transactions are matched.  Defaults to
+    /// [`FilterMode::Include`].
+    pub vote: FilterMode,
+}
+
+impl Default for TransactionPrefilter {
+    fn default() -> Self {
+        Self {
+            accounts_include: HashSet::new(),
+            accounts_required: HashSet::new(),
+            accounts_exclude: HashSet::new(),
+            failed: FilterMode::Exclude,
+            vote: FilterMode::Include,
+        }
+    }
 }
 
 impl TransactionPrefilter {
     /// Merge another transaction prefilter into this one, producing a prefilter
     /// that describes the union of the two.
+    ///
+    /// Only accounts excluded by both prefilters remain excluded, and the
+    /// `failed` and `vote` modes are widened to [`FilterMode::Include`] if
+    /// they differ.
     pub fn merge(&mut self, other: TransactionPrefilter) {
         let Self {
             accounts_include,
             accounts_required,
+            accounts_exclude,
+            failed,
+            vote,
         } = self;
 
         accounts_include.extend(other.accounts_include);
         accounts_required.extend(other.accounts_required);
+        accounts_exclude.retain(|a| other.accounts_exclude.contains(a));
+        failed.merge(other.failed);
+        vote.merge(other.vote);
+    }
+}
+
+/// Whether a transaction prefilter matches transactions with a given
+/// property, such as having failed or being a vote.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterMode {
+    /// Match only transactions without the property.
+    Exclude,
+    /// Match transactions regardless of the property.
+    Include,
+    /// Match only transactions with the property.
+    Only,
+}
+
+impl FilterMode {
+    /// Merge another mode into this one, producing the mode that matches the
+    /// union of the two.
+    pub fn merge(&mut self, other: FilterMode) {
+        if *self != other {
+            *self = Self::Include;
+        }
+    }
+
+    /// Convert this mode to the value expected by the Geyser transaction
+    /// filter.
+    #[must_use]
+    pub fn as_geyser(self) -> Option<bool> {
+        match self {
+            Self::Exclude => Some(false),
+            Self::Include => None,
+            Self::Only => Some(true),
+        }
     }
 }
 
@@ -261,10 +621,63 @@ impl BlockMetaPrefilter {
 
 /// A prefilter for matching slot updates updates.
 #[derive(Debug, Default, Clone, PartialEq, Copy)]
-pub struct SlotPrefilter {}
+pub struct SlotPrefilter {
+    /// Receive every status transition of each slot, including slots that
+    /// die, rather than only those at the commitment level of the
+    /// subscription.
+    pub all_statuses: bool,
+}
 
 impl SlotPrefilter {
     /// Merge another slot prefilter into this one.
+    pub fn merge(lhs: &mut Self, rhs: Self) { lhs.all_statuses |= rhs.all_statuses; }
+}
+
+/// A prefilter for matching full block updates.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BlockPrefilter {
+    /// Only blocks containing a transaction or account update involving at
+    /// least one of these accounts are matched.  If empty, all blocks are
+    /// matched.
+    pub accounts_include: HashSet<Pubkey>,
+    /// Whether matched blocks include their transactions.
+    pub include_transactions: bool,
+    /// Whether matched blocks include their account updates.
+    pub include_accounts: bool,
+    /// Whether matched blocks include their entries.
+    pub include_entries: bool,
+}
+
+impl BlockPrefilter {
+    /// Merge another block prefilter into this one, producing a prefilter
+    /// that describes the union of the two.
+    pub fn merge(&mut self, other: BlockPrefilter) {
+        let Self {
+            accounts_include,
+            include_transactions,
+            include_accounts,
+            include_entries,
+        } = self;
+
+        // A prefilter without accounts already matches every block
+        if accounts_include.is_empty() || other.accounts_include.is_empty() {
+            accounts_include.clear();
+        } else {
+            accounts_include.extend(other.accounts_include);
+        }
+
+        *include_transactions |= other.include_transactions;
+        *include_accounts |= other.include_accounts;
+        *include_entries |= other.include_entries;
+    }
+}
+
+/// A prefilter for matching entry updates.
+#[derive(Debug, Default, Clone, PartialEq, Copy)]
+pub struct EntryPrefilter {}
+
+impl EntryPrefilter {
+    /// Merge another entry prefilter into this one.
     /// This function currently does nothing as the struct has no fields.
     pub fn merge(_lhs: &mut Self, _rhs: Self) {}
 }
@@ -302,6 +715,9 @@ macro_rules! pubkey_convert_helpers {
 /// working with Solana program crates.
 pub type Pubkey = KeyBytes<32>;
 
+/// Helper type representing a Solana transaction signature.
+pub type Signature = KeyBytes<64>;
+
 /// Generic wrapper for a fixed-length array of cryptographic key bytes,
 /// convertible to or from a base58-encoded string.
 #[derive(Clone, Copy, PartialEq, Eq, Hash)]
@@ -322,6 +738,11 @@ impl<const LEN: usize> fmt::Display for KeyBytes<LEN> {
     }
 }
 
+impl<const LEN: usize> Default for KeyBytes<LEN> {
+    #[inline]
+    fn default() -> Self { Self([0; LEN]) }
+}
+
 impl<const LEN: usize> From<[u8; LEN]> for KeyBytes<LEN> {
     #[inline]
     fn from(value: [u8; LEN]) -> Self { Self(value) }
@@ -374,6 +795,34 @@ impl<const LEN: usize> std::borrow::BorrowMut<[u8]> for KeyBytes<LEN> {
     fn borrow_mut(&mut self) -> &mut [u8] { self.as_mut() }
 }
 
+#[cfg(feature = "serde")]
+impl<const LEN: usize> serde::Serialize for KeyBytes<LEN> {
+    fn serialize<S: serde::Serializer>(&self, ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const LEN: usize> serde::Deserialize<'de> for KeyBytes<LEN> {
+    fn deserialize<D: serde::Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        struct Visitor<const LEN: usize>;
+
+        impl<const LEN: usize> serde::de::Visitor<'_> for Visitor<LEN> {
+            type Value = KeyBytes<LEN>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a base58-encoded {LEN}-byte key")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                v.parse().map_err(E::custom)
+            }
+        }
+
+        de.deserialize_str(Visitor)
+    }
+}
+
 type KeyFromSliceError = std::array::TryFromSliceError;
 
 impl<const LEN: usize> TryFrom<&[u8]> for KeyBytes<LEN> {
@@ -458,21 +907,50 @@ pub enum PrefilterError {
     /// An error occurred while parsing a public key as a [`Pubkey`].
     #[error("Invalid pubkey {}", bs58::encode(.0).into_string())]
     BadPubkey(Vec<u8>, std::array::TryFromSliceError),
+    /// An error occurred while decoding base58-encoded filter data.
+    #[error("Invalid base58 filter data {0:?}")]
+    BadBase58(String, bs58::decode::Error),
 }
 
 /// A builder for constructing a prefilter.
 #[derive(Debug, Default)]
 #[must_use = "Consider calling .build() on this builder"]
+#[allow(clippy::struct_excessive_bools)]
 pub struct PrefilterBuilder {
     error: Option<PrefilterError>,
     slots: bool,
+    /// Matching [`SlotPrefilter::all_statuses`]
+    slot_statuses: bool,
     block_metas: bool,
+    blocks: bool,
+    entries: bool,
+    transaction_statuses: bool,
+    /// Matching [`BlockPrefilter::accounts_include`]
+    block_accounts_include: Option<HashSet<Pubkey>>,
+    /// Matching [`BlockPrefilter::include_transactions`]
+    block_include_transactions: bool,
+    /// Matching [`BlockPrefilter::include_accounts`]
+    block_include_accounts: bool,
+    /// Matching [`BlockPrefilter::include_entries`]
+    block_include_entries: bool,
     accounts: Option<HashSet<Pubkey>>,
     account_owners: Option<HashSet<Pubkey>>,
+    /// Filters every set in [`AccountPrefilter::filters`] must include
+    account_filters: Vec<AccountFilter>,
+    /// Alternatives for [`AccountPrefilter::filters`]
+    account_filters_any: Option<Vec<Vec<AccountFilter>>>,
+    /// Matching [`AccountPrefilter::data_slices`]
+    account_data_slices: Vec<DataSlice>,
     /// Matching [`TransactionPrefilter::accounts_include`]
     transaction_accounts_include: Option<HashSet<Pubkey>>,
     /// Matching [`TransactionPrefilter::accounts_required`]
     transaction_accounts_required: Option<HashSet<Pubkey>>,
+    /// Matching [`TransactionPrefilter::accounts_exclude`]
+    transaction_accounts_exclude: Option<HashSet<Pubkey>>,
+    /// Matching [`TransactionPrefilter::failed`]
+    transaction_failed: Option<FilterMode>,
+    /// Matching [`TransactionPrefilter::vote`]
+    transaction_vote: Option<FilterMode>,
 }
 
 fn set_opt<T>(opt: &mut Option<T>, field: &'static str, val: T) -> Result<(), PrefilterError> {
@@ -506,34 +984,96 @@ impl PrefilterBuilder {
             error,
             accounts,
             account_owners,
+            account_filters,
+            account_filters_any,
+            account_data_slices,
             slots,
+            slot_statuses,
             block_metas,
+            blocks,
+            entries,
+            transaction_statuses,
+            block_accounts_include,
+            block_include_transactions,
+            block_include_accounts,
+            block_include_entries,
             transaction_accounts_include,
             transaction_accounts_required,
+            transaction_accounts_exclude,
+            transaction_failed,
+            transaction_vote,
         } = self;
         if let Some(err) = error {
             return Err(err);
         }
 
+        let filters = match account_filters_any {
+            Some(sets) => sets
+                .into_iter()
+                .map(|mut set| {
+                    set.extend(account_filters.iter().cloned());
+                    set
+                })
+                .collect(),
+            None if account_filters.is_empty() => vec![],
+            None => vec![account_filters],
+        };
+
         let account = AccountPrefilter {
             accounts: accounts.unwrap_or_default(),
             owners: account_owners.unwrap_or_default(),
+            filters,
+            data_slices: account_data_slices,
         };
 
         let transaction = TransactionPrefilter {
             accounts_include: transaction_accounts_include.unwrap_or_default(),
             accounts_required: transaction_accounts_required.unwrap_or_default(),
+            accounts_exclude: transaction_accounts_exclude.unwrap_or_default(),
+            // Status subscribers are usually after the outcome of every
+            // transaction, failures included
+            failed: transaction_failed.unwrap_or(if transaction_statuses {
+                FilterMode::Include
+            } else {
+                FilterMode::Exclude
+            }),
+            vote: transaction_vote.unwrap_or(FilterMode::Include),
         };
 
         let block_meta = BlockMetaPrefilter {};
 
-        let slot = SlotPrefilter {};
+        let slot = SlotPrefilter {
+            all_statuses: slot_statuses,
+        };
+
+        let block = BlockPrefilter {
+            accounts_include: block_accounts_include.unwrap_or_default(),
+            include_transactions: block_include_transactions,
+            include_accounts: block_include_accounts,
+            include_entries: block_include_entries,
+        };
+
+        let entry = EntryPrefilter {};
+
+        // Transaction filters apply to transaction statuses instead of full
+        // transactions if requested
+        let (transaction, transaction_status) = if transaction_statuses {
+            (None, Some(transaction))
+        } else {
+            (
+                (transaction != TransactionPrefilter::default()).then_some(transaction),
+                None,
+            )
+        };
 
         Ok(Prefilter {
             account: (account != AccountPrefilter::default()).then_some(account),
-            transaction: (transaction != TransactionPrefilter::default()).then_some(transaction),
+            transaction,
             block_meta: block_metas.then_some(block_meta),
-            slot: slots.then_some(slot),
+            slot: (slots || slot_statuses).then_some(slot),
+            block: (blocks || block != BlockPrefilter::default()).then_some(block),
+            entry: entries.then_some(entry),
+            transaction_status,
         })
     }
 
@@ -553,6 +1093,83 @@ impl PrefilterBuilder {
         })
     }
 
+    /// Set prefilter will request every status transition of each slot,
+    /// including slots that die, regardless of the subscription commitment
+    /// level.
+    pub fn slot_statuses(self) -> Self {
+        self.mutate(|this| {
+            this.slot_statuses = true;
+            Ok(())
+        })
+    }
+
+    /// Set prefilter will request full block updates.
+    pub fn blocks(self) -> Self {
+        self.mutate(|this| {
+            this.blocks = true;
+            Ok(())
+        })
+    }
+
+    /// Set the accounts for the block prefilter.  Only blocks involving at
+    /// least one of these accounts will be retrieved.
+    pub fn block_accounts_include<I: IntoIterator>(self, it: I) -> Self
+    where I::Item: AsRef<[u8]> {
+        self.mutate(|this| {
+            set_opt(
+                &mut this.block_accounts_include,
+                "block_accounts_include",
+                collect_pubkeys(it)?,
+            )
+        })
+    }
+
+    /// Set retrieved blocks to include their transactions.
+    pub fn block_include_transactions(self) -> Self {
+        self.mutate(|this| {
+            this.block_include_transactions = true;
+            Ok(())
+        })
+    }
+
+    /// Set retrieved blocks to include their account updates.
+    pub fn block_include_accounts(self) -> Self {
+        self.mutate(|this| {
+            this.block_include_accounts = true;
+            Ok(())
+        })
+    }
+
+    /// Set retrieved blocks to include their entries.
+    pub fn block_include_entries(self) -> Self {
+        self.mutate(|this| {
+            this.block_include_entries = true;
+            Ok(())
+        })
+    }
+
+    /// Set prefilter will request entry updates.
+    pub fn entries(self) -> Self {
+        self.mutate(|this| {
+            this.entries = true;
+            Ok(())
+        })
+    }
+
+    /// Set prefilter will request transaction status updates instead of full
+    /// transaction updates.
+    ///
+    /// **Note:** The `transaction_*` filters set on this builder apply to the
+    /// transaction statuses, except that failed transactions are included
+    /// unless set otherwise with
+    /// [`transaction_failed`](Self::transaction_failed).
+    pub fn transaction_statuses(self) -> Self {
+        self.mutate(|this| {
+            this.transaction_statuses = true;
+            Ok(())
+        })
+    }
+
     /// Set the accounts that this prefilter will match.
     pub fn accounts<I: IntoIterator>(self, it: I) -> Self
     where I::Item: AsRef<[u8]> {
@@ -571,6 +1188,87 @@ impl PrefilterBuilder {
         })
     }
 
+    /// Add a filter that every matched account must satisfy.
+    pub fn account_filter(self, filter: AccountFilter) -> Self {
+        self.mutate(|this| {
+            this.account_filters.push(filter);
+            Ok(())
+        })
+    }
+
+    /// Match accounts whose data contains the given bytes at the given offset.
+    pub fn account_memcmp<B: Into<Vec<u8>>>(self, offset: u64, bytes: B) -> Self {
+        self.account_filter(AccountFilter::Memcmp {
+            offset,
+            bytes: bytes.into(),
+        })
+    }
+
+    /// Match accounts whose data contains the given base58-encoded bytes at
+    /// the given offset.
+    pub fn account_memcmp_base58(self, offset: u64, data: &str) -> Self {
+        self.mutate(|this| {
+            let bytes = bs58::decode(data)
+                .into_vec()
+                .map_err(|e| PrefilterError::BadBase58(data.to_owned(), e))?;
+            this.account_filters
+                .push(AccountFilter::Memcmp { offset, bytes });
+            Ok(())
+        })
+    }
+
+    /// Match accounts whose data is exactly `size` bytes long.
+    pub fn account_datasize(self, size: u64) -> Self {
+        self.account_filter(AccountFilter::DataSize(size))
+    }
+
+    /// Match only SPL token accounts in a valid, initialized state.
+    pub fn account_token_account_state(self) -> Self {
+        self.account_filter(AccountFilter::TokenAccountState)
+    }
+
+    /// Match accounts whose lamport balance satisfies the given comparison.
+    pub fn account_lamports(self, cmp: LamportsCmp) -> Self {
+        self.account_filter(AccountFilter::Lamports(cmp))
+    }
+
+    /// Set alternative sets of account filters, matching accounts that
+    /// satisfy every filter of at least one set in addition to any filters
+    /// added with [`account_filter`](Self::account_filter).
+    pub fn account_filters_any<I: IntoIterator>(self, it: I) -> Self
+    where I::Item: IntoIterator<Item = AccountFilter> {
+        self.mutate(|this| {
+            set_opt(
+                &mut this.account_filters_any,
+                "account_filters_any",
+                it.into_iter().map(|s| s.into_iter().collect()).collect(),
+            )
+        })
+    }
+
+    /// Match accounts whose data starts with any of the given discriminators.
+    pub fn account_discriminators<I: IntoIterator>(self, it: I) -> Self
+    where I::Item: AsRef<[u8]> {
+        self.account_filters_any(it.into_iter().map(|d| {
+            [AccountFilter::Memcmp {
+                offset: 0,
+                bytes: d.as_ref().to_vec(),
+            }]
+        }))
+    }
+
+    /// Add a range of account data to retrieve.  If no slices are added, the
+    /// full account data is retrieved.
+    ///
+    /// See [`DataSlice`] for how the received data is laid out.
+    pub fn account_data_slice(self, offset: u64, length: u64) -> Self {
+        self.mutate(|this| {
+            this.account_data_slices
+                .push(DataSlice::new(offset, length));
+            Ok(())
+        })
+    }
+
     /// Set the required accounts for this transaction prefilter.
     ///  The accounts set here **must** be present in the transaction.
     ///
@@ -601,6 +1299,34 @@ impl PrefilterBuilder {
             )
         })
     }
+
+    /// Set the excluded accounts for this transaction prefilter.
+    ///
+    /// **Note:** If the transaction includes any of the accounts set here, the
+    /// transaction will not be retrieved.
+    pub fn transaction_accounts_exclude<I: IntoIterator>(self, it: I) -> Self
+    where I::Item: AsRef<[u8]> {
+        self.mutate(|this| {
+            set_opt(
+                &mut this.transaction_accounts_exclude,
+                "transaction_accounts_exclude",
+                collect_pubkeys(it)?,
+            )
+        })
+    }
+
+    /// Set whether failed transactions are matched by this transaction
+    /// prefilter.  Failed transactions are excluded by default, unless
+    /// requesting [transaction statuses](Self::transaction_statuses).
+    pub fn transaction_failed(self, mode: FilterMode) -> Self {
+        self.mutate(|this| set_opt(&mut this.transaction_failed, "transaction_failed", mode))
+    }
+
+    /// Set whether vote transactions are matched by this transaction
+    /// prefilter.  Vote transactions are included by default.
+    pub fn transaction_vote(self, mode: FilterMode) -> Self {
+        self.mutate(|this| set_opt(&mut this.transaction_vote, "transaction_vote", mode))
+    }
 }
 
 /// A collection of filters for a Vixen subscription.
@@ -619,11 +1345,135 @@ impl Filters {
             parsers_filters: filters,
         }
     }
+
+    /// Get the parser ID each fanned-out account subscription filter was
+    /// generated for, keyed by the filter name.
+    ///
+    /// Account prefilters with several alternative sets of data filters are
+    /// subscribed to as one filter per set, named `<parser ID>#<n>` unless
+    /// that name is taken by a parser, in which case more `#` separators are
+    /// added.  Every other subscription filter is named after its parser ID.
+    #[must_use]
+    pub fn parser_ids(&self) -> HashMap<String, String> {
+        self.account_filters()
+            .into_iter()
+            .filter(|(name, (id, ..))| name != id)
+            .map(|(name, (id, ..))| (name, id.to_owned()))
+            .collect()
+    }
+
+    /// Name the account subscription filters, mapping each name to the parser
+    /// ID, prefilter and set of data filters it was generated for.
+    fn account_filters(&self) -> BTreeMap<String, (&str, &AccountPrefilter, &[AccountFilter])> {
+        let mut names = BTreeMap::new();
+        let mut fanned_out = vec![];
+
+        for (id, account) in self
+            .parsers_filters
+            .iter()
+            .filter_map(|(k, v)| Some((k, v.account.as_ref()?)))
+        {
+            match &*account.filters {
+                [] => {
+                    names.insert(id.clone(), (id.as_str(), account, &[][..]));
+                },
+                [set] => {
+                    names.insert(id.clone(), (id.as_str(), account, &**set));
+                },
+                sets => fanned_out.push((id, account, sets)),
+            }
+        }
+
+        // Filters within a subscription filter are all required to match, so
+        // each alternative gets its own
+        fanned_out.sort_unstable_by_key(|&(id, ..)| id);
+
+        for (id, account, sets) in fanned_out {
+            let name = |sep: &str, i: usize| format!("{id}{sep}{i}");
+            let mut sep = "#".to_owned();
+
+            while (0..sets.len()).any(|i| {
+                let name = name(&sep, i);
+                self.parsers_filters.contains_key(&name) || names.contains_key(&name)
+            }) {
+                sep.push('#');
+            }
+
+            for (i, set) in sets.iter().enumerate() {
+                names.insert(name(&sep, i), (id.as_str(), account, &**set));
+            }
+        }
+
+        names
+    }
+
+    /// Get the merged account data slices to request for this subscription.
+    /// Account updates are read in this layout whichever filter they matched,
+    /// so sources subscribing over several streams must request these slices
+    /// on every stream.
+    ///
+    /// Returns an empty list, requesting the full account data, if any
+    /// parser subscribed to accounts did not request specific slices.
+    #[must_use]
+    pub fn account_data_slices(&self) -> Vec<DataSlice> {
+        let mut slices = vec![];
+
+        for account in self
+            .parsers_filters
+            .values()
+            .filter_map(|f| f.account.as_ref())
+        {
+            if account.data_slices.is_empty() {
+                return vec![];
+            }
+
+            slices.extend_from_slice(&account.data_slices);
+        }
+
+        DataSlice::merge_all(slices)
+    }
+}
+
+impl From<&AccountFilter> for SubscribeRequestFilterAccountsFilter {
+    fn from(value: &AccountFilter) -> Self {
+        use geyser::{
+            subscribe_request_filter_accounts_filter::Filter,
+            subscribe_request_filter_accounts_filter_lamports::Cmp,
+            subscribe_request_filter_accounts_filter_memcmp::Data,
+            SubscribeRequestFilterAccountsFilterLamports,
+            SubscribeRequestFilterAccountsFilterMemcmp,
+        };
+
+        let filter = match value {
+            AccountFilter::Memcmp { offset, bytes } => {
+                Filter::Memcmp(SubscribeRequestFilterAccountsFilterMemcmp {
+                    offset: *offset,
+                    data: Some(Data::Bytes(bytes.clone())),
+                })
+            },
+            AccountFilter::DataSize(size) => Filter::Datasize(*size),
+            AccountFilter::TokenAccountState => Filter::TokenAccountState(true),
+            AccountFilter::Lamports(cmp) => {
+                Filter::Lamports(SubscribeRequestFilterAccountsFilterLamports {
+                    cmp: Some(match *cmp {
+                        LamportsCmp::Eq(v) => Cmp::Eq(v),
+                        LamportsCmp::Ne(v) => Cmp::Ne(v),
+                        LamportsCmp::Lt(v) => Cmp::Lt(v),
+                        LamportsCmp::Gt(v) => Cmp::Gt(v),
+                    }),
+                })
+            },
+        };
+
+        Self {
+            filter: Some(filter),
+        }
+    }
 }
 
 /// Type mirroring the `CommitmentLevel` enum in the `geyser` crate but serializable.
 /// Used to avoid need for custom deserialization logic.
-#[derive(Debug, Clone, Copy, Deserialize, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, clap::ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum CommitmentLevel {
     /// Processed
@@ -644,72 +1494,316 @@ impl From<geyser::CommitmentLevel> for CommitmentLevel {
     }
 }
 
+impl From<&TransactionPrefilter> for SubscribeRequestFilterTransactions {
+    fn from(value: &TransactionPrefilter) -> Self {
+        SubscribeRequestFilterTransactions {
+            vote: value.vote.as_geyser(),
+            failed: value.failed.as_geyser(),
+            signature: None,
+            account_include: value
+                .accounts_include
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            account_exclude: value
+                .accounts_exclude
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+            account_required: value
+                .accounts_required
+                .iter()
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
 impl From<Filters> for SubscribeRequest {
     fn from(value: Filters) -> Self {
         SubscribeRequest {
             accounts: value
-                .parsers_filters
-                .iter()
-                .filter_map(|(k, v)| {
-                    let v = v.account.as_ref()?;
-
-                    Some((k.clone(), SubscribeRequestFilterAccounts {
+                .account_filters()
+                .into_iter()
+                .map(|(k, (_, v, filters))| {
+                    (k, SubscribeRequestFilterAccounts {
                         account: v.accounts.iter().map(ToString::to_string).collect(),
                         owner: v.owners.iter().map(ToString::to_string).collect(),
-                        // TODO: probably a good thing to look into
-                        filters: vec![],
+                        filters: filters.iter().map(Into::into).collect(),
                         // We receive all accounts updates
                         nonempty_txn_signature: None,
-                    }))
+                    })
                 })
                 .collect(),
             slots: value
                 .parsers_filters
-                .keys()
-                .map(|k| {
+                .iter()
+                .map(|(k, v)| {
+                    let all = v.slot.is_some_and(|s| s.all_statuses);
+
                     (k.clone(), SubscribeRequestFilterSlots {
-                        filter_by_commitment: Some(true),
-                        interslot_updates: None,
+                        filter_by_commitment: Some(!all),
+                        interslot_updates: all.then_some(true),
                     })
                 })
                 .collect(),
             transactions: value
+                .parsers_filters
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.transaction.as_ref()?.into())))
+                .collect(),
+            transactions_status: value
+                .parsers_filters
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.transaction_status.as_ref()?.into())))
+                .collect(),
+            blocks: value
                 .parsers_filters
                 .iter()
                 .filter_map(|(k, v)| {
-                    let v = v.transaction.as_ref()?;
+                    let v = v.block.as_ref()?;
 
-                    Some((k.clone(), SubscribeRequestFilterTransactions {
-                        vote: None,
-                        // TODO: make this configurable
-                        failed: Some(false),
-                        signature: None,
+                    Some((k.clone(), SubscribeRequestFilterBlocks {
                         account_include: v
                             .accounts_include
                             .iter()
                             .map(ToString::to_string)
                             .collect(),
-                        account_exclude: [].into_iter().collect(),
-                        account_required: v
-                            .accounts_required
-                            .iter()
-                            .map(ToString::to_string)
-                            .collect(),
+                        include_transactions: Some(v.include_transactions),
+                        include_accounts: Some(v.include_accounts),
+                        include_entries: Some(v.include_entries),
                     }))
                 })
                 .collect(),
-            transactions_status: [].into_iter().collect(),
-            blocks: [].into_iter().collect(),
             blocks_meta: value
                 .parsers_filters
                 .keys()
                 .map(|k| (k.clone(), SubscribeRequestFilterBlocksMeta {}))
                 .collect(),
-            entry: [].into_iter().collect(),
+            entry: value
+                .parsers_filters
+                .iter()
+                .filter(|(_, v)| v.entry.is_some())
+                .map(|(k, _)| (k.clone(), SubscribeRequestFilterEntry {}))
+                .collect(),
             commitment: None,
-            accounts_data_slice: vec![],
+            accounts_data_slice: value
+                .account_data_slices()
+                .into_iter()
+                .map(
+                    |DataSlice { offset, length }| SubscribeRequestAccountsDataSlice {
+                        offset,
+                        length,
+                    },
+                )
+                .collect(),
             ping: None,
             from_slot: None,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use yellowstone_grpc_proto::geyser::SubscribeUpdateAccountInfo;
+
+    use super::*;
+
+    #[test]
+    fn test_account_filters() {
+        let owner = [1; 32];
+        let a = Prefilter::builder()
+            .account_owners([owner])
+            .account_discriminators([[1; 8], [2; 8]])
+            .account_datasize(100)
+            .build()
+            .unwrap();
+        let b = Prefilter::builder()
+            .account_owners([owner])
+            .account_memcmp(0, [3; 8])
+            .build()
+            .unwrap();
+
+        let mut merged = a.clone();
+        merged.merge(b);
+        assert_eq!(merged.account.as_ref().unwrap().filters.len(), 3);
+        merged.merge(
+            Prefilter::builder()
+                .account_owners([owner])
+                .build()
+                .unwrap(),
+        );
+        assert!(merged.account.unwrap().filters.is_empty());
+
+        let req: SubscribeRequest = Filters::new(HashMap::from([("p".to_owned(), a)])).into();
+        let mut keys: Vec<_> = req.accounts.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["p#0", "p#1"]);
+        assert_eq!(req.accounts["p#1"].filters.len(), 2);
+    }
+
+    #[test]
+    fn test_filter_parser_ids() {
+        let owner = [1; 32];
+        let fanned_out = Prefilter::builder()
+            .account_owners([owner])
+            .account_discriminators([[1; 8], [2; 8]])
+            .build()
+            .unwrap();
+        let single = Prefilter::builder()
+            .account_owners([owner])
+            .build()
+            .unwrap();
+        let filters = Filters::new(HashMap::from([
+            ("p".to_owned(), fanned_out),
+            ("p#1".to_owned(), single),
+        ]));
+
+        let parser_ids = filters.parser_ids();
+        assert_eq!(parser_ids.len(), 2);
+        assert_eq!(parser_ids["p##0"], "p");
+        assert_eq!(parser_ids["p##1"], "p");
+
+        let req: SubscribeRequest = filters.into();
+        let mut keys: Vec<_> = req.accounts.keys().map(String::as_str).collect();
+        keys.sort_unstable();
+        assert_eq!(keys, ["p##0", "p##1", "p#1"]);
+    }
+
+    #[test]
+    fn test_transaction_prefilter_merge() {
+        let bot = [1; 32];
+        let aggregator = [2; 32];
+        let mut a = Prefilter::builder()
+            .transaction_accounts_include([[3; 32]])
+            .transaction_accounts_exclude([bot, aggregator])
+            .transaction_failed(FilterMode::Only)
+            .build()
+            .unwrap();
+        let b = Prefilter::builder()
+            .transaction_accounts_include([[4; 32]])
+            .transaction_accounts_exclude([bot])
+            .build()
+            .unwrap();
+
+        a.merge(b);
+        let txn = a.transaction.unwrap();
+        assert_eq!(txn.accounts_exclude, HashSet::from([bot.into()]));
+        assert_eq!(txn.failed, FilterMode::Include);
+        assert_eq!(txn.vote, FilterMode::Include);
+    }
+
+    #[test]
+    fn test_block_and_status_prefilters() {
+        let mut a = Prefilter::builder()
+            .block_accounts_include([[1; 32]])
+            .block_include_transactions()
+            .build()
+            .unwrap();
+        let b = Prefilter::builder()
+            .block_include_entries()
+            .transaction_accounts_include([[2; 32]])
+            .transaction_statuses()
+            .build()
+            .unwrap();
+        assert!(b.transaction.is_none());
+        assert_eq!(
+            b.transaction_status.as_ref().unwrap().failed,
+            FilterMode::Include
+        );
+
+        let failed_excluded = Prefilter::builder()
+            .transaction_statuses()
+            .transaction_failed(FilterMode::Exclude)
+            .build()
+            .unwrap();
+        assert_eq!(
+            failed_excluded.transaction_status.unwrap().failed,
+            FilterMode::Exclude
+        );
+
+        a.merge(b);
+        let block = a.block.unwrap();
+        assert!(block.accounts_include.is_empty());
+        assert!(block.include_transactions && block.include_entries);
+        assert!(!block.include_accounts);
+        assert!(a.entry.is_none());
+    }
+
+    #[test]
+    fn test_slot_statuses() {
+        let slots = Prefilter::builder().slots().build().unwrap();
+        let mut statuses = Prefilter::builder().slot_statuses().build().unwrap();
+        assert!(statuses.slot.unwrap().all_statuses);
+
+        statuses.merge(slots.clone());
+        assert!(statuses.slot.unwrap().all_statuses);
+
+        let req: SubscribeRequest =
+            Filters::new([("p".into(), slots), ("s".into(), statuses)].into()).into();
+        assert_eq!(req.slots["p"].filter_by_commitment, Some(true));
+        assert_eq!(req.slots["s"].filter_by_commitment, Some(false));
+        assert_eq!(req.slots["s"].interslot_updates, Some(true));
+        assert!(CommitmentLevel::Processed < CommitmentLevel::Finalized);
+    }
+
+    #[test]
+    fn test_account_data_slices() {
+        let header = Prefilter::builder()
+            .account_owners([[1; 32]])
+            .account_data_slice(0, 8)
+            .account_data_slice(8, 32)
+            .build()
+            .unwrap();
+        let tail = Prefilter::builder()
+            .account_owners([[2; 32]])
+            .account_data_slice(64, 8)
+            .account_data_slice(30, 4)
+            .build()
+            .unwrap();
+        let filters =
+            Filters::new([("header".into(), header.clone()), ("tail".into(), tail)].into());
+
+        let slices = filters.account_data_slices();
+        assert_eq!(slices, [DataSlice::new(0, 40), DataSlice::new(64, 8)]);
+
+        let update = |data: &[u8]| AccountUpdate {
+            update: SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    data: DataSlice::slice(&slices, data),
+                    ..SubscribeUpdateAccountInfo::default()
+                }),
+                ..SubscribeUpdateAccount::default()
+            },
+            data_slices: slices.clone().into(),
+        };
+
+        let data: Vec<u8> = (0..100).collect();
+        let account = update(&data);
+        assert_eq!(account.account.as_ref().unwrap().data.len(), 48);
+        assert_eq!(
+            account.data_slice(DataSlice::new(8, 32)),
+            Some(&data[8..40])
+        );
+        assert_eq!(
+            account.data_slice(DataSlice::new(64, 8)),
+            Some(&data[64..72])
+        );
+        assert_eq!(account.data_slice(DataSlice::new(40, 4)), None);
+
+        // The second slice is not contained in a shorter account
+        let account = update(&data[..70]);
+        assert_eq!(
+            account.data_slice(DataSlice::new(30, 4)),
+            Some(&data[30..34])
+        );
+        assert_eq!(account.data_slice(DataSlice::new(64, 8)), None);
+
+        let full = Prefilter::builder()
+            .account_owners([[3; 32]])
+            .build()
+            .unwrap();
+        let filters = Filters::new([("header".into(), header), ("full".into(), full)].into());
+        assert!(filters.account_data_slices().is_empty());
+    }
+}
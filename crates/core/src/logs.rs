@@ -0,0 +1,464 @@
+//! Helpers for attributing transaction log messages to the instructions that
+//! emitted them.
+
+use std::ops::Range;
+
+use base64::Engine;
+
+use crate::Pubkey;
+
+/// The line the runtime appends in place of any further output once a
+/// transaction exceeds its log size limit.
+pub const LOG_TRUNCATED: &str = "Log truncated";
+
+/// A single line of transaction log output, classified by the runtime
+/// message that produced it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogLine<'a> {
+    /// `Program <id> invoke [<depth>]`
+    Invoke {
+        /// The invoked program.
+        program: Pubkey,
+        /// The invocation depth, starting at 1 for outer instructions.
+        depth: u32,
+    },
+    /// `Program <id> success`
+    Success {
+        /// The program that completed.
+        program: Pubkey,
+    },
+    /// `Program <id> failed: <error>`
+    Failed {
+        /// The program that failed.
+        program: Pubkey,
+        /// The error message reported by the runtime.
+        error: &'a str,
+    },
+    /// `Program <id> consumed <n> of <m> compute units`
+    Consumed {
+        /// The program that consumed the compute units.
+        program: Pubkey,
+        /// The number of compute units consumed by the invocation.
+        consumed: u64,
+        /// The compute unit budget available to the invocation.
+        limit: u64,
+    },
+    /// `Program return: <id> <base64 data>`
+    Return {
+        /// The program that set the return data.
+        program: Pubkey,
+        /// The base64-encoded return data.
+        data: &'a str,
+    },
+    /// `Program data: <base64 data>...`, emitted by `sol_log_data`
+    Data(&'a str),
+    /// `Program log: <message>`, emitted by `sol_log`
+    Log(&'a str),
+    /// The log output was truncated at this point.
+    Truncated,
+    /// Any other line, such as a `Program consumption:` line or a line whose
+    /// program ID could not be parsed.
+    Other(&'a str),
+}
+
+impl<'a> LogLine<'a> {
+    /// Classify a single log line.
+    #[must_use]
+    pub fn parse(line: &'a str) -> Self {
+        if line == LOG_TRUNCATED {
+            return Self::Truncated;
+        }
+
+        let Some(rest) = line.strip_prefix("Program ") else {
+            return Self::Other(line);
+        };
+
+        if let Some(msg) = rest.strip_prefix("log: ") {
+            return Self::Log(msg);
+        }
+
+        if let Some(data) = rest.strip_prefix("data: ") {
+            return Self::Data(data);
+        }
+
+        if let Some(ret) = rest.strip_prefix("return: ") {
+            return ret
+                .split_once(' ')
+                .and_then(|(program, data)| {
+                    Some(Self::Return {
+                        program: program.parse().ok()?,
+                        data,
+                    })
+                })
+                .unwrap_or(Self::Other(line));
+        }
+
+        Self::parse_program_event(rest).unwrap_or(Self::Other(line))
+    }
+
+    fn parse_program_event(rest: &'a str) -> Option<Self> {
+        let (program, event) = rest.split_once(' ')?;
+        let program = program.parse().ok()?;
+
+        if event == "success" {
+            return Some(Self::Success { program });
+        }
+
+        if let Some(error) = event.strip_prefix("failed: ") {
+            return Some(Self::Failed { program, error });
+        }
+
+        if let Some(depth) = event
+            .strip_prefix("invoke [")
+            .and_then(|d| d.strip_suffix(']'))
+        {
+            return Some(Self::Invoke {
+                program,
+                depth: depth.parse().ok()?,
+            });
+        }
+
+        let (consumed, limit) = event
+            .strip_prefix("consumed ")?
+            .strip_suffix(" compute units")?
+            .split_once(" of ")?;
+
+        Some(Self::Consumed {
+            program,
+            consumed: consumed.parse().ok()?,
+            limit: limit.parse().ok()?,
+        })
+    }
+}
+
+/// The outcome of a program invocation as reported in the logs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InvokeResult {
+    /// The invocation completed successfully.
+    Success,
+    /// The invocation failed with the given error message.
+    Failed(String),
+}
+
+/// The log output attributed to a single instruction invocation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InstructionLogs {
+    /// The range of the transaction log messages covering this invocation,
+    /// from its `invoke` line through its `success` or `failed` line,
+    /// including the output of any inner invocations.
+    pub range: Range<usize>,
+    /// The number of compute units consumed by this invocation, including
+    /// its inner invocations.
+    pub compute_units_consumed: Option<u64>,
+    /// The outcome of the invocation, or `None` if the logs end before the
+    /// invocation completed.
+    pub result: Option<InvokeResult>,
+    /// Whether the logs end before this invocation completed, usually
+    /// because they were truncated, in which case `range` extends to the end
+    /// of the logs and output may be missing.
+    pub truncated: bool,
+}
+
+impl InstructionLogs {
+    /// Get the log lines covering this invocation.
+    ///
+    /// Returns an empty slice if `log_messages` is not the log output this
+    /// invocation was attributed from.
+    #[inline]
+    #[must_use]
+    pub fn lines<'a>(&self, log_messages: &'a [String]) -> &'a [String] {
+        log_messages.get(self.range.clone()).unwrap_or_default()
+    }
+
+    /// Iterate over the log lines emitted by this invocation itself,
+    /// excluding the output of its inner invocations.
+    pub fn own_lines<'a>(
+        &self,
+        log_messages: &'a [String],
+    ) -> impl Iterator<Item = &'a String> + 'a {
+        let mut nesting = 0_usize;
+
+        self.lines(log_messages)
+            .iter()
+            .enumerate()
+            .filter(move |&(i, line)| {
+                if i == 0 {
+                    return true;
+                }
+
+                match LogLine::parse(line) {
+                    LogLine::Invoke { .. } => {
+                        nesting += 1;
+                        false
+                    },
+                    LogLine::Success { .. } | LogLine::Failed { .. } if nesting > 0 => {
+                        nesting -= 1;
+                        false
+                    },
+                    _ => nesting == 0,
+                }
+            })
+            .map(|(_, line)| line)
+    }
+}
+
+/// The output of [`attribute`] for a single instruction.
+#[derive(Debug, Default)]
+pub(crate) struct Attributed<'a> {
+    pub logs: Option<InstructionLogs>,
+    pub return_data: Option<(Pubkey, &'a str)>,
+}
+
+struct Frame {
+    program: Pubkey,
+    instruction: Option<usize>,
+    start: usize,
+    consumed: Option<u64>,
+}
+
+impl Frame {
+    fn close(self, out: &mut [Attributed], end: usize, result: Option<InvokeResult>) {
+        let Some(j) = self.instruction else { return };
+
+        out[j].logs = Some(InstructionLogs {
+            range: self.start..end,
+            compute_units_consumed: self.consumed,
+            truncated: result.is_none(),
+            result,
+        });
+    }
+}
+
+/// Attribute the transaction log messages to a list of instructions given in
+/// pre-order as `(program, stack_height)` pairs.
+///
+/// Invocations are matched to the next instruction with the same program
+/// (and stack height, if known), so instructions that produce no `invoke`
+/// line, such as precompiles, are skipped over rather than shifting the
+/// attribution of every following instruction.
+pub(crate) fn attribute<'a>(
+    log_messages: &'a [String],
+    instructions: &[(Pubkey, Option<u32>)],
+) -> Vec<Attributed<'a>> {
+    let mut out: Vec<_> = instructions.iter().map(|_| Attributed::default()).collect();
+    let mut stack: Vec<Frame> = vec![];
+    let mut next = 0;
+
+    for (i, line) in log_messages.iter().enumerate() {
+        match LogLine::parse(line) {
+            LogLine::Invoke { program, depth } => {
+                let instruction = instructions[next..]
+                    .iter()
+                    .position(|&(p, h)| p == program && h.is_none_or(|h| h == depth))
+                    .map(|j| next + j);
+
+                if let Some(j) = instruction {
+                    next = j + 1;
+                }
+
+                stack.push(Frame {
+                    program,
+                    instruction,
+                    start: i,
+                    consumed: None,
+                });
+            },
+            LogLine::Consumed {
+                program, consumed, ..
+            } => {
+                if let Some(frame) = stack.last_mut().filter(|f| f.program == program) {
+                    frame.consumed = Some(consumed);
+                }
+            },
+            LogLine::Return { program, data } => {
+                if let Some(j) = stack
+                    .last()
+                    .filter(|f| f.program == program)
+                    .and_then(|f| f.instruction)
+                {
+                    out[j].return_data = Some((program, data));
+                }
+            },
+            LogLine::Success { .. } => {
+                if let Some(frame) = stack.pop() {
+                    frame.close(&mut out, i + 1, Some(InvokeResult::Success));
+                }
+            },
+            LogLine::Failed { error, .. } => {
+                if let Some(frame) = stack.pop() {
+                    let result = InvokeResult::Failed(error.to_owned());
+                    frame.close(&mut out, i + 1, Some(result));
+                }
+            },
+            LogLine::Truncated => break,
+            LogLine::Data(_) | LogLine::Log(_) | LogLine::Other(_) => (),
+        }
+    }
+
+    // Any invocation still open at this point never reported its outcome
+    for frame in stack {
+        frame.close(&mut out, log_messages.len(), None);
+    }
+
+    out
+}
+
+/// Iterate over the base64-decoded `Program data:` records logged by
+/// invocations of `program`.
+///
+/// Each record is attributed to the innermost program invoked when it was
+/// logged, so data logged by a program `program` invokes is not included.
+/// Lines carrying several fields yield one record per field, and fields that
+/// are not valid base64 are skipped.
+pub fn program_data<'a, I>(log_messages: I, program: Pubkey) -> impl Iterator<Item = Vec<u8>> + 'a
+where
+    I: IntoIterator<Item = &'a String>,
+    I::IntoIter: 'a,
+{
+    let mut stack = vec![];
+
+    log_messages
+        .into_iter()
+        .map(|line| LogLine::parse(line))
+        .take_while(|line| !matches!(line, LogLine::Truncated))
+        .filter_map(move |line| match line {
+            LogLine::Invoke { program, .. } => {
+                stack.push(program);
+                None
+            },
+            LogLine::Success { .. } | LogLine::Failed { .. } => {
+                stack.pop();
+                None
+            },
+            LogLine::Data(data) if stack.last() == Some(&program) => Some(data),
+            _ => None,
+        })
+        .flat_map(|data| data.split(' '))
+        .filter_map(|field| base64::engine::general_purpose::STANDARD.decode(field).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_log_lines() {
+        let id = "11111111111111111111111111111111";
+        let program: Pubkey = id.parse().unwrap();
+
+        assert_eq!(
+            LogLine::parse(&format!("Program {id} invoke [2]")),
+            LogLine::Invoke { program, depth: 2 }
+        );
+        assert_eq!(
+            LogLine::parse(&format!(
+                "Program {id} consumed 150 of 200000 compute units"
+            )),
+            LogLine::Consumed {
+                program,
+                consumed: 150,
+                limit: 200_000
+            }
+        );
+        assert_eq!(
+            LogLine::parse(&format!("Program {id} failed: custom program error: 0x1")),
+            LogLine::Failed {
+                program,
+                error: "custom program error: 0x1"
+            }
+        );
+        assert_eq!(
+            LogLine::parse(&format!("Program return: {id} AQI=")),
+            LogLine::Return {
+                program,
+                data: "AQI="
+            }
+        );
+        assert_eq!(LogLine::parse("Program log: hi"), LogLine::Log("hi"));
+        assert_eq!(LogLine::parse("Log truncated"), LogLine::Truncated);
+        assert_eq!(
+            LogLine::parse("Program bad invoke [1]"),
+            LogLine::Other("Program bad invoke [1]")
+        );
+    }
+
+    #[test]
+    fn test_attribute_return_data() {
+        let [p1, p2, p3]: [Pubkey; 3] = [1, 2, 3].map(|b| crate::KeyBytes([b; 32]));
+        let lines = [
+            format!("Program {p1} invoke [1]"),
+            format!("Program {p2} invoke [2]"),
+            format!("Program return: {p2} AQI="),
+            format!("Program {p2} success"),
+            format!("Program return: {p2} AwQ="),
+            format!("Program {p1} success"),
+            format!("Program {p1} invoke [1]"),
+            format!("Program return: {p1} BQY="),
+            "Log truncated".to_owned(),
+            format!("Program return: {p1} Bwg="),
+        ];
+
+        // The precompile p3 never logs an invocation and must not shift the
+        // attribution of the following instructions
+        let out = attribute(&lines, &[
+            (p3, None),
+            (p1, Some(1)),
+            (p2, Some(2)),
+            (p1, Some(1)),
+        ]);
+
+        assert_eq!(out[0].return_data, None);
+        assert_eq!(out[1].return_data, None, "return data of another program");
+        assert_eq!(out[2].return_data, Some((p2, "AQI=")));
+        assert_eq!(out[3].return_data, Some((p1, "BQY=")));
+    }
+
+    #[test]
+    fn test_program_data() {
+        let outer = "11111111111111111111111111111111";
+        let inner = "SysvarRent111111111111111111111111111111111";
+        let lines: Vec<String> = [
+            format!("Program {outer} invoke [1]"),
+            "Program data: AQI=".to_owned(),
+            format!("Program {inner} invoke [2]"),
+            "Program data: Aw==".to_owned(),
+            format!("Program {inner} success"),
+            "Program data: BA== !! BQ==".to_owned(),
+            format!("Program {outer} success"),
+        ]
+        .into();
+
+        let records: Vec<_> = program_data(&lines, outer.parse().unwrap()).collect();
+        assert_eq!(records, [vec![1, 2], vec![4], vec![5]]);
+
+        let records: Vec<_> = program_data(&lines, inner.parse().unwrap()).collect();
+        assert_eq!(records, [vec![3]]);
+    }
+
+    #[test]
+    fn test_program_data_framing() {
+        let program = "11111111111111111111111111111111";
+        let token = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
+        let lines: Vec<String> = [
+            format!("Program {program} invoke [1]"),
+            "Program log: Instruction: Buy".to_owned(),
+            format!("Program {token} invoke [2]"),
+            "Program data: AQ==".to_owned(),
+            format!("Program {token} consumed 4645 of 182540 compute units"),
+            format!("Program {token} failed: custom program error: 0x1"),
+            "Program data: Ag==".to_owned(),
+            format!("Program {program} consumed 38120 of 200000 compute units"),
+            format!("Program {program} success"),
+            "Program data: Aw==".to_owned(),
+            format!("Program {program} invoke [1]"),
+            "Program data: BA==".to_owned(),
+            LOG_TRUNCATED.to_owned(),
+        ]
+        .into();
+
+        // Records logged outside the program's invocations are skipped, up
+        // to where the logs were truncated
+        let records: Vec<_> = program_data(&lines, program.parse().unwrap()).collect();
+        assert_eq!(records, [vec![2], vec![4]]);
+    }
+}
@@ -0,0 +1,424 @@
+//! Adapters for deriving new parsers from existing ones without
+//! reimplementing [`Parser::id`] and [`Parser::prefilter`].
+
+use std::{borrow::Cow, fmt, future::Future, sync::Arc};
+
+use crate::{
+    instruction::InstructionUpdate, InstructionUpdateOutput, ParseError, ParseResult, Parser,
+    Prefilter, ProgramParser, Pubkey,
+};
+
+/// Extension methods for adapting the output or prefilter of a [`Parser`].
+///
+/// Adapters that only transform the output of a parser keep its ID, since
+/// they receive exactly the same updates.  To register two adapters of the
+/// same parser with the runtime, give them distinct IDs with
+/// [`ParserExt::with_id`].
+pub trait ParserExt: Parser + Sized {
+    /// Transform the output of this parser.
+    fn map<F, U>(self, f: F) -> Map<Self, F>
+    where F: Fn(Self::Output) -> U {
+        Map { parser: self, f }
+    }
+
+    /// Skip any output for which `pred` returns false, as if the parser had
+    /// returned [`ParseError::Filtered`].
+    fn filter<F>(self, pred: F) -> Filter<Self, F>
+    where F: Fn(&Self::Output) -> bool {
+        Filter { parser: self, pred }
+    }
+
+    /// Transform the output of this parser, skipping any output for which
+    /// `f` returns `None`.
+    fn filter_map<F, U>(self, f: F) -> FilterMap<Self, F>
+    where F: Fn(Self::Output) -> Option<U> {
+        FilterMap { parser: self, f }
+    }
+
+    /// Pass the output of this parser to an asynchronous function, which may
+    /// itself fail or filter the output.
+    fn and_then<F, Fut, U>(self, f: F) -> AndThen<Self, F>
+    where
+        F: Fn(Self::Output) -> Fut,
+        Fut: Future<Output = ParseResult<U>>,
+    {
+        AndThen { parser: self, f }
+    }
+
+    /// Wrap the output of this instruction parser in an
+    /// [`InstructionUpdateOutput`], exposing the data shared by every
+    /// instruction of the transaction to handlers.
+    fn with_shared(self) -> WithShared<Self>
+    where Self: Parser<Input = InstructionUpdate> {
+        WithShared { parser: self }
+    }
+
+    /// Modify the prefilter of this parser.
+    ///
+    /// The ID of the resulting parser is derived from the ID of this parser
+    /// and `name`, which must be unique among the prefilter modifications
+    /// applied to the same parser.
+    fn with_prefilter<F>(self, name: impl Into<Cow<'static, str>>, f: F) -> WithPrefilter<Self, F>
+    where F: Fn(Prefilter) -> Prefilter {
+        WithPrefilter {
+            parser: self,
+            name: name.into(),
+            f,
+        }
+    }
+
+    /// Override the ID of this parser.
+    ///
+    /// **NOTE:** The new ID must not be shared with any parser requesting a
+    /// different prefilter, see [`Parser::id`].
+    fn with_id(self, id: impl Into<Cow<'static, str>>) -> WithId<Self> {
+        WithId {
+            parser: self,
+            id: id.into(),
+        }
+    }
+}
+
+impl<P: Parser> ParserExt for P {}
+
+macro_rules! forward_program_id {
+    ($ty:ident<P $(, $f:ident)?> $(where $($bounds:tt)*)?) => {
+        impl<P: ProgramParser $(, $f)?> ProgramParser for $ty<P $(, $f)?>
+        where
+            Self: Parser,
+            $($($bounds)*)?
+        {
+            #[inline]
+            fn program_id(&self) -> Pubkey { self.parser.program_id() }
+        }
+    };
+}
+
+/// Parser adapter returned by [`ParserExt::map`].
+pub struct Map<P, F> {
+    parser: P,
+    f: F,
+}
+
+impl<P: fmt::Debug, F> fmt::Debug for Map<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Map")
+            .field("parser", &self.parser)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P, F, U> Parser for Map<P, F>
+where
+    P: Parser + Sync,
+    F: Fn(P::Output) -> U + Sync,
+{
+    type Input = P::Input;
+    type Output = U;
+
+    #[inline]
+    fn id(&self) -> Cow<str> { self.parser.id() }
+
+    #[inline]
+    fn prefilter(&self) -> Prefilter { self.parser.prefilter() }
+
+    #[inline]
+    fn order_key(&self, value: &Self::Input) -> Option<Pubkey> { self.parser.order_key(value) }
+
+    fn parse(&self, value: &Self::Input) -> impl Future<Output = ParseResult<U>> + Send {
+        let fut = self.parser.parse(value);
+
+        async move { fut.await.map(&self.f) }
+    }
+}
+
+forward_program_id!(Map<P, F>);
+
+/// Parser adapter returned by [`ParserExt::filter`].
+pub struct Filter<P, F> {
+    parser: P,
+    pred: F,
+}
+
+impl<P: fmt::Debug, F> fmt::Debug for Filter<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Filter")
+            .field("parser", &self.parser)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P, F> Parser for Filter<P, F>
+where
+    P: Parser + Sync,
+    F: Fn(&P::Output) -> bool + Sync,
+{
+    type Input = P::Input;
+    type Output = P::Output;
+
+    #[inline]
+    fn id(&self) -> Cow<str> { self.parser.id() }
+
+    #[inline]
+    fn prefilter(&self) -> Prefilter { self.parser.prefilter() }
+
+    #[inline]
+    fn order_key(&self, value: &Self::Input) -> Option<Pubkey> { self.parser.order_key(value) }
+
+    fn parse(&self, value: &Self::Input) -> impl Future<Output = ParseResult<P::Output>> + Send {
+        let fut = self.parser.parse(value);
+
+        async move {
+            let output = fut.await?;
+
+            if (self.pred)(&output) {
+                Ok(output)
+            } else {
+                Err(ParseError::Filtered)
+            }
+        }
+    }
+}
+
+forward_program_id!(Filter<P, F>);
+
+/// Parser adapter returned by [`ParserExt::filter_map`].
+pub struct FilterMap<P, F> {
+    parser: P,
+    f: F,
+}
+
+impl<P: fmt::Debug, F> fmt::Debug for FilterMap<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FilterMap")
+            .field("parser", &self.parser)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P, F, U> Parser for FilterMap<P, F>
+where
+    P: Parser + Sync,
+    F: Fn(P::Output) -> Option<U> + Sync,
+{
+    type Input = P::Input;
+    type Output = U;
+
+    #[inline]
+    fn id(&self) -> Cow<str> { self.parser.id() }
+
+    #[inline]
+    fn prefilter(&self) -> Prefilter { self.parser.prefilter() }
+
+    #[inline]
+    fn order_key(&self, value: &Self::Input) -> Option<Pubkey> { self.parser.order_key(value) }
+
+    fn parse(&self, value: &Self::Input) -> impl Future<Output = ParseResult<U>> + Send {
+        let fut = self.parser.parse(value);
+
+        async move { (self.f)(fut.await?).ok_or(ParseError::Filtered) }
+    }
+}
+
+forward_program_id!(FilterMap<P, F>);
+
+/// Parser adapter returned by [`ParserExt::and_then`].
+pub struct AndThen<P, F> {
+    parser: P,
+    f: F,
+}
+
+impl<P: fmt::Debug, F> fmt::Debug for AndThen<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AndThen")
+            .field("parser", &self.parser)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P, F, Fut, U> Parser for AndThen<P, F>
+where
+    P: Parser + Sync,
+    F: Fn(P::Output) -> Fut + Sync,
+    Fut: Future<Output = ParseResult<U>> + Send,
+{
+    type Input = P::Input;
+    type Output = U;
+
+    #[inline]
+    fn id(&self) -> Cow<str> { self.parser.id() }
+
+    #[inline]
+    fn prefilter(&self) -> Prefilter { self.parser.prefilter() }
+
+    #[inline]
+    fn order_key(&self, value: &Self::Input) -> Option<Pubkey> { self.parser.order_key(value) }
+
+    fn parse(&self, value: &Self::Input) -> impl Future<Output = ParseResult<U>> + Send {
+        let fut = self.parser.parse(value);
+
+        async move {
+            let fut = (self.f)(fut.await?);
+            fut.await
+        }
+    }
+}
+
+forward_program_id!(AndThen<P, F>);
+
+/// Parser adapter returned by [`ParserExt::with_shared`].
+#[derive(Debug)]
+pub struct WithShared<P> {
+    parser: P,
+}
+
+impl<P> Parser for WithShared<P>
+where P: Parser<Input = InstructionUpdate> + Sync
+{
+    type Input = InstructionUpdate;
+    type Output = InstructionUpdateOutput<P::Output>;
+
+    #[inline]
+    fn id(&self) -> Cow<str> { self.parser.id() }
+
+    #[inline]
+    fn prefilter(&self) -> Prefilter { self.parser.prefilter() }
+
+    #[inline]
+    fn order_key(&self, value: &Self::Input) -> Option<Pubkey> { self.parser.order_key(value) }
+
+    fn parse(
+        &self,
+        value: &InstructionUpdate,
+    ) -> impl Future<Output = ParseResult<Self::Output>> + Send {
+        let fut = self.parser.parse(value);
+        let shared_data = Arc::clone(&value.shared);
+
+        async move {
+            Ok(InstructionUpdateOutput {
+                parsed_ix: fut.await?,
+                shared_data,
+            })
+        }
+    }
+}
+
+forward_program_id!(WithShared<P>);
+
+/// Parser adapter returned by [`ParserExt::with_prefilter`].
+pub struct WithPrefilter<P, F> {
+    parser: P,
+    name: Cow<'static, str>,
+    f: F,
+}
+
+impl<P: fmt::Debug, F> fmt::Debug for WithPrefilter<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("WithPrefilter")
+            .field("parser", &self.parser)
+            .field("name", &self.name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P, F> Parser for WithPrefilter<P, F>
+where
+    P: Parser + Sync,
+    F: Fn(Prefilter) -> Prefilter + Sync,
+{
+    type Input = P::Input;
+    type Output = P::Output;
+
+    fn id(&self) -> Cow<str> { format!("{}[{}]", self.parser.id(), self.name).into() }
+
+    #[inline]
+    fn prefilter(&self) -> Prefilter { (self.f)(self.parser.prefilter()) }
+
+    #[inline]
+    fn order_key(&self, value: &Self::Input) -> Option<Pubkey> { self.parser.order_key(value) }
+
+    #[inline]
+    fn parse(&self, value: &Self::Input) -> impl Future<Output = ParseResult<P::Output>> + Send {
+        self.parser.parse(value)
+    }
+}
+
+forward_program_id!(WithPrefilter<P, F>);
+
+/// Parser adapter returned by [`ParserExt::with_id`].
+#[derive(Debug)]
+pub struct WithId<P> {
+    parser: P,
+    id: Cow<'static, str>,
+}
+
+impl<P: Parser> Parser for WithId<P> {
+    type Input = P::Input;
+    type Output = P::Output;
+
+    #[inline]
+    fn id(&self) -> Cow<str> { Cow::Borrowed(&self.id) }
+
+    #[inline]
+    fn prefilter(&self) -> Prefilter { self.parser.prefilter() }
+
+    #[inline]
+    fn order_key(&self, value: &Self::Input) -> Option<Pubkey> { self.parser.order_key(value) }
+
+    #[inline]
+    fn parse(&self, value: &Self::Input) -> impl Future<Output = ParseResult<P::Output>> + Send {
+        self.parser.parse(value)
+    }
+}
+
+forward_program_id!(WithId<P>);
+
+#[cfg(test)]
+mod tests {
+    use super::ParserExt;
+    use crate::{test_util::block_on, ParseError, ParseResult, Parser, Prefilter};
+
+    #[derive(Debug)]
+    struct Len;
+
+    impl Parser for Len {
+        type Input = Vec<u8>;
+        type Output = usize;
+
+        fn id(&self) -> std::borrow::Cow<str> { "Len".into() }
+
+        fn prefilter(&self) -> Prefilter { Prefilter::builder().slots().build().unwrap() }
+
+        async fn parse(&self, value: &Vec<u8>) -> ParseResult<usize> { Ok(value.len()) }
+    }
+
+    #[test]
+    fn test_adapters() {
+        let parser = Len.map(|n| n * 2).filter(|n| *n > 2);
+        assert_eq!(parser.id(), "Len");
+        assert!(parser.prefilter().slot.is_some());
+        assert_eq!(block_on(parser.parse(&vec![0; 2])).unwrap(), 4);
+        assert!(matches!(
+            block_on(parser.parse(&vec![0; 1])),
+            Err(ParseError::Filtered)
+        ));
+
+        let parser = Len
+            .filter_map(|n| n.checked_sub(1))
+            .and_then(|n| async move { Ok(n.to_string()) });
+        assert_eq!(block_on(parser.parse(&vec![0; 3])).unwrap(), "2");
+        assert!(matches!(
+            block_on(parser.parse(&vec![])),
+            Err(ParseError::Filtered)
+        ));
+
+        let parser = Len.with_prefilter("blocks", |mut p| {
+            p.merge(Prefilter::builder().blocks().build().unwrap());
+            p
+        });
+        assert_eq!(parser.id(), "Len[blocks]");
+        assert!(parser.prefilter().slot.is_some());
+        assert!(parser.prefilter().block.is_some());
+        assert_eq!(Len.with_id("Other").id(), "Other");
+    }
+}
@@ -0,0 +1,373 @@
+//! A parser dispatching instructions to one of many program parsers by
+//! program ID.
+
+use std::{
+    any::Any,
+    borrow::Cow,
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+};
+
+use crate::{
+    instruction::InstructionUpdate, ParseError, ParseResult, Parser, Prefilter, ProgramParser,
+    Pubkey,
+};
+
+/// A type-erased parsed value, the default output of a [`ProgramRouter`].
+pub type BoxAny = Box<dyn Any + Send + Sync>;
+type BoxFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// An error returned when building a [`ProgramRouter`].
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ProgramRouterError {
+    /// Two parsers were registered for the same program.
+    #[error("Parsers {0:?} and {1:?} were both registered for program {2}")]
+    DuplicateProgram(String, String, Pubkey),
+}
+
+/// The output of a [`ProgramRouter`], holding the value produced by the
+/// parser registered for the instruction's program.
+#[derive(Debug, Clone)]
+pub struct RoutedInstruction<T = BoxAny> {
+    /// The program ID of the parsed instruction.
+    pub program: Pubkey,
+    /// The ID of the parser that produced the value.
+    pub parser_id: Arc<str>,
+    /// The parsed value.
+    pub value: T,
+}
+
+impl RoutedInstruction {
+    /// Returns true if the parsed value is of type `T`.
+    #[inline]
+    #[must_use]
+    pub fn is<T: Any>(&self) -> bool { self.value.is::<T>() }
+
+    /// Get a reference to the parsed value if it is of type `T`.
+    #[inline]
+    #[must_use]
+    pub fn downcast_ref<T: Any>(&self) -> Option<&T> { self.value.downcast_ref() }
+
+    /// Take the parsed value if it is of type `T`.
+    ///
+    /// # Errors
+    /// Returns `self` unchanged if the parsed value is not of type `T`.
+    pub fn downcast<T: Any>(self) -> Result<T, Self> {
+        let Self {
+            program,
+            parser_id,
+            value,
+        } = self;
+
+        value.downcast().map(|v| *v).map_err(|value| Self {
+            program,
+            parser_id,
+            value,
+        })
+    }
+}
+
+impl<T> RoutedInstruction<T> {
+    /// Take the parsed value.
+    #[inline]
+    #[must_use]
+    pub fn into_inner(self) -> T { self.value }
+}
+
+trait DynProgramParser<T>: fmt::Debug + Send + Sync {
+    fn prefilter(&self) -> Prefilter;
+
+    fn order_key(&self, value: &InstructionUpdate) -> Option<Pubkey>;
+
+    fn parse<'a>(&'a self, value: &'a InstructionUpdate) -> BoxFuture<'a, ParseResult<T>>;
+}
+
+struct MapOutput<P, F>(P, F);
+
+impl<P: fmt::Debug, F> fmt::Debug for MapOutput<P, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("MapOutput")
+            .field(&self.0)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<P, F, T> DynProgramParser<T> for MapOutput<P, F>
+where
+    P: Parser<Input = InstructionUpdate> + fmt::Debug + Send + Sync,
+    F: Fn(P::Output) -> T + Send + Sync,
+{
+    #[inline]
+    fn prefilter(&self) -> Prefilter { self.0.prefilter() }
+
+    #[inline]
+    fn order_key(&self, value: &InstructionUpdate) -> Option<Pubkey> { self.0.order_key(value) }
+
+    fn parse<'a>(&'a self, value: &'a InstructionUpdate) -> BoxFuture<'a, ParseResult<T>> {
+        let fut = self.0.parse(value);
+
+        Box::pin(async move { fut.await.map(&self.1) })
+    }
+}
+
+#[derive(Debug)]
+struct Route<T> {
+    id: Arc<str>,
+    parser: Box<dyn DynProgramParser<T>>,
+}
+
+/// A builder for a [`ProgramRouter`].
+#[derive(Debug)]
+#[must_use = "Consider calling .build() on this builder"]
+pub struct ProgramRouterBuilder<T = BoxAny> {
+    routes: Vec<(Pubkey, Route<T>)>,
+}
+
+impl<T> Default for ProgramRouterBuilder<T> {
+    fn default() -> Self { Self { routes: vec![] } }
+}
+
+impl ProgramRouterBuilder {
+    /// Register a parser for the instructions of its program.
+    pub fn parser<P>(self, parser: P) -> Self
+    where
+        P: ProgramParser<Input = InstructionUpdate> + fmt::Debug + Send + Sync + 'static,
+        P::Output: Any + Send + Sync,
+    {
+        self.parser_with(parser, |v| Box::new(v) as BoxAny)
+    }
+}
+
+impl<T> ProgramRouterBuilder<T> {
+    /// Register a parser for the instructions of its program, converting its
+    /// output to the output type of the router with `f`.
+    pub fn parser_with<P, F>(mut self, parser: P, f: F) -> Self
+    where
+        P: ProgramParser<Input = InstructionUpdate> + fmt::Debug + Send + Sync + 'static,
+        F: Fn(P::Output) -> T + Send + Sync + 'static,
+    {
+        self.routes.push((parser.program_id(), Route {
+            id: parser.id().into(),
+            parser: Box::new(MapOutput(parser, f)),
+        }));
+        self
+    }
+
+    /// Build the router.
+    ///
+    /// # Errors
+    /// Returns an error if more than one parser was registered for the same
+    /// program.
+    pub fn build(self) -> Result<ProgramRouter<T>, ProgramRouterError> {
+        let mut routes = HashMap::with_capacity(self.routes.len());
+
+        for (program, route) in self.routes {
+            match routes.entry(program) {
+                Entry::Vacant(v) => {
+                    v.insert(route);
+                },
+                Entry::Occupied(o) => {
+                    let Route { id, .. } = o.get();
+                    return Err(ProgramRouterError::DuplicateProgram(
+                        id.to_string(),
+                        route.id.to_string(),
+                        program,
+                    ));
+                },
+            }
+        }
+
+        let mut ids: Vec<_> = routes.values().map(|r: &Route<T>| &*r.id).collect();
+        ids.sort_unstable();
+        let parser_ids = ids.join(",");
+
+        Ok(ProgramRouter {
+            id: format!("ProgramRouter[{parser_ids}]"),
+            parser_ids,
+            routes,
+        })
+    }
+}
+
+#[cfg(feature = "transaction-proto")]
+impl ProgramRouterBuilder<yellowstone_vixen_proto::prost_types::Any> {
+    /// Register a parser for the instructions of its program, encoding its
+    /// output as a Protobuf `Any` message.
+    pub fn proto_parser<P>(self, parser: P) -> Self
+    where P: crate::proto::ParseProto
+            + ProgramParser<Input = InstructionUpdate>
+            + fmt::Debug
+            + Send
+            + Sync
+            + 'static {
+        use yellowstone_vixen_proto::prost::{Message, Name};
+
+        self.parser_with(parser, |v| {
+            let msg = P::output_into_message(v);
+
+            yellowstone_vixen_proto::prost_types::Any {
+                type_url: P::Message::type_url(),
+                value: msg.encode_to_vec(),
+            }
+        })
+    }
+}
+
+/// An instruction parser that routes each instruction to the parser
+/// registered for its program.
+///
+/// Registering many program parsers as separate pipelines means every
+/// instruction of a matching transaction is offered to every parser, each of
+/// which rejects the programs it does not handle.  A router instead looks up
+/// the single parser for each instruction's program, and emits its output as
+/// a [`RoutedInstruction`].  By default the output of each parser is boxed
+/// and can be downcast by handlers, but parsers may also be registered with
+/// [`ProgramRouterBuilder::parser_with`] to convert their output to a common
+/// type, such as a tagged enum.
+///
+/// The prefilter of the router is the union of the prefilters of its parsers,
+/// and its ID is derived from their IDs.
+#[derive(Debug)]
+pub struct ProgramRouter<T = BoxAny> {
+    id: String,
+    parser_ids: String,
+    routes: HashMap<Pubkey, Route<T>>,
+}
+
+impl ProgramRouter {
+    /// Create a new router builder.
+    #[inline]
+    pub fn builder() -> ProgramRouterBuilder { ProgramRouterBuilder::default() }
+}
+
+impl<T> ProgramRouter<T> {
+    /// Iterate over the programs handled by this router.
+    #[inline]
+    pub fn programs(&self) -> impl Iterator<Item = &Pubkey> { self.routes.keys() }
+
+    /// The comma-separated, sorted IDs of the parsers in this router.
+    #[inline]
+    pub(crate) fn parser_ids(&self) -> &str { &self.parser_ids }
+}
+
+impl<T> Parser for ProgramRouter<T> {
+    type Input = InstructionUpdate;
+    type Output = RoutedInstruction<T>;
+
+    #[inline]
+    fn id(&self) -> Cow<str> { Cow::Borrowed(&self.id) }
+
+    fn prefilter(&self) -> Prefilter {
+        self.routes.values().fold(Prefilter::default(), |mut p, r| {
+            p.merge(r.parser.prefilter());
+            p
+        })
+    }
+
+    fn order_key(&self, value: &InstructionUpdate) -> Option<Pubkey> {
+        self.routes.get(&value.program)?.parser.order_key(value)
+    }
+
+    async fn parse(&self, value: &InstructionUpdate) -> ParseResult<RoutedInstruction<T>> {
+        let route = self
+            .routes
+            .get(&value.program)
+            .ok_or(ParseError::Filtered)?;
+
+        Ok(RoutedInstruction {
+            program: value.program,
+            parser_id: Arc::clone(&route.id),
+            value: route.parser.parse(value).await?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{borrow::Cow, sync::Arc};
+
+    use super::{ProgramRouter, ProgramRouterError};
+    use crate::{
+        instruction::{InstructionPath, InstructionUpdate, SharedSlice},
+        test_util::block_on,
+        KeyBytes, ParseError, ParseResult, Parser, Prefilter, ProgramParser, Pubkey,
+    };
+
+    #[derive(Debug)]
+    struct Program(u8);
+
+    impl Parser for Program {
+        type Input = InstructionUpdate;
+        type Output = (u8, usize);
+
+        fn id(&self) -> Cow<str> { format!("Program{}", self.0).into() }
+
+        fn prefilter(&self) -> Prefilter {
+            Prefilter::builder()
+                .transaction_accounts_include([self.program_id()])
+                .build()
+                .unwrap()
+        }
+
+        async fn parse(&self, ix: &InstructionUpdate) -> ParseResult<(u8, usize)> {
+            Ok((self.0, ix.data.len()))
+        }
+    }
+
+    impl ProgramParser for Program {
+        fn program_id(&self) -> Pubkey { KeyBytes([self.0; 32]) }
+    }
+
+    fn ix(program: u8) -> InstructionUpdate {
+        InstructionUpdate {
+            program: KeyBytes([program; 32]),
+            accounts: SharedSlice::default(),
+            account_indices: SharedSlice::default(),
+            data: vec![0; 3].into(),
+            shared: Arc::default(),
+            inner: vec![],
+            path: InstructionPath::default(),
+            logs: None,
+            return_data: None,
+        }
+    }
+
+    #[test]
+    fn test_program_router() {
+        let router = ProgramRouter::builder()
+            .parser(Program(2))
+            .parser(Program(1))
+            .build()
+            .unwrap();
+
+        assert_eq!(router.id(), "ProgramRouter[Program1,Program2]");
+        assert_eq!(
+            router
+                .prefilter()
+                .transaction
+                .unwrap()
+                .accounts_include
+                .len(),
+            2
+        );
+
+        let output = block_on(router.parse(&ix(2))).unwrap();
+        assert_eq!(&*output.parser_id, "Program2");
+        assert_eq!(output.downcast_ref::<(u8, usize)>(), Some(&(2, 3)));
+        assert!(matches!(
+            block_on(router.parse(&ix(3))),
+            Err(ParseError::Filtered)
+        ));
+
+        assert!(matches!(
+            ProgramRouter::builder()
+                .parser(Program(1))
+                .parser(Program(1))
+                .build(),
+            Err(ProgramRouterError::DuplicateProgram(..))
+        ));
+    }
+}
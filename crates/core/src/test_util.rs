@@ -0,0 +1,15 @@
+//! Helpers shared by the tests of the core crate.
+
+use std::{
+    future::Future,
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+/// Poll a future once, panicking if it is not immediately ready.
+pub fn block_on<F: Future>(fut: F) -> F::Output {
+    match pin!(fut).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(v) => v,
+        Poll::Pending => panic!("Future was not ready"),
+    }
+}
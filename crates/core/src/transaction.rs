@@ -0,0 +1,391 @@
+//! A parser producing a decoded view of a whole transaction from a set of
+//! instruction parsers.
+
+use std::{borrow::Cow, future::Future, pin::Pin, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use yellowstone_grpc_proto::geyser::SubscribeUpdateTransactionInfo;
+
+use crate::{
+    instruction::{self, InstructionPath, InstructionUpdate, Missing},
+    router::{BoxAny, ProgramRouter, RoutedInstruction},
+    ParseError, ParseResult, Parser, Prefilter, Pubkey, Signature, TransactionUpdate,
+};
+
+/// A transaction with each of its instructions decoded by the parser
+/// registered for its program, if any.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedTransaction<T = BoxAny> {
+    /// The signature of the transaction.
+    #[serde(with = "b58")]
+    pub signature: Signature,
+    /// The slot in which the transaction was processed.
+    pub slot: u64,
+    /// Whether the transaction failed.
+    pub failed: bool,
+    /// The outer instructions of the transaction, with their inner
+    /// instructions nested within them.
+    pub instructions: Vec<DecodedInstruction<T>>,
+}
+
+impl<T> DecodedTransaction<T> {
+    /// Iterate over all instructions of the transaction in execution order,
+    /// outer and inner.
+    pub fn visit_all(&self) -> impl Iterator<Item = &DecodedInstruction<T>> {
+        let mut stack: Vec<_> = self.instructions.iter().rev().collect();
+
+        std::iter::from_fn(move || {
+            let ix = stack.pop()?;
+            stack.extend(ix.inner.iter().rev());
+            Some(ix)
+        })
+    }
+}
+
+/// A single instruction of a [`DecodedTransaction`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecodedInstruction<T = BoxAny> {
+    /// The program ID of the instruction.
+    #[serde(with = "b58")]
+    pub program: Pubkey,
+    /// The position of this instruction within its transaction.
+    pub path: InstructionPath,
+    /// The accounts passed to the instruction.
+    #[serde(with = "b58_seq")]
+    pub accounts: Vec<Pubkey>,
+    /// The serialized binary instruction payload.
+    #[serde(with = "b58")]
+    pub data: Vec<u8>,
+    /// The ID of the parser that decoded this instruction and its output, or
+    /// `None` if no registered parser recognised the instruction.
+    pub decoded: Option<Decoded<T>>,
+    /// The error returned by the parser registered for the program of this
+    /// instruction, if it failed to decode it.
+    pub decode_error: Option<String>,
+    /// Inner instructions invoked by this instruction.
+    pub inner: Vec<DecodedInstruction<T>>,
+}
+
+/// The output of the parser that decoded a [`DecodedInstruction`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Decoded<T = BoxAny> {
+    /// The ID of the parser that decoded the instruction.
+    pub parser_id: String,
+    /// The decoded instruction.
+    pub value: T,
+}
+
+/// A transaction parser that decodes every instruction of a transaction with
+/// the instruction parsers of a [`ProgramRouter`], producing a
+/// [`DecodedTransaction`].
+///
+/// Instructions whose program has no registered parser, or which the parser
+/// for their program filters out, are kept in their raw form.  To make the
+/// decoded transaction serializable, register each parser with
+/// [`ProgramRouterBuilder::parser_with`](crate::router::ProgramRouterBuilder::parser_with)
+/// to convert its output to a serializable type.
+#[derive(Debug)]
+pub struct TransactionParser<T = BoxAny> {
+    id: String,
+    router: ProgramRouter<T>,
+}
+
+impl<T> TransactionParser<T> {
+    /// Create a new transaction parser from a router of instruction parsers.
+    #[must_use]
+    pub fn new(router: ProgramRouter<T>) -> Self {
+        Self {
+            id: format!("TransactionParser[{}]", router.parser_ids()),
+            router,
+        }
+    }
+
+    /// Get the router used to decode instructions.
+    #[inline]
+    #[must_use]
+    pub fn router(&self) -> &ProgramRouter<T> { &self.router }
+
+    fn decode<'a>(
+        &'a self,
+        ix: &'a InstructionUpdate,
+    ) -> Pin<Box<dyn Future<Output = DecodedInstruction<T>> + Send + 'a>>
+    where
+        T: Send,
+    {
+        Box::pin(async move {
+            let (decoded, decode_error) = match self.router.parse(ix).await {
+                Ok(RoutedInstruction {
+                    program: _,
+                    parser_id,
+                    value,
+                }) => (
+                    Some(Decoded {
+                        parser_id: parser_id.to_string(),
+                        value,
+                    }),
+                    None,
+                ),
+                Err(ParseError::Filtered) => (None, None),
+                Err(ParseError::Other(e)) => (None, Some(e.to_string())),
+            };
+
+            let mut inner = Vec::with_capacity(ix.inner.len());
+            for i in &ix.inner {
+                inner.push(self.decode(i).await);
+            }
+
+            DecodedInstruction {
+                program: ix.program,
+                path: ix.path,
+                accounts: ix.accounts.to_vec(),
+                data: ix.data.to_vec(),
+                decoded,
+                decode_error,
+                inner,
+            }
+        })
+    }
+}
+
+impl<T: Send> Parser for TransactionParser<T> {
+    type Input = TransactionUpdate;
+    type Output = DecodedTransaction<T>;
+
+    #[inline]
+    fn id(&self) -> Cow<str> { Cow::Borrowed(&self.id) }
+
+    #[inline]
+    fn prefilter(&self) -> Prefilter { self.router.prefilter() }
+
+    async fn parse(&self, txn: &TransactionUpdate) -> ParseResult<DecodedTransaction<T>> {
+        let SubscribeUpdateTransactionInfo {
+            signature, meta, ..
+        } = txn
+            .transaction
+            .as_ref()
+            .ok_or(instruction::ParseError::from(Missing::TransactionInfo))?;
+        // Copy the update once and share it between its instructions
+        let ixs = InstructionUpdate::parse_from_shared_txn(&Arc::new(txn.clone()))?;
+
+        let mut instructions = Vec::with_capacity(ixs.len());
+        for ix in &ixs {
+            instructions.push(self.decode(ix).await);
+        }
+
+        Ok(DecodedTransaction {
+            signature: signature
+                .as_slice()
+                .try_into()
+                .map_err(instruction::ParseError::Signature)?,
+            slot: txn.slot,
+            failed: meta.as_ref().is_some_and(|m| m.err.is_some()),
+            instructions,
+        })
+    }
+}
+
+#[cfg(feature = "transaction-proto")]
+mod proto {
+    use yellowstone_vixen_proto::{
+        prost_types::Any,
+        transaction::{DecodedInstructionProto, DecodedTransactionProto},
+    };
+
+    use super::{Decoded, DecodedInstruction, DecodedTransaction};
+
+    impl From<DecodedTransaction<Any>> for DecodedTransactionProto {
+        fn from(value: DecodedTransaction<Any>) -> Self {
+            let DecodedTransaction {
+                signature,
+                slot,
+                failed,
+                instructions,
+            } = value;
+
+            Self {
+                signature: signature.to_string(),
+                slot,
+                failed,
+                instructions: instructions.into_iter().map(Into::into).collect(),
+            }
+        }
+    }
+
+    impl From<DecodedInstruction<Any>> for DecodedInstructionProto {
+        fn from(value: DecodedInstruction<Any>) -> Self {
+            let DecodedInstruction {
+                program,
+                path,
+                accounts,
+                data,
+                decoded,
+                decode_error,
+                inner,
+            } = value;
+            let (parser_id, decoded) = decoded
+                .map(|Decoded { parser_id, value }| (parser_id, value))
+                .unzip();
+
+            Self {
+                program: program.to_string(),
+                path: path.to_string(),
+                accounts: accounts.iter().map(ToString::to_string).collect(),
+                data,
+                parser_id,
+                decoded,
+                decode_error,
+                inner: inner.into_iter().map(Into::into).collect(),
+            }
+        }
+    }
+}
+
+mod b58 {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer, T: AsRef<[u8]>>(value: &T, ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_str(&bs58::encode(value).into_string())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>, T: for<'a> TryFrom<&'a [u8]>>(
+        de: D,
+    ) -> Result<T, D::Error>
+    where for<'a> <T as TryFrom<&'a [u8]>>::Error: std::fmt::Display {
+        let s = <std::borrow::Cow<str>>::deserialize(de)?;
+        let bytes = bs58::decode(&*s).into_vec().map_err(D::Error::custom)?;
+
+        T::try_from(&bytes).map_err(D::Error::custom)
+    }
+}
+
+mod b58_seq {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::Pubkey;
+
+    pub fn serialize<S: Serializer>(value: &[Pubkey], ser: S) -> Result<S::Ok, S::Error> {
+        ser.collect_seq(value.iter().map(ToString::to_string))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Vec<Pubkey>, D::Error> {
+        Vec::<String>::deserialize(de)?
+            .into_iter()
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use yellowstone_grpc_proto::{
+        geyser::SubscribeUpdateTransactionInfo,
+        prelude::MessageHeader,
+        solana::storage::confirmed_block::{
+            CompiledInstruction, InnerInstruction, InnerInstructions, Message, Transaction,
+            TransactionStatusMeta,
+        },
+    };
+
+    use super::TransactionParser;
+    use crate::{
+        instruction::InstructionUpdate, router::ProgramRouter, test_util::block_on, KeyBytes,
+        ParseError, ParseResult, Parser, Prefilter, ProgramParser, Pubkey, TransactionUpdate,
+    };
+
+    #[derive(Debug)]
+    struct Program(u8);
+
+    impl Parser for Program {
+        type Input = InstructionUpdate;
+        type Output = Vec<u8>;
+
+        fn id(&self) -> Cow<str> { format!("Program{}", self.0).into() }
+
+        fn prefilter(&self) -> Prefilter {
+            Prefilter::builder()
+                .transaction_accounts_include([self.program_id()])
+                .build()
+                .unwrap()
+        }
+
+        async fn parse(&self, ix: &InstructionUpdate) -> ParseResult<Vec<u8>> {
+            match ix.data.first() {
+                Some(0) => Err(ParseError::Filtered),
+                Some(_) => Ok(ix.data.to_vec()),
+                None => Err(ParseError::from("Empty instruction data")),
+            }
+        }
+    }
+
+    impl ProgramParser for Program {
+        fn program_id(&self) -> Pubkey { KeyBytes([self.0; 32]) }
+    }
+
+    fn ix(program_id_index: u32, data: Vec<u8>) -> CompiledInstruction {
+        CompiledInstruction {
+            program_id_index,
+            accounts: vec![0],
+            data,
+        }
+    }
+
+    #[test]
+    fn test_decoded_transaction() {
+        let txn = TransactionUpdate {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: vec![1; 64],
+                transaction: Some(Transaction {
+                    signatures: vec![vec![1; 64]],
+                    message: Some(Message {
+                        header: Some(MessageHeader {
+                            num_required_signatures: 1,
+                            num_readonly_signed_accounts: 0,
+                            num_readonly_unsigned_accounts: 3,
+                        }),
+                        account_keys: (0..4).map(|b| vec![b; 32]).collect(),
+                        instructions: vec![ix(1, vec![1, 2]), ix(2, vec![3]), ix(1, vec![])],
+                        ..Message::default()
+                    }),
+                }),
+                meta: Some(TransactionStatusMeta {
+                    inner_instructions: vec![InnerInstructions {
+                        index: 0,
+                        instructions: vec![InnerInstruction {
+                            program_id_index: 1,
+                            accounts: vec![],
+                            data: vec![0],
+                            stack_height: Some(2),
+                        }],
+                    }],
+                    ..TransactionStatusMeta::default()
+                }),
+                ..SubscribeUpdateTransactionInfo::default()
+            }),
+            slot: 7,
+        };
+
+        let parser =
+            TransactionParser::new(ProgramRouter::builder().parser(Program(1)).build().unwrap());
+        assert_eq!(parser.id(), "TransactionParser[Program1]");
+
+        let decoded = block_on(parser.parse(&txn)).unwrap();
+        assert_eq!(decoded.slot, 7);
+        assert!(!decoded.failed);
+
+        let ixs: Vec<_> = decoded.visit_all().collect();
+        assert_eq!(ixs.len(), 4);
+
+        let value = ixs[0].decoded.as_ref().unwrap();
+        assert_eq!(value.parser_id, "Program1");
+        assert_eq!(value.value.downcast_ref::<Vec<u8>>(), Some(&vec![1, 2]));
+
+        // Filtered and unknown instructions are kept raw
+        assert_eq!(ixs[1].path.to_string(), "0.0");
+        assert!(ixs[1].decoded.is_none() && ixs[1].decode_error.is_none());
+        assert_eq!(ixs[2].program, KeyBytes([2; 32]));
+        assert!(ixs[2].decoded.is_none());
+        assert!(ixs[3].decoded.is_none() && ixs[3].decode_error.is_some());
+    }
+}
@@ -0,0 +1,512 @@
+//! Decoding of the bincode-serialized errors reported for failed
+//! transactions.
+
+use crate::Pubkey;
+
+/// The names of the `TransactionError` variants, indexed by their bincode
+/// discriminant.
+const TRANSACTION_ERRORS: &[&str] = &[
+    "AccountInUse",
+    "AccountLoadedTwice",
+    "AccountNotFound",
+    "ProgramAccountNotFound",
+    "InsufficientFundsForFee",
+    "InvalidAccountForFee",
+    "AlreadyProcessed",
+    "BlockhashNotFound",
+    "InstructionError",
+    "CallChainTooDeep",
+    "MissingSignatureForFee",
+    "InvalidAccountIndex",
+    "SignatureFailure",
+    "InvalidProgramForExecution",
+    "SanitizeFailure",
+    "ClusterMaintenance",
+    "AccountBorrowOutstanding",
+    "WouldExceedMaxBlockCostLimit",
+    "UnsupportedVersion",
+    "InvalidWritableAccount",
+    "WouldExceedMaxAccountCostLimit",
+    "WouldExceedAccountDataBlockLimit",
+    "TooManyAccountLocks",
+    "AddressLookupTableNotFound",
+    "InvalidAddressLookupTableOwner",
+    "InvalidAddressLookupTableData",
+    "InvalidAddressLookupTableIndex",
+    "InvalidRentPayingAccount",
+    "WouldExceedMaxVoteCostLimit",
+    "WouldExceedAccountDataTotalLimit",
+    "DuplicateInstruction",
+    "InsufficientFundsForRent",
+    "MaxLoadedAccountsDataSizeExceeded",
+    "InvalidLoadedAccountsDataSizeLimit",
+    "ResanitizationNeeded",
+    "ProgramExecutionTemporarilyRestricted",
+    "UnbalancedTransaction",
+    "ProgramCacheHitMaxLimit",
+    "CommitCancelled",
+];
+
+const INSTRUCTION_ERROR: u32 = 8;
+const DUPLICATE_INSTRUCTION: u32 = 30;
+const INSUFFICIENT_FUNDS_FOR_RENT: u32 = 31;
+const PROGRAM_EXECUTION_TEMPORARILY_RESTRICTED: u32 = 35;
+
+/// The names of the `InstructionError` variants, indexed by their bincode
+/// discriminant.
+const INSTRUCTION_ERRORS: &[&str] = &[
+    "GenericError",
+    "InvalidArgument",
+    "InvalidInstructionData",
+    "InvalidAccountData",
+    "AccountDataTooSmall",
+    "InsufficientFunds",
+    "IncorrectProgramId",
+    "MissingRequiredSignature",
+    "AccountAlreadyInitialized",
+    "UninitializedAccount",
+    "UnbalancedInstruction",
+    "ModifiedProgramId",
+    "ExternalAccountLamportSpend",
+    "ExternalAccountDataModified",
+    "ReadonlyLamportChange",
+    "ReadonlyDataModified",
+    "DuplicateAccountIndex",
+    "ExecutableModified",
+    "RentEpochModified",
+    "NotEnoughAccountKeys",
+    "AccountDataSizeChanged",
+    "AccountNotExecutable",
+    "AccountBorrowFailed",
+    "AccountBorrowOutstanding",
+    "DuplicateAccountOutOfSync",
+    "Custom",
+    "InvalidError",
+    "ExecutableDataModified",
+    "ExecutableLamportChange",
+    "ExecutableAccountNotRentExempt",
+    "UnsupportedProgramId",
+    "CallDepth",
+    "MissingAccount",
+    "ReentrancyNotAllowed",
+    "MaxSeedLengthExceeded",
+    "InvalidSeeds",
+    "InvalidRealloc",
+    "ComputationalBudgetExceeded",
+    "PrivilegeEscalation",
+    "ProgramEnvironmentSetupFailure",
+    "ProgramFailedToComplete",
+    "ProgramFailedToCompile",
+    "Immutable",
+    "IncorrectAuthority",
+    "BorshIoError",
+    "AccountNotRentExempt",
+    "InvalidAccountOwner",
+    "ArithmeticOverflow",
+    "UnsupportedSysvar",
+    "IllegalOwner",
+    "MaxAccountsDataAllocationsExceeded",
+    "MaxAccountsResizeExceeded",
+    "MaxInstructionTraceLengthExceeded",
+    "BuiltinProgramsMustConsumeComputeUnits",
+];
+
+const CUSTOM: u32 = 25;
+const BORSH_IO_ERROR: u32 = 44;
+
+/// An error encountered while decoding a transaction error.
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+pub enum DecodeError {
+    /// The serialized error ended unexpectedly.
+    #[error("Unexpected end of serialized transaction error")]
+    UnexpectedEof,
+    /// The serialized error contained an unrecognized variant.
+    #[error("Unknown {0} variant {1}")]
+    UnknownVariant(&'static str, u32),
+    /// A string in the serialized error was not valid UTF-8.
+    #[error("Invalid UTF-8 in serialized transaction error")]
+    InvalidUtf8,
+}
+
+/// The reason a transaction failed, decoded from
+/// [`InstructionShared::err`](crate::instruction::InstructionShared::err).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TxnError {
+    /// An instruction failed.
+    #[error("{0}")]
+    Instruction(InstructionFailure),
+    /// The transaction contains the same instruction more than once.
+    #[error("Transaction contains a duplicate instruction ({0})")]
+    DuplicateInstruction(u8),
+    /// An account would be left without enough lamports to pay rent.
+    #[error(
+        "Transaction results in an account ({account_index}) with insufficient funds for rent"
+    )]
+    InsufficientFundsForRent {
+        /// The index of the account in the transaction.
+        account_index: u8,
+    },
+    /// Execution of a program referenced by the transaction is restricted.
+    #[error(
+        "Execution of the program referenced by account at index {account_index} is temporarily \
+         restricted"
+    )]
+    ProgramExecutionTemporarilyRestricted {
+        /// The index of the program account in the transaction.
+        account_index: u8,
+    },
+    /// Any other transaction error, identified by its variant name.
+    #[error("{0}")]
+    Other(&'static str),
+}
+
+/// The failure of a single instruction within a transaction.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("Error processing instruction {index}: {error}")]
+pub struct InstructionFailure {
+    /// The index of the outer instruction that failed.
+    pub index: u8,
+    /// The program that raised the error.
+    ///
+    /// This is the innermost failing program as reported in the logs.  If
+    /// the logs do not report it, this is the program of the failing outer
+    /// instruction if that program invoked no other, and `None` if it did
+    /// or if the logs were truncated.
+    pub program: Option<Pubkey>,
+    /// The error raised by the instruction.
+    pub error: InstructionErrorKind,
+}
+
+/// The kind of error raised by a failing instruction.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum InstructionErrorKind {
+    /// A program-specific error code.
+    #[error("custom program error: {0:#x}")]
+    Custom(u32),
+    /// A Borsh serialization error, with its message.
+    #[error("Failed to serialize or deserialize account data: {0}")]
+    BorshIo(String),
+    /// Any other error defined by the runtime, identified by its variant
+    /// name.
+    #[error("{0}")]
+    Builtin(&'static str),
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl Reader<'_> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        let (head, rest) = self
+            .0
+            .split_first_chunk()
+            .ok_or(DecodeError::UnexpectedEof)?;
+        self.0 = rest;
+        Ok(*head)
+    }
+
+    fn u8(&mut self) -> Result<u8, DecodeError> { self.take().map(|[b]| b) }
+
+    fn u32(&mut self) -> Result<u32, DecodeError> { self.take().map(u32::from_le_bytes) }
+
+    fn string(&mut self) -> Result<String, DecodeError> {
+        let len = usize::try_from(u64::from_le_bytes(self.take()?))
+            .map_err(|_| DecodeError::UnexpectedEof)?;
+        let bytes = self.0.get(..len).ok_or(DecodeError::UnexpectedEof)?;
+        self.0 = &self.0[len..];
+
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+}
+
+fn variant_name(
+    names: &[&'static str],
+    kind: &'static str,
+    idx: u32,
+) -> Result<&'static str, DecodeError> {
+    usize::try_from(idx)
+        .ok()
+        .and_then(|i| names.get(i))
+        .copied()
+        .ok_or(DecodeError::UnknownVariant(kind, idx))
+}
+
+impl TxnError {
+    /// Decode a bincode-serialized `TransactionError`, as found in
+    /// [`TransactionError::err`](yellowstone_grpc_proto::solana::storage::confirmed_block::TransactionError::err).
+    ///
+    /// The failing program of an [`InstructionFailure`] is not part of the
+    /// serialized error and is left as `None`.
+    ///
+    /// # Errors
+    /// Returns an error if the data is not a valid serialized
+    /// `TransactionError`.
+    pub fn decode(data: &[u8]) -> Result<Self, DecodeError> {
+        let mut r = Reader(data);
+
+        Ok(match r.u32()? {
+            INSTRUCTION_ERROR => {
+                let index = r.u8()?;
+                let error = InstructionErrorKind::decode(&mut r)?;
+
+                Self::Instruction(InstructionFailure {
+                    index,
+                    program: None,
+                    error,
+                })
+            },
+            DUPLICATE_INSTRUCTION => Self::DuplicateInstruction(r.u8()?),
+            INSUFFICIENT_FUNDS_FOR_RENT => Self::InsufficientFundsForRent {
+                account_index: r.u8()?,
+            },
+            PROGRAM_EXECUTION_TEMPORARILY_RESTRICTED => {
+                Self::ProgramExecutionTemporarilyRestricted {
+                    account_index: r.u8()?,
+                }
+            },
+            idx => Self::Other(variant_name(TRANSACTION_ERRORS, "TransactionError", idx)?),
+        })
+    }
+
+    /// Get the instruction failure, if the transaction failed because of an
+    /// instruction error.
+    #[inline]
+    #[must_use]
+    pub fn instruction(&self) -> Option<&InstructionFailure> {
+        match self {
+            Self::Instruction(f) => Some(f),
+            _ => None,
+        }
+    }
+
+    /// Get the custom error code and the program that raised it, if the
+    /// transaction failed with a custom program error.
+    #[must_use]
+    pub fn custom_error(&self) -> Option<(Option<Pubkey>, u32)> {
+        match self.instruction()? {
+            InstructionFailure {
+                program,
+                error: InstructionErrorKind::Custom(code),
+                ..
+            } => Some((*program, *code)),
+            _ => None,
+        }
+    }
+}
+
+impl InstructionErrorKind {
+    fn decode(r: &mut Reader) -> Result<Self, DecodeError> {
+        Ok(match r.u32()? {
+            CUSTOM => Self::Custom(r.u32()?),
+            BORSH_IO_ERROR => Self::BorshIo(r.string()?),
+            idx => Self::Builtin(variant_name(INSTRUCTION_ERRORS, "InstructionError", idx)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::Cow;
+
+    use yellowstone_grpc_proto::{
+        geyser::SubscribeUpdateTransactionInfo,
+        prelude::{
+            CompiledInstruction, InnerInstruction, InnerInstructions, Message, MessageHeader,
+            Transaction, TransactionError, TransactionStatusMeta,
+        },
+    };
+
+    use super::*;
+    use crate::{
+        instruction::InstructionUpdate, logs::LOG_TRUNCATED, KeyBytes, ParseResult, Parser,
+        Prefilter, ProgramErrorParser, ProgramParser, TransactionUpdate,
+    };
+
+    const PAYER: Pubkey = KeyBytes([0; 32]);
+    const OUTER: Pubkey = KeyBytes([1; 32]);
+    const INNER: Pubkey = KeyBytes([2; 32]);
+
+    /// A transaction whose only instruction, invoking `OUTER`, failed with
+    /// custom error 0x1771.  `OUTER` invoked `INNER` if `cpi` is set.
+    fn failed_txn(log_messages: &[String], cpi: bool) -> TransactionUpdate {
+        let inner_instructions = cpi
+            .then(|| InnerInstructions {
+                index: 0,
+                instructions: vec![InnerInstruction {
+                    program_id_index: 2,
+                    accounts: vec![],
+                    data: vec![],
+                    stack_height: Some(2),
+                }],
+            })
+            .into_iter()
+            .collect();
+
+        TransactionUpdate {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                signature: vec![1; 64],
+                transaction: Some(Transaction {
+                    signatures: vec![vec![1; 64]],
+                    message: Some(Message {
+                        header: Some(MessageHeader {
+                            num_required_signatures: 1,
+                            num_readonly_signed_accounts: 0,
+                            num_readonly_unsigned_accounts: 2,
+                        }),
+                        account_keys: [PAYER, OUTER, INNER].map(|k| k.0.to_vec()).into(),
+                        instructions: vec![CompiledInstruction {
+                            program_id_index: 1,
+                            accounts: vec![0],
+                            data: vec![],
+                        }],
+                        ..Message::default()
+                    }),
+                }),
+                meta: Some(TransactionStatusMeta {
+                    err: Some(TransactionError {
+                        err: vec![8, 0, 0, 0, 0, 25, 0, 0, 0, 0x71, 0x17, 0, 0],
+                    }),
+                    inner_instructions,
+                    log_messages: log_messages.to_vec(),
+                    ..TransactionStatusMeta::default()
+                }),
+                ..SubscribeUpdateTransactionInfo::default()
+            }),
+            slot: 1,
+        }
+    }
+
+    fn failing_program(txn: &TransactionUpdate) -> Option<Pubkey> {
+        let ixs = InstructionUpdate::parse_from_txn(txn).unwrap();
+        let (program, code) = ixs[0].shared.txn_error.as_ref()?.custom_error()?;
+        assert_eq!(code, 0x1771);
+        program
+    }
+
+    fn invoke(program: Pubkey, depth: u8) -> String {
+        format!("Program {program} invoke [{depth}]")
+    }
+
+    fn failed(program: Pubkey) -> String {
+        format!("Program {program} failed: custom program error: 0x1771")
+    }
+
+    #[test]
+    fn test_failing_program() {
+        // The innermost failure reported in the logs
+        let logs = [
+            invoke(OUTER, 1),
+            invoke(INNER, 2),
+            failed(INNER),
+            failed(OUTER),
+        ];
+        assert_eq!(failing_program(&failed_txn(&logs, true)), Some(INNER));
+
+        // Without logs, the outer program failed only if it invoked no other
+        assert_eq!(failing_program(&failed_txn(&[], false)), Some(OUTER));
+        assert_eq!(failing_program(&failed_txn(&[], true)), None);
+
+        let mut txn = failed_txn(&[], false);
+        let meta = txn.transaction.as_mut().unwrap().meta.as_mut().unwrap();
+        meta.inner_instructions_none = true;
+        assert_eq!(failing_program(&txn), None);
+
+        // The failure may have been cut from truncated logs
+        let logs = [invoke(OUTER, 1), LOG_TRUNCATED.to_owned()];
+        assert_eq!(failing_program(&failed_txn(&logs, false)), None);
+    }
+
+    /// Mirrors the error enums generated for each program
+    #[derive(Debug, PartialEq, Eq, thiserror::Error)]
+    enum InnerError {
+        #[error("The bonding curve has completed")]
+        BondingCurveComplete = 0x1771,
+    }
+
+    struct InnerParser;
+
+    impl Parser for InnerParser {
+        type Input = InstructionUpdate;
+        type Output = ();
+
+        fn id(&self) -> Cow<str> { "InnerParser".into() }
+
+        fn prefilter(&self) -> Prefilter { Prefilter::default() }
+
+        async fn parse(&self, _: &InstructionUpdate) -> ParseResult<()> { Ok(()) }
+    }
+
+    impl ProgramParser for InnerParser {
+        fn program_id(&self) -> Pubkey { INNER }
+    }
+
+    impl ProgramErrorParser for InnerParser {
+        type Error = InnerError;
+
+        fn parse_error(&self, code: u32) -> Option<InnerError> {
+            (code == InnerError::BondingCurveComplete as u32)
+                .then_some(InnerError::BondingCurveComplete)
+        }
+    }
+
+    #[test]
+    fn test_program_error() {
+        let logs = [
+            invoke(OUTER, 1),
+            invoke(INNER, 2),
+            failed(INNER),
+            failed(OUTER),
+        ];
+        let ixs = InstructionUpdate::parse_from_txn(&failed_txn(&logs, true)).unwrap();
+        assert_eq!(
+            ixs[0].shared.program_error(&InnerParser),
+            Some(InnerError::BondingCurveComplete)
+        );
+
+        // Errors are only resolved for the program that raised them
+        let logs = [invoke(OUTER, 1), failed(OUTER)];
+        let ixs = InstructionUpdate::parse_from_txn(&failed_txn(&logs, false)).unwrap();
+        assert_eq!(ixs[0].shared.program_error(&InnerParser), None);
+    }
+
+    #[test]
+    fn test_decode() {
+        let custom = [8, 0, 0, 0, 2, 25, 0, 0, 0, 0x71, 0x17, 0, 0];
+        assert_eq!(
+            TxnError::decode(&custom).unwrap(),
+            TxnError::Instruction(InstructionFailure {
+                index: 2,
+                program: None,
+                error: InstructionErrorKind::Custom(6001),
+            })
+        );
+
+        let borsh = [
+            8, 0, 0, 0, 0, 44, 0, 0, 0, 2, 0, 0, 0, 0, 0, 0, 0, b'h', b'i',
+        ];
+        assert_eq!(
+            TxnError::decode(&borsh)
+                .unwrap()
+                .instruction()
+                .unwrap()
+                .error,
+            InstructionErrorKind::BorshIo("hi".into())
+        );
+
+        let builtin = [8, 0, 0, 0, 1, 2, 0, 0, 0];
+        assert_eq!(
+            TxnError::decode(&builtin).unwrap().to_string(),
+            "Error processing instruction 1: InvalidInstructionData"
+        );
+
+        assert_eq!(
+            TxnError::decode(&[31, 0, 0, 0, 3]).unwrap(),
+            TxnError::InsufficientFundsForRent { account_index: 3 }
+        );
+        assert_eq!(
+            TxnError::decode(&[7, 0, 0, 0]).unwrap(),
+            TxnError::Other("BlockhashNotFound")
+        );
+        assert!(TxnError::decode(&[8, 0, 0, 0, 0, 25, 0]).is_err());
+        assert!(TxnError::decode(&[200, 0, 0, 0]).is_err());
+    }
+}
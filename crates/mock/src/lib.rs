@@ -32,7 +32,7 @@ use solana_transaction_status::{
 };
 use yellowstone_grpc_proto::geyser::{SubscribeUpdateAccount, SubscribeUpdateAccountInfo};
 use yellowstone_vixen_core::{
-    instruction::{InstructionShared, InstructionUpdate},
+    instruction::{InstructionPath, InstructionShared, InstructionUpdate, SharedSlice},
     ProgramParser, Pubkey as VixenPubkey,
 };
 
@@ -125,11 +125,9 @@ impl From<SerializablePubkey> for VixenPubkey {
     fn from(value: SerializablePubkey) -> Self { Self::new(value.0) }
 }
 
-pub type IxIndex = [usize; 2]; // [outer_ix_index, inner_ix_index]
-
 #[derive(Clone, Serialize, Deserialize, Debug)]
 pub struct SerializableInstructionUpdate {
-    pub ix_index: IxIndex,
+    pub path: InstructionPath,
     pub program: SerializablePubkey,
     pub accounts: Vec<SerializablePubkey>,
     pub data: Vec<u8>,
@@ -139,14 +137,14 @@ pub struct SerializableInstructionUpdate {
 impl From<&InstructionUpdate> for SerializableInstructionUpdate {
     fn from(value: &InstructionUpdate) -> Self {
         Self {
-            ix_index: [0; 2],
+            path: value.path,
             program: SerializablePubkey(value.program.0),
             accounts: value
                 .accounts
                 .iter()
                 .map(|x| SerializablePubkey(x.into_bytes()))
                 .collect(),
-            data: value.data.clone(),
+            data: value.data.to_vec(),
             inner: value.inner.iter().map(Into::into).collect(),
         }
     }
@@ -157,9 +155,13 @@ impl From<&SerializableInstructionUpdate> for InstructionUpdate {
         Self {
             program: value.program.into(),
             accounts: value.accounts.iter().copied().map(Into::into).collect(),
-            data: value.data.clone(),
+            account_indices: SharedSlice::default(),
+            data: value.data.clone().into(),
             shared: Arc::new(InstructionShared::default()),
             inner: value.inner.iter().map(Into::into).collect(),
+            path: value.path,
+            logs: None,
+            return_data: None,
         }
     }
 }
@@ -201,7 +203,7 @@ fn try_from_ui_instructions(
         let program = get_account_pubkey_from_index(ix.program_id_index as usize, accounts)?;
 
         let ix = SerializableInstructionUpdate {
-            ix_index: [idx, 0],
+            path: InstructionPath::outer(idx),
             data: decode_bs58_to_bytes(&ix.data)?,
             accounts: accounts_out,
             program,
@@ -219,6 +221,7 @@ fn try_from_ui_inner_ixs(
     program_id: &str,
 ) -> Result<Vec<SerializableInstructionUpdate>, String> {
     let mut ixs: Vec<SerializableInstructionUpdate> = Vec::new();
+    let mut stack: Vec<(usize, u32)> = Vec::new();
     for (idx, ix) in ui_inner_ixs.instructions.iter().enumerate() {
         if let UiInstruction::Compiled(compiled_ix) = ix {
             let accounts_out = compiled_ix
@@ -229,8 +232,22 @@ fn try_from_ui_inner_ixs(
             let program =
                 get_account_pubkey_from_index(compiled_ix.program_id_index as usize, accounts)?;
 
+            let mut parent = None;
+            if let Some(height) = compiled_ix.stack_height {
+                while stack.last().is_some_and(|&(_, h)| h >= height) {
+                    stack.pop();
+                }
+                parent = stack.last().map(|&(i, _)| i);
+                stack.push((idx, height));
+            }
+
             let ix = SerializableInstructionUpdate {
-                ix_index: [ui_inner_ixs.index.into(), idx],
+                path: InstructionPath {
+                    outer_index: ui_inner_ixs.index.into(),
+                    inner_index: Some(idx),
+                    stack_height: compiled_ix.stack_height,
+                    parent,
+                },
                 data: decode_bs58_to_bytes(&compiled_ix.data)?,
                 accounts: accounts_out,
                 program,
@@ -350,7 +367,7 @@ macro_rules! tx_fixture {
 #[macro_export]
 macro_rules! run_account_parse {
     ($parser:expr, $account:expr) => {
-        $parser.parse(&$account).await.unwrap()
+        $parser.parse(&$account.into()).await.unwrap()
     };
 }
 
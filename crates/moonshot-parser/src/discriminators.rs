@@ -0,0 +1,41 @@
+//! Discriminators of the Moonshot program accounts.
+//!
+//! Codama does not generate these, so this module is maintained by hand
+//! alongside the generated code.
+
+use yellowstone_vixen_core::{AccountFilter, Prefilter};
+
+/// Discriminators of the accounts
+/// [`AccountParser`](crate::accounts_parser::AccountParser) can parse.
+pub const ACCOUNT_DISCRIMINATORS: [[u8; 8]; 2] = [[189, 255, 97, 70, 186, 189, 24, 102], [
+    8, 91, 83, 28, 132, 216, 248, 22,
+]];
+
+/// Restrict the accounts matched by a prefilter to those starting with one
+/// of the [`ACCOUNT_DISCRIMINATORS`].
+///
+/// The account parser does not apply this by default, but can opt in with
+/// [`ParserExt::with_prefilter`](yellowstone_vixen_core::ParserExt::with_prefilter):
+///
+/// ```
+/// use yellowstone_vixen_core::{Parser, ParserExt};
+/// use yellowstone_vixen_moonshot_parser::{accounts_parser::AccountParser, discriminators};
+///
+/// let parser = AccountParser.with_prefilter("discriminators", discriminators::prefilter);
+/// assert!(!parser.prefilter().account.unwrap().filters.is_empty());
+/// ```
+pub fn prefilter(mut prefilter: Prefilter) -> Prefilter {
+    if let Some(account) = &mut prefilter.account {
+        account.filters = ACCOUNT_DISCRIMINATORS
+            .iter()
+            .map(|d| {
+                vec![AccountFilter::Memcmp {
+                    offset: 0,
+                    bytes: d.to_vec(),
+                }]
+            })
+            .collect();
+    }
+
+    prefilter
+}
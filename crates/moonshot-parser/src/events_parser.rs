@@ -0,0 +1,571 @@
+//! Parsers for the Anchor events emitted by the Moonshot program.
+//!
+//! Codama does not generate event parsers, so this module is maintained by
+//! hand alongside the generated code.
+
+#[cfg(feature = "shared-data")]
+use std::sync::Arc;
+
+use borsh::{BorshDeserialize, BorshSerialize};
+use solana_pubkey::{pubkey, Pubkey};
+#[cfg(feature = "shared-data")]
+use yellowstone_vixen_core::InstructionUpdateOutput;
+
+use crate::{
+    deserialize_checked,
+    instructions_parser::{InstructionParser, TokenLaunchpadProgramIx},
+    types::TradeType,
+    ID,
+};
+
+/// The PDA signing the self-invoked instructions `emit_cpi!` records events
+/// with, derived from the `__event_authority` seed.
+pub const EVENT_AUTHORITY: Pubkey = pubkey!("5kkX3TXyaZWCDBfLG8p2TLiL1nWqkKSGdD4iyBJ11REH");
+
+/// Emitted on every buy and sell against a bonding curve.
+///
+/// The program IDL does not declare its events, so their layouts are
+/// defined here rather than generated.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TradeEvent {
+    pub amount: u64,
+    pub collateral_amount: u64,
+    pub dex_fee: u64,
+    pub helio_fee: u64,
+    pub allocation: u64,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "serde_with::As::<serde_with::DisplayFromStr>")
+    )]
+    pub curve: Pubkey,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "serde_with::As::<serde_with::DisplayFromStr>")
+    )]
+    pub cost_token: Pubkey,
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "serde_with::As::<serde_with::DisplayFromStr>")
+    )]
+    pub sender: Pubkey,
+    pub r#type: TradeType,
+    pub label: String,
+}
+
+/// Emitted when a completed bonding curve is migrated to a DEX.
+#[derive(BorshSerialize, BorshDeserialize, Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MigrationEvent {
+    pub tokens_migrated: u64,
+    pub tokens_burned: u64,
+    pub collateral_migrated: u64,
+    pub fee: u64,
+    pub label: String,
+}
+
+/// TokenLaunchpad Events
+#[derive(Debug)]
+#[cfg_attr(feature = "tracing", derive(strum_macros::Display))]
+#[allow(clippy::large_enum_variant)]
+pub enum TokenLaunchpadProgramEvent {
+    Migration(MigrationEvent),
+    Trade(TradeEvent),
+}
+
+impl TokenLaunchpadProgramEvent {
+    /// Deserialize an Anchor event record, consisting of the 8-byte event
+    /// discriminator followed by the Borsh-serialized event.
+    pub fn try_unpack(data: &[u8]) -> yellowstone_vixen_core::ParseResult<Self> {
+        let event_discriminator: [u8; 8] = data
+            .get(0..8)
+            .ok_or(yellowstone_vixen_core::ParseError::from(
+                "Event data too short".to_owned(),
+            ))?
+            .try_into()?;
+        let event_data = &data[8..];
+        match event_discriminator {
+            [255, 202, 76, 147, 91, 231, 73, 22] => Ok(TokenLaunchpadProgramEvent::Migration(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [189, 219, 127, 211, 78, 230, 97, 238] => Ok(TokenLaunchpadProgramEvent::Trade(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            _ => Err(yellowstone_vixen_core::ParseError::from(
+                "Invalid Event discriminator".to_owned(),
+            )),
+        }
+    }
+
+    /// Decode the events the program logged with `sol_log_data` in the given
+    /// log lines, skipping records that are not known events.
+    pub fn from_logs<'a, I>(log_messages: I) -> Vec<Self>
+    where
+        I: IntoIterator<Item = &'a String>,
+        I::IntoIter: 'a,
+    {
+        yellowstone_vixen_core::logs::program_data(log_messages, ID.to_bytes().into())
+            .filter_map(|data| {
+                let event = Self::try_unpack(&data);
+
+                #[cfg(feature = "tracing")]
+                if let Err(e) = &event {
+                    tracing::debug!(
+                        name: "incorrectly_parsed_event",
+                        name = "log_event",
+                        program = ID.to_string(),
+                        event = "deserialization_error",
+                        discriminator = ?data.get(..8),
+                        error = ?e
+                    );
+                }
+
+                event.ok()
+            })
+            .collect()
+    }
+
+    /// Deserialize an event recorded with `emit_cpi!` from the data of the
+    /// self-invoked instruction following its
+    /// [`EVENT_IX_TAG`](yellowstone_vixen_core::anchor::EVENT_IX_TAG),
+    /// checking that the instruction was signed by [`EVENT_AUTHORITY`].
+    pub fn try_unpack_cpi(
+        accounts: &[yellowstone_vixen_core::Pubkey],
+        data: &[u8],
+    ) -> yellowstone_vixen_core::ParseResult<Self> {
+        if !accounts
+            .first()
+            .is_some_and(|a| a.equals_ref(EVENT_AUTHORITY))
+        {
+            return Err(yellowstone_vixen_core::ParseError::from(
+                "Event instruction not signed by the event authority".to_owned(),
+            ));
+        }
+
+        Self::try_unpack(data)
+    }
+}
+
+/// Parses the events an instruction logs with `sol_log_data`, as emitted by
+/// Anchor's `emit!` macro.
+#[derive(Debug, Copy, Clone)]
+pub struct LogEventParser;
+
+impl yellowstone_vixen_core::Parser for LogEventParser {
+    type Input = yellowstone_vixen_core::instruction::InstructionUpdate;
+    #[cfg(not(feature = "shared-data"))]
+    type Output = Vec<TokenLaunchpadProgramEvent>;
+    #[cfg(feature = "shared-data")]
+    type Output = InstructionUpdateOutput<Vec<TokenLaunchpadProgramEvent>>;
+
+    fn id(&self) -> std::borrow::Cow<str> { "TokenLaunchpad::LogEventParser".into() }
+
+    fn prefilter(&self) -> yellowstone_vixen_core::Prefilter {
+        yellowstone_vixen_core::Prefilter::builder()
+            .transaction_accounts([ID])
+            .build()
+            .unwrap()
+    }
+
+    async fn parse(
+        &self,
+        ix_update: &yellowstone_vixen_core::instruction::InstructionUpdate,
+    ) -> yellowstone_vixen_core::ParseResult<Self::Output> {
+        if !ix_update.program.equals_ref(ID) {
+            return Err(yellowstone_vixen_core::ParseError::Filtered);
+        }
+
+        let events = TokenLaunchpadProgramEvent::from_logs(ix_update.own_log_messages());
+
+        if events.is_empty() {
+            return Err(yellowstone_vixen_core::ParseError::Filtered);
+        }
+
+        #[cfg(not(feature = "shared-data"))]
+        return Ok(events);
+
+        #[cfg(feature = "shared-data")]
+        Ok(InstructionUpdateOutput {
+            parsed_ix: events,
+            shared_data: Arc::clone(&ix_update.shared),
+        })
+    }
+}
+
+impl yellowstone_vixen_core::ProgramParser for LogEventParser {
+    #[inline]
+    fn program_id(&self) -> yellowstone_vixen_core::Pubkey { ID.to_bytes().into() }
+}
+
+/// TokenLaunchpad Instructions, along with the events the program records with
+/// `emit_cpi!`
+#[derive(Debug)]
+#[cfg_attr(feature = "tracing", derive(strum_macros::Display))]
+#[allow(clippy::large_enum_variant)]
+pub enum TokenLaunchpadProgramIxOrEvent {
+    Ix(TokenLaunchpadProgramIx),
+    CpiEvent(TokenLaunchpadProgramEvent),
+}
+
+/// Parses the self-invoked instructions `emit_cpi!` records events with, and
+/// any other instruction with the generated [`InstructionParser`].
+#[derive(Debug, Copy, Clone)]
+pub struct InstructionEventParser;
+
+impl yellowstone_vixen_core::Parser for InstructionEventParser {
+    type Input = yellowstone_vixen_core::instruction::InstructionUpdate;
+    #[cfg(not(feature = "shared-data"))]
+    type Output = TokenLaunchpadProgramIxOrEvent;
+    #[cfg(feature = "shared-data")]
+    type Output = InstructionUpdateOutput<TokenLaunchpadProgramIxOrEvent>;
+
+    fn id(&self) -> std::borrow::Cow<str> { "TokenLaunchpad::InstructionEventParser".into() }
+
+    fn prefilter(&self) -> yellowstone_vixen_core::Prefilter {
+        yellowstone_vixen_core::Parser::prefilter(&InstructionParser)
+    }
+
+    async fn parse(
+        &self,
+        ix_update: &yellowstone_vixen_core::instruction::InstructionUpdate,
+    ) -> yellowstone_vixen_core::ParseResult<Self::Output> {
+        let event_data = match ix_update
+            .data
+            .strip_prefix(&yellowstone_vixen_core::anchor::EVENT_IX_TAG)
+        {
+            Some(data) if ix_update.program.equals_ref(ID) => data,
+            _ => {
+                let ix =
+                    yellowstone_vixen_core::Parser::parse(&InstructionParser, ix_update).await?;
+
+                #[cfg(not(feature = "shared-data"))]
+                return Ok(TokenLaunchpadProgramIxOrEvent::Ix(ix));
+
+                #[cfg(feature = "shared-data")]
+                return Ok(InstructionUpdateOutput {
+                    parsed_ix: TokenLaunchpadProgramIxOrEvent::Ix(ix.parsed_ix),
+                    shared_data: ix.shared_data,
+                });
+            },
+        };
+
+        let event = TokenLaunchpadProgramEvent::try_unpack_cpi(&ix_update.accounts, event_data);
+
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &event {
+            tracing::debug!(
+                name: "incorrectly_parsed_event",
+                name = "cpi_event",
+                program = ID.to_string(),
+                event = "deserialization_error",
+                discriminator = ?event_data.get(..8),
+                error = ?e
+            );
+        }
+
+        #[cfg(not(feature = "shared-data"))]
+        return Ok(TokenLaunchpadProgramIxOrEvent::CpiEvent(event?));
+
+        #[cfg(feature = "shared-data")]
+        Ok(InstructionUpdateOutput {
+            parsed_ix: TokenLaunchpadProgramIxOrEvent::CpiEvent(event?),
+            shared_data: Arc::clone(&ix_update.shared),
+        })
+    }
+}
+
+impl yellowstone_vixen_core::ProgramParser for InstructionEventParser {
+    #[inline]
+    fn program_id(&self) -> yellowstone_vixen_core::Pubkey { ID.to_bytes().into() }
+}
+
+/// Protobuf messages for the Moonshot events, in the
+/// `vixen.parser.moonshot` package.
+///
+/// Codama only renders messages for the instructions, accounts and types of
+/// the program, so these are derived by hand. They are not part of
+/// [`DESCRIPTOR_SET`](crate::proto_def::DESCRIPTOR_SET).
+// #[cfg(feature = "proto")]
+pub mod proto_def {
+    #![allow(clippy::large_enum_variant)]
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct TradeEvent {
+        #[prost(uint64, tag = "1")]
+        pub amount: u64,
+        #[prost(uint64, tag = "2")]
+        pub collateral_amount: u64,
+        #[prost(uint64, tag = "3")]
+        pub dex_fee: u64,
+        #[prost(uint64, tag = "4")]
+        pub helio_fee: u64,
+        #[prost(uint64, tag = "5")]
+        pub allocation: u64,
+        #[prost(string, tag = "6")]
+        pub curve: ::prost::alloc::string::String,
+        #[prost(string, tag = "7")]
+        pub cost_token: ::prost::alloc::string::String,
+        #[prost(string, tag = "8")]
+        pub sender: ::prost::alloc::string::String,
+        #[prost(enumeration = "crate::proto_def::TradeType", tag = "9")]
+        pub r#type: i32,
+        #[prost(string, tag = "10")]
+        pub label: ::prost::alloc::string::String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct MigrationEvent {
+        #[prost(uint64, tag = "1")]
+        pub tokens_migrated: u64,
+        #[prost(uint64, tag = "2")]
+        pub tokens_burned: u64,
+        #[prost(uint64, tag = "3")]
+        pub collateral_migrated: u64,
+        #[prost(uint64, tag = "4")]
+        pub fee: u64,
+        #[prost(string, tag = "5")]
+        pub label: ::prost::alloc::string::String,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramEvent {
+        #[prost(oneof = "program_event::EventOneof", tags = "1, 2")]
+        pub event_oneof: ::core::option::Option<program_event::EventOneof>,
+    }
+
+    /// Nested message and enum types in `ProgramEvent`.
+    pub mod program_event {
+        use super::{MigrationEvent, TradeEvent};
+
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum EventOneof {
+            #[prost(message, tag = "1")]
+            Migration(MigrationEvent),
+            #[prost(message, tag = "2")]
+            Trade(TradeEvent),
+        }
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramEvents {
+        #[prost(message, repeated, tag = "1")]
+        pub events: ::prost::alloc::vec::Vec<ProgramEvent>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramIxOrEvent {
+        #[prost(oneof = "program_ix_or_event::IxOrEventOneof", tags = "1, 2")]
+        pub ix_or_event_oneof: ::core::option::Option<program_ix_or_event::IxOrEventOneof>,
+    }
+
+    /// Nested message and enum types in `ProgramIxOrEvent`.
+    pub mod program_ix_or_event {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum IxOrEventOneof {
+            #[prost(message, tag = "1")]
+            Ix(crate::proto_def::ProgramIxs),
+            #[prost(message, tag = "2")]
+            CpiEvent(super::ProgramEvent),
+        }
+    }
+
+    macro_rules! impl_name {
+        ($($msg:ident),*) => {
+            $(impl ::prost::Name for $msg {
+                const NAME: &'static str = stringify!($msg);
+                const PACKAGE: &'static str = "vixen.parser.moonshot";
+            })*
+        };
+    }
+
+    impl_name!(
+        TradeEvent,
+        MigrationEvent,
+        ProgramEvent,
+        ProgramEvents,
+        ProgramIxOrEvent
+    );
+}
+
+// #[cfg(feature = "proto")]
+mod proto_parser {
+    use yellowstone_vixen_core::proto::ParseProto;
+
+    use super::{
+        proto_def, InstructionEventParser, LogEventParser, MigrationEvent,
+        TokenLaunchpadProgramEvent, TokenLaunchpadProgramIxOrEvent, TradeEvent,
+    };
+    use crate::proto_helpers::proto_types_parsers::IntoProto;
+
+    impl IntoProto<proto_def::TradeEvent> for TradeEvent {
+        fn into_proto(self) -> proto_def::TradeEvent {
+            proto_def::TradeEvent {
+                amount: self.amount,
+                collateral_amount: self.collateral_amount,
+                dex_fee: self.dex_fee,
+                helio_fee: self.helio_fee,
+                allocation: self.allocation,
+                curve: self.curve.to_string(),
+                cost_token: self.cost_token.to_string(),
+                sender: self.sender.to_string(),
+                r#type: self.r#type as i32,
+                label: self.label,
+            }
+        }
+    }
+
+    impl IntoProto<proto_def::MigrationEvent> for MigrationEvent {
+        fn into_proto(self) -> proto_def::MigrationEvent {
+            proto_def::MigrationEvent {
+                tokens_migrated: self.tokens_migrated,
+                tokens_burned: self.tokens_burned,
+                collateral_migrated: self.collateral_migrated,
+                fee: self.fee,
+                label: self.label,
+            }
+        }
+    }
+
+    impl IntoProto<proto_def::ProgramEvent> for TokenLaunchpadProgramEvent {
+        fn into_proto(self) -> proto_def::ProgramEvent {
+            match self {
+                TokenLaunchpadProgramEvent::Migration(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::Migration(
+                        event.into_proto(),
+                    )),
+                },
+                TokenLaunchpadProgramEvent::Trade(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::Trade(
+                        event.into_proto(),
+                    )),
+                },
+            }
+        }
+    }
+
+    impl ParseProto for LogEventParser {
+        type Message = proto_def::ProgramEvents;
+
+        fn output_into_message(value: Self::Output) -> Self::Message {
+            #[cfg(not(feature = "shared-data"))]
+            let events = value;
+
+            #[cfg(feature = "shared-data")]
+            let events = value.parsed_ix;
+
+            proto_def::ProgramEvents {
+                events: events.into_iter().map(IntoProto::into_proto).collect(),
+            }
+        }
+    }
+
+    impl IntoProto<proto_def::ProgramIxOrEvent> for TokenLaunchpadProgramIxOrEvent {
+        fn into_proto(self) -> proto_def::ProgramIxOrEvent {
+            match self {
+                TokenLaunchpadProgramIxOrEvent::Ix(ix) => proto_def::ProgramIxOrEvent {
+                    ix_or_event_oneof: Some(proto_def::program_ix_or_event::IxOrEventOneof::Ix(
+                        ix.into_proto(),
+                    )),
+                },
+                TokenLaunchpadProgramIxOrEvent::CpiEvent(event) => proto_def::ProgramIxOrEvent {
+                    ix_or_event_oneof: Some(
+                        proto_def::program_ix_or_event::IxOrEventOneof::CpiEvent(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+            }
+        }
+    }
+
+    impl ParseProto for InstructionEventParser {
+        type Message = proto_def::ProgramIxOrEvent;
+
+        fn output_into_message(value: Self::Output) -> Self::Message {
+            #[cfg(not(feature = "shared-data"))]
+            return value.into_proto();
+
+            #[cfg(feature = "shared-data")]
+            value.parsed_ix.into_proto()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yellowstone_vixen_core::anchor::EVENT_IX_TAG;
+
+    use super::*;
+
+    // The base58 instruction data of an `emit_cpi!` self-invocation
+    const CPI_EVENT: &str = "4TREoEUsrZemsVZeDXPSuHqEPcYNVrTJfZzfZndYVEEMMVENCaMb9Ji2Hfxpsnp1iqjNJf6zyrYh\
+         PFe6NsKngVP6diFqX5VUimUBV8MQksAuqtT73Bg35fDNDyrPFLAuSQEKNMUAMHug6LZWkBjuzmnU\
+         uWCLs8zmDDGbxXD7SYi8gRqf7rnzSbWTkUawjcEfPeaDP8kFCAh7dCMW1uf3ndwmZpYUgqyNYT";
+
+    #[test]
+    fn test_unpack_cpi_event() {
+        let data = bs58::decode(CPI_EVENT).into_vec().unwrap();
+        assert_eq!(data[..8], EVENT_IX_TAG);
+
+        let event = TokenLaunchpadProgramEvent::try_unpack_cpi(
+            &[EVENT_AUTHORITY.to_bytes().into()],
+            &data[8..],
+        );
+        let Ok(TokenLaunchpadProgramEvent::Trade(event)) = event else {
+            panic!("Invalid event");
+        };
+
+        assert_eq!(
+            event.curve.to_string(),
+            "Gv3DCApwG35qzoWvNc21D8JzPN6oK32kY9HV5FKsUbVd"
+        );
+        assert_eq!(
+            event.cost_token.to_string(),
+            "So11111111111111111111111111111111111111112"
+        );
+        assert_eq!(event.amount, 1_500_000_000_000);
+        assert_eq!(event.collateral_amount, 42_000_000);
+        assert_eq!(event.dex_fee, 420_000);
+        assert_eq!(event.r#type, crate::types::TradeType::Buy);
+        assert_eq!(event.label, "moonshot");
+
+        assert!(
+            TokenLaunchpadProgramEvent::try_unpack_cpi(&[ID.to_bytes().into()], &data[8..])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_events_from_logs() {
+        // The base64 `Program data:` record of an `emit!` event
+        const LOG_EVENT: &str =
+            "vdt/007mYe4AmPc+XQEAAIDegAIAAAAAoGgGAAAAAAAQpAAAAAAAAAAAAAAAAAAA7HWV+/\
+             7w4V4K67KAF+bN8Z2sYRw/+isFHkP+j57lf1wGm4hX/\
+             quBhPtof2NGGMA12sQ53BrrO1WYoPAAAAAAAe6e9xHN/\
+             BuAp+d1RbXAbIeP8bWv7BRJHHSOdkRY8UpLAAgAAABtb29uc2hvdA==";
+        // A record with an unknown discriminator
+        const UNKNOWN_EVENT: &str =
+            "AQIDBAUGBwgAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYn";
+
+        let logs = [
+            format!("Program {ID} invoke [1]"),
+            format!("Program data: {LOG_EVENT}"),
+            format!("Program data: {UNKNOWN_EVENT}"),
+            format!("Program {ID} success"),
+        ];
+
+        let events = TokenLaunchpadProgramEvent::from_logs(&logs);
+        let [TokenLaunchpadProgramEvent::Trade(event)] = events.as_slice() else {
+            panic!("Invalid events: {events:?}");
+        };
+
+        assert_eq!(
+            event.curve.to_string(),
+            "Gv3DCApwG35qzoWvNc21D8JzPN6oK32kY9HV5FKsUbVd"
+        );
+        assert_eq!(event.amount, 1_500_000_000_000);
+        assert_eq!(event.collateral_amount, 42_000_000);
+        assert_eq!(event.dex_fee, 420_000);
+        assert_eq!(event.label, "moonshot");
+    }
+}
@@ -1,3 +1,6 @@
+pub mod discriminators;
+mod errors_parser;
+pub mod events_parser;
 mod generated_parser;
 mod generated_sdk;
 pub use generated::*;
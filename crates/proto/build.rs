@@ -22,6 +22,15 @@ fn main() {
             .unwrap();
     }
 
+    #[cfg(feature = "transaction")]
+    {
+        prost_build::Config::new()
+            .enable_type_names()
+            .file_descriptor_set_path(out_dir.join("vixen.transaction.bin"))
+            .compile_protos(&["proto/transaction.proto"], &["proto"])
+            .unwrap();
+    }
+
     #[cfg(feature = "stream")]
     {
         tonic_build::configure()
@@ -4,7 +4,7 @@
 //! Protobuf definitions used by the `yellowstone-vixen` family of crates.
 
 pub extern crate prost;
-#[cfg(feature = "stream")]
+#[cfg(any(feature = "stream", feature = "transaction"))]
 pub extern crate prost_types;
 #[cfg(feature = "stream")]
 pub extern crate tonic;
@@ -38,6 +38,20 @@ mod vixen {
         }
     }
 
+    #[cfg(feature = "transaction")]
+    pub mod transaction {
+        #![allow(missing_docs, clippy::all)]
+
+        //! Protobuf definitions for decoded transactions.
+
+        include!(concat!(env!("OUT_DIR"), "/vixen.transaction.rs"));
+
+        /// Compiled protobuf file descriptor set for the `vixen.transaction`
+        /// package.
+        pub const DESCRIPTOR_SET: &[u8] =
+            include_bytes!(concat!(env!("OUT_DIR"), "/vixen.transaction.bin"));
+    }
+
     #[cfg(feature = "stream")]
     pub mod stream {
         #![allow(missing_docs)]
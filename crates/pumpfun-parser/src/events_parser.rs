@@ -0,0 +1,549 @@
+//! Parsers for the Anchor events emitted by the Pump program.
+//!
+//! Codama does not generate event parsers, so this module is maintained by
+//! hand alongside the generated code.
+
+#[cfg(feature = "shared-data")]
+use std::sync::Arc;
+
+use solana_pubkey::{pubkey, Pubkey};
+#[cfg(feature = "shared-data")]
+use yellowstone_vixen_core::InstructionUpdateOutput;
+
+use crate::{
+    deserialize_checked,
+    instructions_parser::{InstructionParser, PumpProgramIx},
+    types::{
+        CollectCreatorFeeEvent, CompleteEvent, CompletePumpAmmMigrationEvent, CreateEvent,
+        ExtendAccountEvent, SetCreatorEvent, SetMetaplexCreatorEvent, SetParamsEvent, TradeEvent,
+        UpdateGlobalAuthorityEvent,
+    },
+    ID,
+};
+
+/// The PDA signing the self-invoked instructions `emit_cpi!` records events
+/// with, derived from the `__event_authority` seed.
+pub const EVENT_AUTHORITY: Pubkey = pubkey!("Ce6TQqeHC9p8KetsN6JsjHK7UTZk7nasjjnr7XxXp9F1");
+
+/// Pump Events
+#[derive(Debug)]
+#[cfg_attr(feature = "tracing", derive(strum_macros::Display))]
+#[allow(clippy::large_enum_variant)]
+pub enum PumpProgramEvent {
+    CollectCreatorFee(CollectCreatorFeeEvent),
+    Complete(CompleteEvent),
+    CompletePumpAmmMigration(CompletePumpAmmMigrationEvent),
+    Create(CreateEvent),
+    ExtendAccount(ExtendAccountEvent),
+    SetCreator(SetCreatorEvent),
+    SetMetaplexCreator(SetMetaplexCreatorEvent),
+    SetParams(SetParamsEvent),
+    Trade(TradeEvent),
+    UpdateGlobalAuthority(UpdateGlobalAuthorityEvent),
+}
+
+impl PumpProgramEvent {
+    /// Deserialize an Anchor event record, consisting of the 8-byte event
+    /// discriminator followed by the Borsh-serialized event.
+    pub fn try_unpack(data: &[u8]) -> yellowstone_vixen_core::ParseResult<Self> {
+        let event_discriminator: [u8; 8] = data
+            .get(0..8)
+            .ok_or(yellowstone_vixen_core::ParseError::from(
+                "Event data too short".to_owned(),
+            ))?
+            .try_into()?;
+        let event_data = &data[8..];
+        match event_discriminator {
+            [122, 2, 127, 1, 14, 191, 12, 175] => Ok(PumpProgramEvent::CollectCreatorFee(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [95, 114, 97, 156, 212, 46, 152, 8] => Ok(PumpProgramEvent::Complete(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [189, 233, 93, 185, 92, 148, 234, 148] => {
+                Ok(PumpProgramEvent::CompletePumpAmmMigration(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [27, 114, 169, 77, 222, 235, 99, 118] => Ok(PumpProgramEvent::Create(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [97, 97, 215, 144, 93, 146, 22, 124] => Ok(PumpProgramEvent::ExtendAccount(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [237, 52, 123, 37, 245, 251, 72, 210] => Ok(PumpProgramEvent::SetCreator(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [142, 203, 6, 32, 127, 105, 191, 162] => Ok(PumpProgramEvent::SetMetaplexCreator(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [223, 195, 159, 246, 62, 48, 143, 131] => Ok(PumpProgramEvent::SetParams(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [189, 219, 127, 211, 78, 230, 97, 238] => Ok(PumpProgramEvent::Trade(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [182, 195, 137, 42, 35, 206, 207, 247] => Ok(PumpProgramEvent::UpdateGlobalAuthority(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            _ => Err(yellowstone_vixen_core::ParseError::from(
+                "Invalid Event discriminator".to_owned(),
+            )),
+        }
+    }
+
+    /// Decode the events the program logged with `sol_log_data` in the given
+    /// log lines, skipping records that are not known events.
+    pub fn from_logs<'a, I>(log_messages: I) -> Vec<Self>
+    where
+        I: IntoIterator<Item = &'a String>,
+        I::IntoIter: 'a,
+    {
+        yellowstone_vixen_core::logs::program_data(log_messages, ID.to_bytes().into())
+            .filter_map(|data| {
+                let event = Self::try_unpack(&data);
+
+                #[cfg(feature = "tracing")]
+                if let Err(e) = &event {
+                    tracing::debug!(
+                        name: "incorrectly_parsed_event",
+                        name = "log_event",
+                        program = ID.to_string(),
+                        event = "deserialization_error",
+                        discriminator = ?data.get(..8),
+                        error = ?e
+                    );
+                }
+
+                event.ok()
+            })
+            .collect()
+    }
+
+    /// Deserialize an event recorded with `emit_cpi!` from the data of the
+    /// self-invoked instruction following its
+    /// [`EVENT_IX_TAG`](yellowstone_vixen_core::anchor::EVENT_IX_TAG),
+    /// checking that the instruction was signed by [`EVENT_AUTHORITY`].
+    pub fn try_unpack_cpi(
+        accounts: &[yellowstone_vixen_core::Pubkey],
+        data: &[u8],
+    ) -> yellowstone_vixen_core::ParseResult<Self> {
+        if !accounts
+            .first()
+            .is_some_and(|a| a.equals_ref(EVENT_AUTHORITY))
+        {
+            return Err(yellowstone_vixen_core::ParseError::from(
+                "Event instruction not signed by the event authority".to_owned(),
+            ));
+        }
+
+        Self::try_unpack(data)
+    }
+}
+
+/// Parses the events an instruction logs with `sol_log_data`, as emitted by
+/// Anchor's `emit!` macro.
+#[derive(Debug, Copy, Clone)]
+pub struct LogEventParser;
+
+impl yellowstone_vixen_core::Parser for LogEventParser {
+    type Input = yellowstone_vixen_core::instruction::InstructionUpdate;
+    #[cfg(not(feature = "shared-data"))]
+    type Output = Vec<PumpProgramEvent>;
+    #[cfg(feature = "shared-data")]
+    type Output = InstructionUpdateOutput<Vec<PumpProgramEvent>>;
+
+    fn id(&self) -> std::borrow::Cow<str> { "Pump::LogEventParser".into() }
+
+    fn prefilter(&self) -> yellowstone_vixen_core::Prefilter {
+        yellowstone_vixen_core::Prefilter::builder()
+            .transaction_accounts([ID])
+            .build()
+            .unwrap()
+    }
+
+    async fn parse(
+        &self,
+        ix_update: &yellowstone_vixen_core::instruction::InstructionUpdate,
+    ) -> yellowstone_vixen_core::ParseResult<Self::Output> {
+        if !ix_update.program.equals_ref(ID) {
+            return Err(yellowstone_vixen_core::ParseError::Filtered);
+        }
+
+        let events = PumpProgramEvent::from_logs(ix_update.own_log_messages());
+
+        if events.is_empty() {
+            return Err(yellowstone_vixen_core::ParseError::Filtered);
+        }
+
+        #[cfg(not(feature = "shared-data"))]
+        return Ok(events);
+
+        #[cfg(feature = "shared-data")]
+        Ok(InstructionUpdateOutput {
+            parsed_ix: events,
+            shared_data: Arc::clone(&ix_update.shared),
+        })
+    }
+}
+
+impl yellowstone_vixen_core::ProgramParser for LogEventParser {
+    #[inline]
+    fn program_id(&self) -> yellowstone_vixen_core::Pubkey { ID.to_bytes().into() }
+}
+
+/// Pump Instructions, along with the events the program records with
+/// `emit_cpi!`
+#[derive(Debug)]
+#[cfg_attr(feature = "tracing", derive(strum_macros::Display))]
+#[allow(clippy::large_enum_variant)]
+pub enum PumpProgramIxOrEvent {
+    Ix(PumpProgramIx),
+    CpiEvent(PumpProgramEvent),
+}
+
+/// Parses the self-invoked instructions `emit_cpi!` records events with, and
+/// any other instruction with the generated [`InstructionParser`].
+#[derive(Debug, Copy, Clone)]
+pub struct InstructionEventParser;
+
+impl yellowstone_vixen_core::Parser for InstructionEventParser {
+    type Input = yellowstone_vixen_core::instruction::InstructionUpdate;
+    #[cfg(not(feature = "shared-data"))]
+    type Output = PumpProgramIxOrEvent;
+    #[cfg(feature = "shared-data")]
+    type Output = InstructionUpdateOutput<PumpProgramIxOrEvent>;
+
+    fn id(&self) -> std::borrow::Cow<str> { "Pump::InstructionEventParser".into() }
+
+    fn prefilter(&self) -> yellowstone_vixen_core::Prefilter {
+        yellowstone_vixen_core::Parser::prefilter(&InstructionParser)
+    }
+
+    async fn parse(
+        &self,
+        ix_update: &yellowstone_vixen_core::instruction::InstructionUpdate,
+    ) -> yellowstone_vixen_core::ParseResult<Self::Output> {
+        let event_data = match ix_update
+            .data
+            .strip_prefix(&yellowstone_vixen_core::anchor::EVENT_IX_TAG)
+        {
+            Some(data) if ix_update.program.equals_ref(ID) => data,
+            _ => {
+                let ix =
+                    yellowstone_vixen_core::Parser::parse(&InstructionParser, ix_update).await?;
+
+                #[cfg(not(feature = "shared-data"))]
+                return Ok(PumpProgramIxOrEvent::Ix(ix));
+
+                #[cfg(feature = "shared-data")]
+                return Ok(InstructionUpdateOutput {
+                    parsed_ix: PumpProgramIxOrEvent::Ix(ix.parsed_ix),
+                    shared_data: ix.shared_data,
+                });
+            },
+        };
+
+        let event = PumpProgramEvent::try_unpack_cpi(&ix_update.accounts, event_data);
+
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &event {
+            tracing::debug!(
+                name: "incorrectly_parsed_event",
+                name = "cpi_event",
+                program = ID.to_string(),
+                event = "deserialization_error",
+                discriminator = ?event_data.get(..8),
+                error = ?e
+            );
+        }
+
+        #[cfg(not(feature = "shared-data"))]
+        return Ok(PumpProgramIxOrEvent::CpiEvent(event?));
+
+        #[cfg(feature = "shared-data")]
+        Ok(InstructionUpdateOutput {
+            parsed_ix: PumpProgramIxOrEvent::CpiEvent(event?),
+            shared_data: Arc::clone(&ix_update.shared),
+        })
+    }
+}
+
+impl yellowstone_vixen_core::ProgramParser for InstructionEventParser {
+    #[inline]
+    fn program_id(&self) -> yellowstone_vixen_core::Pubkey { ID.to_bytes().into() }
+}
+
+/// Protobuf messages for the Pump events, in the
+/// `vixen.parser.pumpfun` package.
+///
+/// Codama only renders messages for the instructions, accounts and types of
+/// the program, so these are derived by hand. They are not part of
+/// [`DESCRIPTOR_SET`](crate::proto_def::DESCRIPTOR_SET).
+// #[cfg(feature = "proto")]
+pub mod proto_def {
+    #![allow(clippy::large_enum_variant)]
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramEvent {
+        #[prost(
+            oneof = "program_event::EventOneof",
+            tags = "1, 2, 3, 4, 5, 6, 7, 8, 9, 10"
+        )]
+        pub event_oneof: ::core::option::Option<program_event::EventOneof>,
+    }
+
+    /// Nested message and enum types in `ProgramEvent`.
+    pub mod program_event {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum EventOneof {
+            #[prost(message, tag = "1")]
+            CollectCreatorFee(crate::proto_def::CollectCreatorFeeEvent),
+            #[prost(message, tag = "2")]
+            Complete(crate::proto_def::CompleteEvent),
+            #[prost(message, tag = "3")]
+            CompletePumpAmmMigration(crate::proto_def::CompletePumpAmmMigrationEvent),
+            #[prost(message, tag = "4")]
+            Create(crate::proto_def::CreateEvent),
+            #[prost(message, tag = "5")]
+            ExtendAccount(crate::proto_def::ExtendAccountEvent),
+            #[prost(message, tag = "6")]
+            SetCreator(crate::proto_def::SetCreatorEvent),
+            #[prost(message, tag = "7")]
+            SetMetaplexCreator(crate::proto_def::SetMetaplexCreatorEvent),
+            #[prost(message, tag = "8")]
+            SetParams(crate::proto_def::SetParamsEvent),
+            #[prost(message, tag = "9")]
+            Trade(crate::proto_def::TradeEvent),
+            #[prost(message, tag = "10")]
+            UpdateGlobalAuthority(crate::proto_def::UpdateGlobalAuthorityEvent),
+        }
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramEvents {
+        #[prost(message, repeated, tag = "1")]
+        pub events: ::prost::alloc::vec::Vec<ProgramEvent>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramIxOrEvent {
+        #[prost(oneof = "program_ix_or_event::IxOrEventOneof", tags = "1, 2")]
+        pub ix_or_event_oneof: ::core::option::Option<program_ix_or_event::IxOrEventOneof>,
+    }
+
+    /// Nested message and enum types in `ProgramIxOrEvent`.
+    pub mod program_ix_or_event {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum IxOrEventOneof {
+            #[prost(message, tag = "1")]
+            Ix(crate::proto_def::ProgramIxs),
+            #[prost(message, tag = "2")]
+            CpiEvent(super::ProgramEvent),
+        }
+    }
+
+    macro_rules! impl_name {
+        ($($msg:ident),*) => {
+            $(impl ::prost::Name for $msg {
+                const NAME: &'static str = stringify!($msg);
+                const PACKAGE: &'static str = "vixen.parser.pumpfun";
+            })*
+        };
+    }
+
+    impl_name!(ProgramEvent, ProgramEvents, ProgramIxOrEvent);
+}
+
+// #[cfg(feature = "proto")]
+mod proto_parser {
+    use yellowstone_vixen_core::proto::ParseProto;
+
+    use super::{
+        proto_def, InstructionEventParser, LogEventParser, PumpProgramEvent, PumpProgramIxOrEvent,
+    };
+    use crate::proto_helpers::proto_types_parsers::IntoProto;
+
+    impl IntoProto<proto_def::ProgramEvent> for PumpProgramEvent {
+        fn into_proto(self) -> proto_def::ProgramEvent {
+            match self {
+                PumpProgramEvent::CollectCreatorFee(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::CollectCreatorFee(
+                        event.into_proto(),
+                    )),
+                },
+                PumpProgramEvent::Complete(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::Complete(
+                        event.into_proto(),
+                    )),
+                },
+                PumpProgramEvent::CompletePumpAmmMigration(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(
+                        proto_def::program_event::EventOneof::CompletePumpAmmMigration(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+                PumpProgramEvent::Create(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::Create(
+                        event.into_proto(),
+                    )),
+                },
+                PumpProgramEvent::ExtendAccount(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::ExtendAccount(
+                        event.into_proto(),
+                    )),
+                },
+                PumpProgramEvent::SetCreator(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::SetCreator(
+                        event.into_proto(),
+                    )),
+                },
+                PumpProgramEvent::SetMetaplexCreator(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::SetMetaplexCreator(
+                        event.into_proto(),
+                    )),
+                },
+                PumpProgramEvent::SetParams(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::SetParams(
+                        event.into_proto(),
+                    )),
+                },
+                PumpProgramEvent::Trade(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::Trade(
+                        event.into_proto(),
+                    )),
+                },
+                PumpProgramEvent::UpdateGlobalAuthority(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::UpdateGlobalAuthority(
+                        event.into_proto(),
+                    )),
+                },
+            }
+        }
+    }
+
+    impl ParseProto for LogEventParser {
+        type Message = proto_def::ProgramEvents;
+
+        fn output_into_message(value: Self::Output) -> Self::Message {
+            #[cfg(not(feature = "shared-data"))]
+            let events = value;
+
+            #[cfg(feature = "shared-data")]
+            let events = value.parsed_ix;
+
+            proto_def::ProgramEvents {
+                events: events.into_iter().map(IntoProto::into_proto).collect(),
+            }
+        }
+    }
+
+    impl IntoProto<proto_def::ProgramIxOrEvent> for PumpProgramIxOrEvent {
+        fn into_proto(self) -> proto_def::ProgramIxOrEvent {
+            match self {
+                PumpProgramIxOrEvent::Ix(ix) => proto_def::ProgramIxOrEvent {
+                    ix_or_event_oneof: Some(proto_def::program_ix_or_event::IxOrEventOneof::Ix(
+                        ix.into_proto(),
+                    )),
+                },
+                PumpProgramIxOrEvent::CpiEvent(event) => proto_def::ProgramIxOrEvent {
+                    ix_or_event_oneof: Some(
+                        proto_def::program_ix_or_event::IxOrEventOneof::CpiEvent(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+            }
+        }
+    }
+
+    impl ParseProto for InstructionEventParser {
+        type Message = proto_def::ProgramIxOrEvent;
+
+        fn output_into_message(value: Self::Output) -> Self::Message {
+            #[cfg(not(feature = "shared-data"))]
+            return value.into_proto();
+
+            #[cfg(feature = "shared-data")]
+            value.parsed_ix.into_proto()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yellowstone_vixen_core::anchor::EVENT_IX_TAG;
+
+    use super::*;
+
+    // The base58 instruction data of an `emit_cpi!` self-invocation
+    const CPI_EVENT: &str = "2zjR1PvPvgqdhPdZLxuWCL7RJ3LVWkyWt6UfjRKaEhPph71G6dtJPpE4phabjwZJ4ZgUpG4Pkwpb\
+         XskUF5fyrRfU7e2kgSt4nqivTSBVA8D26gjfudbkjjYTrY5Zb2DUwZ952qz66K12kNP7nYg52iPK\
+         EuNL2Z8hMHbHMFJCfcFEgwo1Uf5JYwujD73JiQDVxFbELqZkYyDxCaYVLm3Lo383hQbHbDmP14rn\
+         gtfCWqbLaV3DJr2pMfjeK6VDDDJnuY9Ax8eingtv4aY4VcZrjDk4N4xBs6DE5g3NpZFPFaNEdfeN\
+         rygzA6CMGybf9PM";
+
+    #[test]
+    fn test_unpack_cpi_event() {
+        let data = bs58::decode(CPI_EVENT).into_vec().unwrap();
+        assert_eq!(data[..8], EVENT_IX_TAG);
+
+        let event =
+            PumpProgramEvent::try_unpack_cpi(&[EVENT_AUTHORITY.to_bytes().into()], &data[8..]);
+        let Ok(PumpProgramEvent::Trade(event)) = event else {
+            panic!("Invalid event");
+        };
+
+        assert_eq!(
+            event.mint.to_string(),
+            "6GGm6eTUnmWMYsAEcdkbmv4vN6YacJyeZYteMbfuytrQ"
+        );
+        assert_eq!(
+            event.user.to_string(),
+            "9PmgynCb1YoK1D4ZdpWUi4MjSJ8AASzFojs2xAUuekRr"
+        );
+        assert_eq!(event.sol_amount, 250_000_000);
+        assert_eq!(event.token_amount, 8_604_736_140_905);
+        assert!(event.is_buy);
+        assert_eq!(event.timestamp, 1_760_659_200);
+        assert_eq!(event.fee, 2_375_000);
+        assert_eq!(event.creator_fee, 125_000);
+
+        assert!(PumpProgramEvent::try_unpack_cpi(&[ID.to_bytes().into()], &data[8..]).is_err());
+    }
+
+    #[test]
+    fn test_events_from_logs() {
+        // The base64 `Program data:` record of an `emit!` event
+        const LOG_EVENT: &str = "vdt/007mYe5OM/eM+BTzRj8Lh74Azp34GEMRZla30vzoGQmJPC1knYCy5g4AAAAAabY9ctMHAAAB\
+         fLLNtb07e882vmmCXOx6OL/HHqeqxg9VSdmQ57l/+FUAh/FoAAAAAIAopUYHAAAAAGAekMeoAwCA\
+         fIFKAAAAAAAIw4fwqgIAhyppRhBeulj32KzDj8+enPH1+JcnoBSJQ65hKVgkmSZfAAAAAAAAAFg9\
+         JAAAAAAAVMj/n1Je2gLlstVrWHnSNNKJndbxw+GhMjYDrqOFTnQFAAAAAAAAAEjoAQAAAAAA";
+        // A record with an unknown discriminator
+        const UNKNOWN_EVENT: &str =
+            "AQIDBAUGBwgAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYn";
+
+        let logs = [
+            format!("Program {ID} invoke [1]"),
+            format!("Program data: {LOG_EVENT}"),
+            format!("Program data: {UNKNOWN_EVENT}"),
+            format!("Program {ID} success"),
+        ];
+
+        let events = PumpProgramEvent::from_logs(&logs);
+        let [PumpProgramEvent::Trade(event)] = events.as_slice() else {
+            panic!("Invalid events: {events:?}");
+        };
+
+        assert_eq!(
+            event.mint.to_string(),
+            "6GGm6eTUnmWMYsAEcdkbmv4vN6YacJyeZYteMbfuytrQ"
+        );
+        assert_eq!(event.sol_amount, 250_000_000);
+        assert_eq!(event.token_amount, 8_604_736_140_905);
+        assert!(event.is_buy);
+        assert_eq!(event.real_token_reserves, 750_900_000_000_000);
+        assert_eq!(event.creator_fee, 125_000);
+    }
+}
@@ -0,0 +1,44 @@
+//! Discriminators of the Raydium Launchpad program accounts.
+//!
+//! Codama does not generate these, so this module is maintained by hand
+//! alongside the generated code.
+
+use yellowstone_vixen_core::{AccountFilter, Prefilter};
+
+/// Discriminators of the accounts
+/// [`AccountParser`](crate::accounts_parser::AccountParser) can parse.
+pub const ACCOUNT_DISCRIMINATORS: [[u8; 8]; 4] = [
+    [149, 8, 156, 202, 160, 252, 176, 217],
+    [160, 78, 128, 0, 248, 83, 230, 160],
+    [247, 237, 227, 245, 215, 195, 222, 70],
+    [106, 243, 221, 205, 230, 126, 85, 83],
+];
+
+/// Restrict the accounts matched by a prefilter to those starting with one
+/// of the [`ACCOUNT_DISCRIMINATORS`].
+///
+/// The account parser does not apply this by default, but can opt in with
+/// [`ParserExt::with_prefilter`](yellowstone_vixen_core::ParserExt::with_prefilter):
+///
+/// ```
+/// use yellowstone_vixen_core::{Parser, ParserExt};
+/// use yellowstone_vixen_raydium_launchpad_parser::{accounts_parser::AccountParser, discriminators};
+///
+/// let parser = AccountParser.with_prefilter("discriminators", discriminators::prefilter);
+/// assert!(!parser.prefilter().account.unwrap().filters.is_empty());
+/// ```
+pub fn prefilter(mut prefilter: Prefilter) -> Prefilter {
+    if let Some(account) = &mut prefilter.account {
+        account.filters = ACCOUNT_DISCRIMINATORS
+            .iter()
+            .map(|d| {
+                vec![AccountFilter::Memcmp {
+                    offset: 0,
+                    bytes: d.to_vec(),
+                }]
+            })
+            .collect();
+    }
+
+    prefilter
+}
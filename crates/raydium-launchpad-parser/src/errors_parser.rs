@@ -0,0 +1,25 @@
+//! Resolution of the custom error codes returned by the Raydium Launchpad program.
+//!
+//! Codama does not generate these impls, so this module is maintained by
+//! hand alongside the generated code.
+
+use crate::{
+    errors::RaydiumLaunchpadError, events_parser::InstructionEventParser,
+    instructions_parser::InstructionParser,
+};
+
+impl yellowstone_vixen_core::ProgramErrorParser for InstructionParser {
+    type Error = RaydiumLaunchpadError;
+
+    fn parse_error(&self, code: u32) -> Option<Self::Error> {
+        num_traits::FromPrimitive::from_u32(code)
+    }
+}
+
+impl yellowstone_vixen_core::ProgramErrorParser for InstructionEventParser {
+    type Error = RaydiumLaunchpadError;
+
+    fn parse_error(&self, code: u32) -> Option<Self::Error> {
+        num_traits::FromPrimitive::from_u32(code)
+    }
+}
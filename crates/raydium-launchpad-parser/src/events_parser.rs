@@ -0,0 +1,472 @@
+//! Parsers for the Anchor events emitted by the Raydium Launchpad program.
+//!
+//! Codama does not generate event parsers, so this module is maintained by
+//! hand alongside the generated code.
+
+#[cfg(feature = "shared-data")]
+use std::sync::Arc;
+
+use solana_pubkey::{pubkey, Pubkey};
+#[cfg(feature = "shared-data")]
+use yellowstone_vixen_core::InstructionUpdateOutput;
+
+use crate::{
+    deserialize_checked,
+    instructions_parser::{InstructionParser, RaydiumLaunchpadProgramIx},
+    types::{ClaimVestedEvent, CreateVestingEvent, PoolCreateEvent, TradeEvent},
+    ID,
+};
+
+/// The PDA signing the self-invoked instructions `emit_cpi!` records events
+/// with, derived from the `__event_authority` seed.
+pub const EVENT_AUTHORITY: Pubkey = pubkey!("2DPAtwB8L12vrMRExbLuyGnC7n2J5LNoZQSejeQGpwkr");
+
+/// RaydiumLaunchpad Events
+#[derive(Debug)]
+#[cfg_attr(feature = "tracing", derive(strum_macros::Display))]
+#[allow(clippy::large_enum_variant)]
+pub enum RaydiumLaunchpadProgramEvent {
+    ClaimVested(ClaimVestedEvent),
+    CreateVesting(CreateVestingEvent),
+    PoolCreate(PoolCreateEvent),
+    Trade(TradeEvent),
+}
+
+impl RaydiumLaunchpadProgramEvent {
+    /// Deserialize an Anchor event record, consisting of the 8-byte event
+    /// discriminator followed by the Borsh-serialized event.
+    pub fn try_unpack(data: &[u8]) -> yellowstone_vixen_core::ParseResult<Self> {
+        let event_discriminator: [u8; 8] = data
+            .get(0..8)
+            .ok_or(yellowstone_vixen_core::ParseError::from(
+                "Event data too short".to_owned(),
+            ))?
+            .try_into()?;
+        let event_data = &data[8..];
+        match event_discriminator {
+            [21, 194, 114, 87, 120, 211, 226, 32] => Ok(RaydiumLaunchpadProgramEvent::ClaimVested(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [150, 152, 11, 179, 52, 210, 191, 125] => {
+                Ok(RaydiumLaunchpadProgramEvent::CreateVesting(
+                    deserialize_checked(event_data, &event_discriminator)?,
+                ))
+            },
+            [151, 215, 226, 9, 118, 161, 115, 174] => Ok(RaydiumLaunchpadProgramEvent::PoolCreate(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            [189, 219, 127, 211, 78, 230, 97, 238] => Ok(RaydiumLaunchpadProgramEvent::Trade(
+                deserialize_checked(event_data, &event_discriminator)?,
+            )),
+            _ => Err(yellowstone_vixen_core::ParseError::from(
+                "Invalid Event discriminator".to_owned(),
+            )),
+        }
+    }
+
+    /// Decode the events the program logged with `sol_log_data` in the given
+    /// log lines, skipping records that are not known events.
+    pub fn from_logs<'a, I>(log_messages: I) -> Vec<Self>
+    where
+        I: IntoIterator<Item = &'a String>,
+        I::IntoIter: 'a,
+    {
+        yellowstone_vixen_core::logs::program_data(log_messages, ID.to_bytes().into())
+            .filter_map(|data| {
+                let event = Self::try_unpack(&data);
+
+                #[cfg(feature = "tracing")]
+                if let Err(e) = &event {
+                    tracing::debug!(
+                        name: "incorrectly_parsed_event",
+                        name = "log_event",
+                        program = ID.to_string(),
+                        event = "deserialization_error",
+                        discriminator = ?data.get(..8),
+                        error = ?e
+                    );
+                }
+
+                event.ok()
+            })
+            .collect()
+    }
+
+    /// Deserialize an event recorded with `emit_cpi!` from the data of the
+    /// self-invoked instruction following its
+    /// [`EVENT_IX_TAG`](yellowstone_vixen_core::anchor::EVENT_IX_TAG),
+    /// checking that the instruction was signed by [`EVENT_AUTHORITY`].
+    pub fn try_unpack_cpi(
+        accounts: &[yellowstone_vixen_core::Pubkey],
+        data: &[u8],
+    ) -> yellowstone_vixen_core::ParseResult<Self> {
+        if !accounts
+            .first()
+            .is_some_and(|a| a.equals_ref(EVENT_AUTHORITY))
+        {
+            return Err(yellowstone_vixen_core::ParseError::from(
+                "Event instruction not signed by the event authority".to_owned(),
+            ));
+        }
+
+        Self::try_unpack(data)
+    }
+}
+
+/// Parses the events an instruction logs with `sol_log_data`, as emitted by
+/// Anchor's `emit!` macro.
+#[derive(Debug, Copy, Clone)]
+pub struct LogEventParser;
+
+impl yellowstone_vixen_core::Parser for LogEventParser {
+    type Input = yellowstone_vixen_core::instruction::InstructionUpdate;
+    #[cfg(not(feature = "shared-data"))]
+    type Output = Vec<RaydiumLaunchpadProgramEvent>;
+    #[cfg(feature = "shared-data")]
+    type Output = InstructionUpdateOutput<Vec<RaydiumLaunchpadProgramEvent>>;
+
+    fn id(&self) -> std::borrow::Cow<str> { "RaydiumLaunchpad::LogEventParser".into() }
+
+    fn prefilter(&self) -> yellowstone_vixen_core::Prefilter {
+        yellowstone_vixen_core::Prefilter::builder()
+            .transaction_accounts([ID])
+            .build()
+            .unwrap()
+    }
+
+    async fn parse(
+        &self,
+        ix_update: &yellowstone_vixen_core::instruction::InstructionUpdate,
+    ) -> yellowstone_vixen_core::ParseResult<Self::Output> {
+        if !ix_update.program.equals_ref(ID) {
+            return Err(yellowstone_vixen_core::ParseError::Filtered);
+        }
+
+        let events = RaydiumLaunchpadProgramEvent::from_logs(ix_update.own_log_messages());
+
+        if events.is_empty() {
+            return Err(yellowstone_vixen_core::ParseError::Filtered);
+        }
+
+        #[cfg(not(feature = "shared-data"))]
+        return Ok(events);
+
+        #[cfg(feature = "shared-data")]
+        Ok(InstructionUpdateOutput {
+            parsed_ix: events,
+            shared_data: Arc::clone(&ix_update.shared),
+        })
+    }
+}
+
+impl yellowstone_vixen_core::ProgramParser for LogEventParser {
+    #[inline]
+    fn program_id(&self) -> yellowstone_vixen_core::Pubkey { ID.to_bytes().into() }
+}
+
+/// RaydiumLaunchpad Instructions, along with the events the program records with
+/// `emit_cpi!`
+#[derive(Debug)]
+#[cfg_attr(feature = "tracing", derive(strum_macros::Display))]
+#[allow(clippy::large_enum_variant)]
+pub enum RaydiumLaunchpadProgramIxOrEvent {
+    Ix(RaydiumLaunchpadProgramIx),
+    CpiEvent(RaydiumLaunchpadProgramEvent),
+}
+
+/// Parses the self-invoked instructions `emit_cpi!` records events with, and
+/// any other instruction with the generated [`InstructionParser`].
+#[derive(Debug, Copy, Clone)]
+pub struct InstructionEventParser;
+
+impl yellowstone_vixen_core::Parser for InstructionEventParser {
+    type Input = yellowstone_vixen_core::instruction::InstructionUpdate;
+    #[cfg(not(feature = "shared-data"))]
+    type Output = RaydiumLaunchpadProgramIxOrEvent;
+    #[cfg(feature = "shared-data")]
+    type Output = InstructionUpdateOutput<RaydiumLaunchpadProgramIxOrEvent>;
+
+    fn id(&self) -> std::borrow::Cow<str> { "RaydiumLaunchpad::InstructionEventParser".into() }
+
+    fn prefilter(&self) -> yellowstone_vixen_core::Prefilter {
+        yellowstone_vixen_core::Parser::prefilter(&InstructionParser)
+    }
+
+    async fn parse(
+        &self,
+        ix_update: &yellowstone_vixen_core::instruction::InstructionUpdate,
+    ) -> yellowstone_vixen_core::ParseResult<Self::Output> {
+        let event_data = match ix_update
+            .data
+            .strip_prefix(&yellowstone_vixen_core::anchor::EVENT_IX_TAG)
+        {
+            Some(data) if ix_update.program.equals_ref(ID) => data,
+            _ => {
+                let ix =
+                    yellowstone_vixen_core::Parser::parse(&InstructionParser, ix_update).await?;
+
+                #[cfg(not(feature = "shared-data"))]
+                return Ok(RaydiumLaunchpadProgramIxOrEvent::Ix(ix));
+
+                #[cfg(feature = "shared-data")]
+                return Ok(InstructionUpdateOutput {
+                    parsed_ix: RaydiumLaunchpadProgramIxOrEvent::Ix(ix.parsed_ix),
+                    shared_data: ix.shared_data,
+                });
+            },
+        };
+
+        let event = RaydiumLaunchpadProgramEvent::try_unpack_cpi(&ix_update.accounts, event_data);
+
+        #[cfg(feature = "tracing")]
+        if let Err(e) = &event {
+            tracing::debug!(
+                name: "incorrectly_parsed_event",
+                name = "cpi_event",
+                program = ID.to_string(),
+                event = "deserialization_error",
+                discriminator = ?event_data.get(..8),
+                error = ?e
+            );
+        }
+
+        #[cfg(not(feature = "shared-data"))]
+        return Ok(RaydiumLaunchpadProgramIxOrEvent::CpiEvent(event?));
+
+        #[cfg(feature = "shared-data")]
+        Ok(InstructionUpdateOutput {
+            parsed_ix: RaydiumLaunchpadProgramIxOrEvent::CpiEvent(event?),
+            shared_data: Arc::clone(&ix_update.shared),
+        })
+    }
+}
+
+impl yellowstone_vixen_core::ProgramParser for InstructionEventParser {
+    #[inline]
+    fn program_id(&self) -> yellowstone_vixen_core::Pubkey { ID.to_bytes().into() }
+}
+
+/// Protobuf messages for the Raydium Launchpad events, in the
+/// `vixen.parser.raydium_launchpad` package.
+///
+/// Codama only renders messages for the instructions, accounts and types of
+/// the program, so these are derived by hand. They are not part of
+/// [`DESCRIPTOR_SET`](crate::proto_def::DESCRIPTOR_SET).
+// #[cfg(feature = "proto")]
+pub mod proto_def {
+    #![allow(clippy::large_enum_variant)]
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramEvent {
+        #[prost(oneof = "program_event::EventOneof", tags = "1, 2, 3, 4")]
+        pub event_oneof: ::core::option::Option<program_event::EventOneof>,
+    }
+
+    /// Nested message and enum types in `ProgramEvent`.
+    pub mod program_event {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum EventOneof {
+            #[prost(message, tag = "1")]
+            ClaimVested(crate::proto_def::ClaimVestedEvent),
+            #[prost(message, tag = "2")]
+            CreateVesting(crate::proto_def::CreateVestingEvent),
+            #[prost(message, tag = "3")]
+            PoolCreate(crate::proto_def::PoolCreateEvent),
+            #[prost(message, tag = "4")]
+            Trade(crate::proto_def::TradeEvent),
+        }
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramEvents {
+        #[prost(message, repeated, tag = "1")]
+        pub events: ::prost::alloc::vec::Vec<ProgramEvent>,
+    }
+
+    #[derive(Clone, PartialEq, ::prost::Message)]
+    pub struct ProgramIxOrEvent {
+        #[prost(oneof = "program_ix_or_event::IxOrEventOneof", tags = "1, 2")]
+        pub ix_or_event_oneof: ::core::option::Option<program_ix_or_event::IxOrEventOneof>,
+    }
+
+    /// Nested message and enum types in `ProgramIxOrEvent`.
+    pub mod program_ix_or_event {
+        #[derive(Clone, PartialEq, ::prost::Oneof)]
+        pub enum IxOrEventOneof {
+            #[prost(message, tag = "1")]
+            Ix(crate::proto_def::ProgramIxs),
+            #[prost(message, tag = "2")]
+            CpiEvent(super::ProgramEvent),
+        }
+    }
+
+    macro_rules! impl_name {
+        ($($msg:ident),*) => {
+            $(impl ::prost::Name for $msg {
+                const NAME: &'static str = stringify!($msg);
+                const PACKAGE: &'static str = "vixen.parser.raydium_launchpad";
+            })*
+        };
+    }
+
+    impl_name!(ProgramEvent, ProgramEvents, ProgramIxOrEvent);
+}
+
+// #[cfg(feature = "proto")]
+mod proto_parser {
+    use yellowstone_vixen_core::proto::ParseProto;
+
+    use super::{
+        proto_def, InstructionEventParser, LogEventParser, RaydiumLaunchpadProgramEvent,
+        RaydiumLaunchpadProgramIxOrEvent,
+    };
+    use crate::proto_helpers::proto_types_parsers::IntoProto;
+
+    impl IntoProto<proto_def::ProgramEvent> for RaydiumLaunchpadProgramEvent {
+        fn into_proto(self) -> proto_def::ProgramEvent {
+            match self {
+                RaydiumLaunchpadProgramEvent::ClaimVested(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::ClaimVested(
+                        event.into_proto(),
+                    )),
+                },
+                RaydiumLaunchpadProgramEvent::CreateVesting(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::CreateVesting(
+                        event.into_proto(),
+                    )),
+                },
+                RaydiumLaunchpadProgramEvent::PoolCreate(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::PoolCreate(
+                        event.into_proto(),
+                    )),
+                },
+                RaydiumLaunchpadProgramEvent::Trade(event) => proto_def::ProgramEvent {
+                    event_oneof: Some(proto_def::program_event::EventOneof::Trade(
+                        event.into_proto(),
+                    )),
+                },
+            }
+        }
+    }
+
+    impl ParseProto for LogEventParser {
+        type Message = proto_def::ProgramEvents;
+
+        fn output_into_message(value: Self::Output) -> Self::Message {
+            #[cfg(not(feature = "shared-data"))]
+            let events = value;
+
+            #[cfg(feature = "shared-data")]
+            let events = value.parsed_ix;
+
+            proto_def::ProgramEvents {
+                events: events.into_iter().map(IntoProto::into_proto).collect(),
+            }
+        }
+    }
+
+    impl IntoProto<proto_def::ProgramIxOrEvent> for RaydiumLaunchpadProgramIxOrEvent {
+        fn into_proto(self) -> proto_def::ProgramIxOrEvent {
+            match self {
+                RaydiumLaunchpadProgramIxOrEvent::Ix(ix) => proto_def::ProgramIxOrEvent {
+                    ix_or_event_oneof: Some(proto_def::program_ix_or_event::IxOrEventOneof::Ix(
+                        ix.into_proto(),
+                    )),
+                },
+                RaydiumLaunchpadProgramIxOrEvent::CpiEvent(event) => proto_def::ProgramIxOrEvent {
+                    ix_or_event_oneof: Some(
+                        proto_def::program_ix_or_event::IxOrEventOneof::CpiEvent(
+                            event.into_proto(),
+                        ),
+                    ),
+                },
+            }
+        }
+    }
+
+    impl ParseProto for InstructionEventParser {
+        type Message = proto_def::ProgramIxOrEvent;
+
+        fn output_into_message(value: Self::Output) -> Self::Message {
+            #[cfg(not(feature = "shared-data"))]
+            return value.into_proto();
+
+            #[cfg(feature = "shared-data")]
+            value.parsed_ix.into_proto()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use yellowstone_vixen_core::anchor::EVENT_IX_TAG;
+
+    use super::*;
+
+    // The base58 instruction data of an `emit_cpi!` self-invocation
+    const CPI_EVENT: &str = "EwDfpErTWwQhCAycT1hw3m4zs4faQA3fPtsWvQ8AGdKYTuETSH4TxH6nUBKJAjZjL56kpBzKw19y\
+         n6cLF6o9FxWWkAF33Pyfm9os3hEKtD9zxcGtzYYte9dpRRDHcshNoMgcFCAevpG8A1uJsjxx6sYS\
+         cNJrsdiuCEfF124iLDHbE6uMTVJVF3hupsjQHjCrWM6pDzbHNx8ATxsZpUg8";
+
+    #[test]
+    fn test_unpack_cpi_event() {
+        let data = bs58::decode(CPI_EVENT).into_vec().unwrap();
+        assert_eq!(data[..8], EVENT_IX_TAG);
+
+        let event = RaydiumLaunchpadProgramEvent::try_unpack_cpi(
+            &[EVENT_AUTHORITY.to_bytes().into()],
+            &data[8..],
+        );
+        let Ok(RaydiumLaunchpadProgramEvent::Trade(event)) = event else {
+            panic!("Invalid event");
+        };
+
+        assert_eq!(
+            event.pool_state.to_string(),
+            "Hsi9pw1nfbESwED5yhzbQzdEM33HR8KdPCx9duEaRipN"
+        );
+        assert_eq!(event.amount_in, 150_000_000);
+        assert_eq!(event.amount_out, 3_910_694_881_588);
+        assert_eq!(event.real_quote_after, 4_647_487_500);
+        assert_eq!(event.trade_direction, crate::types::TradeDirection::Buy);
+        assert_eq!(event.pool_status, crate::types::PoolStatus::Fund);
+        assert!(event.exact_in);
+
+        assert!(
+            RaydiumLaunchpadProgramEvent::try_unpack_cpi(&[ID.to_bytes().into()], &data[8..])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_events_from_logs() {
+        // The base64 `Program data:` record of an `emit!` event
+        const LOG_EVENT: &str = "vdt/007mYe76uI/r38FoiN0FSw4bBwqAOvHBnhoNLigytx26EnVabwB4xftR0QIA3nQOPunPAwDX\
+         rzD8BgAAAACAX60jbQAAAI04DAEAAADMNssllWkAAAwIAxUBAAAAgNHwCAAAAAA0SZSHjgMAANi4\
+         BQAAAAAAYOMWAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAB";
+        // A record with an unknown discriminator
+        const UNKNOWN_EVENT: &str =
+            "AQIDBAUGBwgAAQIDBAUGBwgJCgsMDQ4PEBESExQVFhcYGRobHB0eHyAhIiMkJSYn";
+
+        let logs = [
+            format!("Program {ID} invoke [1]"),
+            format!("Program data: {LOG_EVENT}"),
+            format!("Program data: {UNKNOWN_EVENT}"),
+            format!("Program {ID} success"),
+        ];
+
+        let events = RaydiumLaunchpadProgramEvent::from_logs(&logs);
+        let [RaydiumLaunchpadProgramEvent::Trade(event)] = events.as_slice() else {
+            panic!("Invalid events: {events:?}");
+        };
+
+        assert_eq!(
+            event.pool_state.to_string(),
+            "Hsi9pw1nfbESwED5yhzbQzdEM33HR8KdPCx9duEaRipN"
+        );
+        assert_eq!(event.amount_in, 150_000_000);
+        assert_eq!(event.amount_out, 3_910_694_881_588);
+        assert_eq!(event.real_quote_after, 4_647_487_500);
+        assert!(event.exact_in);
+    }
+}
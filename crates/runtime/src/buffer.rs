@@ -1,19 +1,38 @@
-use std::sync::Arc;
+use std::{
+    hash::{BuildHasher, RandomState},
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
-use tokio::sync::mpsc::Receiver;
+use futures_util::future::OptionFuture;
+use tokio::{
+    sync::{
+        mpsc::{self, Receiver},
+        Notify,
+    },
+    time::Instant,
+};
 use topograph::{
     executor::{self, Executor, Nonblock, Tokio},
     prelude::*,
 };
-use tracing::warn;
+use vixen_core::{AccountUpdate, DataSlice, Pubkey, TransactionUpdate};
 use yellowstone_grpc_proto::{
     geyser::{subscribe_update::UpdateOneof, SubscribeUpdate, SubscribeUpdatePing},
     tonic::Status,
 };
 
 use crate::{
-    config::BufferConfig,
-    handler::PipelineSets,
+    checkpoint::{InFlight, SlotProgress},
+    commitment::{Ready, Rollback, Tracker},
+    config::{BufferConfig, OrderBy},
+    dead_letter::{self, Failure},
+    handler::{LivePipelines, PipelineSets},
+    instruction::TransactionInstructions,
     metrics::{Counters, Instrumenter, UpdateType},
     stop::{self, StopCode, StopRx, StopTx},
 };
@@ -44,125 +63,714 @@ impl Buffer {
     }
 }
 
-struct Job(tracing::Span, SubscribeUpdate);
+#[allow(clippy::large_enum_variant)]
+enum Job {
+    Update {
+        span: tracing::Span,
+        update: SubscribeUpdate,
+        /// Set if the update was prepared when it was dispatched
+        prepared: Option<Prepared>,
+        /// Marks the update as processed for checkpointing once finished
+        in_flight: Option<InFlight>,
+    },
+    Rollback(Rollback),
+}
 
-struct Handler<M: Instrumenter> {
+/// An update made ready to be passed to its pipelines.
+pub(crate) struct Prepared {
     pipelines: Arc<PipelineSets>,
-    counters: Arc<Counters<M>>,
+    /// The account of an account update, taken out of the update along with
+    /// the data slices it was requested with
+    account: Option<AccountUpdate>,
+    /// The transaction of a transaction update, taken out of the update to
+    /// be shared with its decomposed instructions
+    transaction: Option<Arc<TransactionUpdate>>,
+    /// The decomposed instructions of a transaction update, if any
+    /// instruction pipeline will receive it
+    ixs: Option<TransactionInstructions>,
 }
-impl<M: Instrumenter> Clone for Handler<M> {
-    fn clone(&self) -> Self {
-        let Self {
-            pipelines,
-            counters,
-        } = self;
+
+impl Prepared {
+    pub fn new(pipelines: Arc<PipelineSets>, update: &mut SubscribeUpdate) -> Self {
+        let SubscribeUpdate {
+            filters,
+            update_oneof,
+            created_at: _,
+        } = update;
+        // Map fanned-out account filters back to the parser they were
+        // generated for.  An update can match several of the filters fanned
+        // out from a single prefilter, but should only be handled once
+        if !pipelines.parser_ids.is_empty() {
+            for f in filters.iter_mut() {
+                if let Some(id) = pipelines.parser_ids.get(f) {
+                    f.clone_from(id);
+                }
+            }
+
+            filters.sort_unstable();
+            filters.dedup();
+        }
+
+        let mut account = None;
+        let mut transaction = None;
+        let ixs = match update_oneof {
+            Some(UpdateOneof::Account(a)) => {
+                account = Some(AccountUpdate {
+                    update: std::mem::take(a),
+                    data_slices: Arc::clone(&pipelines.data_slices),
+                });
+
+                None
+            },
+            // Decompose the transaction once for every instruction
+            // pipeline, and only if any of them will receive it
+            Some(UpdateOneof::Transaction(t)) if pipelines.instruction.matches_any(&*filters) => {
+                let t = transaction.insert(Arc::new(std::mem::take(t)));
+
+                Some(TransactionInstructions::parse(t))
+            },
+            _ => None,
+        };
+
         Self {
-            pipelines: Arc::clone(pipelines),
-            counters: Arc::clone(counters),
+            pipelines,
+            account,
+            transaction,
+            ixs,
         }
     }
-}
-impl<M: Instrumenter, H: Send> topograph::AsyncHandler<Job, H> for Handler<M> {
-    type Output = ();
 
-    async fn handle(&self, update: Job, _: H) {
+    /// Put the account or transaction taken by [`Prepared::new`] back into
+    /// the update, returning the data slices the account was requested with.
+    pub fn restore(&mut self, update: &mut SubscribeUpdate) -> Arc<[DataSlice]> {
+        // Release the instructions sharing the transaction first
+        self.ixs = None;
+
+        match (
+            self.account.take(),
+            self.transaction.take(),
+            update.update_oneof.as_mut(),
+        ) {
+            (Some(account), _, Some(UpdateOneof::Account(a))) => {
+                *a = account.update;
+                account.data_slices
+            },
+            (_, Some(txn), Some(UpdateOneof::Transaction(t))) => {
+                // A handler may have kept an instruction alive
+                *t = Arc::try_unwrap(txn).unwrap_or_else(|t| (*t).clone());
+                Arc::default()
+            },
+            _ => Arc::default(),
+        }
+    }
+
+    /// Pass the update to the pipelines matching its filters, returning the
+    /// errors they raised.
+    pub async fn run<M: Instrumenter>(
+        &self,
+        span: tracing::Span,
+        update: &SubscribeUpdate,
+        counters: &Counters<M>,
+    ) -> Vec<Failure> {
+        counters
+            .scope(self.run_pipelines(span, update, counters))
+            .await
+    }
+
+    async fn run_pipelines<M: Instrumenter>(
+        &self,
+        span: tracing::Span,
+        update: &SubscribeUpdate,
+        counters: &Counters<M>,
+    ) -> Vec<Failure> {
         let Self {
             pipelines,
-            counters,
+            account,
+            transaction,
+            ixs,
         } = self;
-        let Job(
-            span,
-            SubscribeUpdate {
-                filters,
-                update_oneof,
-                created_at: _,
-            },
-        ) = update;
-        let Some(update) = update_oneof else { return };
+        let SubscribeUpdate {
+            filters,
+            update_oneof,
+            created_at: _,
+        } = update;
+        let Some(update) = update_oneof else {
+            return vec![];
+        };
+        let errors = &pipelines.errors;
 
         match update {
-            UpdateOneof::Account(a) => {
+            UpdateOneof::Account(_) => {
+                let Some(a) = account else {
+                    return vec![];
+                };
+
                 pipelines
                     .account
-                    .get_handlers(&filters)
-                    .run(span, &a, counters)
-                    .await;
+                    .get_handlers(filters)
+                    .run(span, a, counters, errors)
+                    .await
             },
             UpdateOneof::Transaction(t) => {
-                let transaction_fut =
-                    pipelines
-                        .transaction
-                        .get_handlers(&filters)
-                        .run(span.clone(), &t, counters);
+                let t = transaction.as_deref().unwrap_or(t);
+                let transaction_fut = pipelines.transaction.get_handlers(filters).run(
+                    span.clone(),
+                    t,
+                    counters,
+                    errors,
+                );
 
-                let instruction_fut = pipelines
-                    .instruction
-                    .get_handlers(&filters)
-                    .run(span, &t, counters);
+                let instruction_fut: OptionFuture<_> = ixs
+                    .as_ref()
+                    .map(|ixs| {
+                        pipelines
+                            .instruction
+                            .get_handlers(filters)
+                            .run(span, ixs, counters, errors)
+                    })
+                    .into();
 
-                futures_util::future::join_all([transaction_fut, instruction_fut]).await;
+                let (mut failures, ix_failures) =
+                    futures_util::future::join(transaction_fut, instruction_fut).await;
+                failures.extend(ix_failures.into_iter().flatten());
+                failures
             },
             UpdateOneof::BlockMeta(b) => {
                 pipelines
                     .block_meta
-                    .get_handlers(&filters)
-                    .run(span, &b, counters)
-                    .await;
+                    .get_handlers(filters)
+                    .run(span, b, counters, errors)
+                    .await
             },
             UpdateOneof::Slot(s) => {
                 pipelines
                     .slot
-                    .get_handlers(&filters)
-                    .run(span, &s, counters)
-                    .await;
+                    .get_handlers(filters)
+                    .run(span, s, counters, errors)
+                    .await
+            },
+            UpdateOneof::Block(b) => {
+                pipelines
+                    .block
+                    .get_handlers(filters)
+                    .run(span, b, counters, errors)
+                    .await
+            },
+            UpdateOneof::Entry(e) => {
+                pipelines
+                    .entry
+                    .get_handlers(filters)
+                    .run(span, e, counters, errors)
+                    .await
+            },
+            UpdateOneof::TransactionStatus(t) => {
+                pipelines
+                    .transaction_status
+                    .get_handlers(filters)
+                    .run(span, t, counters, errors)
+                    .await
+            },
+            UpdateOneof::Ping(SubscribeUpdatePing {}) | UpdateOneof::Pong(_) => vec![],
+        }
+    }
+
+    /// Get the order key given for the update by the pipelines receiving it.
+    fn parser_key(&self, update: &SubscribeUpdate) -> Option<Pubkey> {
+        let Self {
+            pipelines,
+            account,
+            transaction,
+            ixs,
+        } = self;
+        let filters = &update.filters;
+
+        match update.update_oneof.as_ref()? {
+            UpdateOneof::Account(_) => pipelines.account.order_key(filters, account.as_ref()?),
+            UpdateOneof::Transaction(t) => {
+                let t = transaction.as_deref().unwrap_or(t);
+
+                pipelines.transaction.order_key(filters, t).or_else(|| {
+                    ixs.as_ref()
+                        .and_then(|ixs| pipelines.instruction.order_key(filters, ixs))
+                })
             },
-            UpdateOneof::Ping(SubscribeUpdatePing {}) => (),
-            var => warn!(?var, "Unknown update variant"),
+            UpdateOneof::BlockMeta(b) => pipelines.block_meta.order_key(filters, b),
+            UpdateOneof::Slot(s) => pipelines.slot.order_key(filters, s),
+            UpdateOneof::Block(b) => pipelines.block.order_key(filters, b),
+            UpdateOneof::Entry(e) => pipelines.entry.order_key(filters, e),
+            UpdateOneof::TransactionStatus(t) => pipelines.transaction_status.order_key(filters, t),
+            _ => None,
         }
     }
 }
 
-impl Buffer {
-    fn dispatch<M: Instrumenter, E: ExecutorHandle<Job>>(
-        exec: &E,
-        update: SubscribeUpdate,
-        counters: &Counters<M>,
+/// The time to wait on shutdown for received updates to be processed if
+/// [`BufferConfig::drain_timeout`] is unset.
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::ZERO;
+
+/// Counts the jobs dispatched but not yet processed, so they can be waited
+/// for on shutdown.
+#[derive(Debug, Default)]
+struct Pending {
+    count: AtomicUsize,
+    /// The number of jobs processed since the buffer started
+    finished: AtomicUsize,
+    idle: Notify,
+}
+
+impl Pending {
+    fn add(&self) { self.count.fetch_add(1, Ordering::AcqRel); }
+
+    fn finish(&self) {
+        self.finished.fetch_add(1, Ordering::Relaxed);
+
+        if self.count.fetch_sub(1, Ordering::AcqRel) == 1 {
+            self.idle.notify_waiters();
+        }
+    }
+
+    #[inline]
+    fn count(&self) -> usize { self.count.load(Ordering::Acquire) }
+
+    #[inline]
+    fn finished(&self) -> usize { self.finished.load(Ordering::Relaxed) }
+
+    /// Wait until every dispatched job has been processed.
+    async fn idle(&self) {
+        loop {
+            let notified = self.idle.notified();
+            let mut notified = std::pin::pin!(notified);
+            notified.as_mut().enable();
+
+            if self.count() == 0 {
+                return;
+            }
+
+            notified.await;
+        }
+    }
+}
+
+struct Handler<M: Instrumenter> {
+    pipelines: Arc<LivePipelines>,
+    counters: Arc<Counters<M>>,
+    pending: Arc<Pending>,
+}
+impl<M: Instrumenter> Clone for Handler<M> {
+    fn clone(&self) -> Self {
+        let Self {
+            pipelines,
+            counters,
+            pending,
+        } = self;
+        Self {
+            pipelines: Arc::clone(pipelines),
+            counters: Arc::clone(counters),
+            pending: Arc::clone(pending),
+        }
+    }
+}
+impl<M: Instrumenter, H: Send> topograph::AsyncHandler<Job, H> for Handler<M> {
+    type Output = ();
+
+    async fn handle(&self, job: Job, _: H) { self.process(job).await }
+}
+
+impl<M: Instrumenter> Handler<M> {
+    async fn process(&self, job: Job) {
+        match job {
+            Job::Update {
+                span,
+                update,
+                prepared,
+                in_flight,
+            } => {
+                self.process_update(span, update, prepared).await;
+
+                if let Some(f) = in_flight {
+                    f.finish();
+                }
+            },
+            Job::Rollback(r) => self.pipelines.load().rollback.run(r).await,
+        }
+
+        self.pending.finish();
+    }
+
+    async fn process_update(
+        &self,
+        span: tracing::Span,
+        mut update: SubscribeUpdate,
+        prepared: Option<Prepared>,
+    ) {
+        let mut prepared =
+            prepared.unwrap_or_else(|| Prepared::new(self.pipelines.load(), &mut update));
+        let failures = prepared.run(span, &update, &self.counters).await;
+        let data_slices = prepared.restore(&mut update);
+
+        let Some(sink) = prepared.pipelines.errors.dead_letters() else {
+            return;
+        };
+
+        for letter in dead_letter::letters(failures, update, &data_slices) {
+            if let Err(e) = sink.store(&letter).await {
+                tracing::error!(
+                    err = %crate::Chain(&*e),
+                    parser = letter.parser_id,
+                    "Failed to store dead letter",
+                );
+            }
+        }
+    }
+}
+
+/// A set of workers each processing its queue of updates sequentially.
+/// Updates are assigned to a worker by the hash of their order key, so
+/// updates sharing a key are processed in the order they were dispatched.
+///
+/// Each queue holds at most `capacity` jobs, after which dispatching waits
+/// for the worker to catch up.
+struct Shards<M: Instrumenter> {
+    order_by: OrderBy,
+    handler: Handler<M>,
+    hasher: RandomState,
+    /// Round-robin counter for assigning updates with no key
+    next: AtomicUsize,
+    workers: Box<[mpsc::Sender<Job>]>,
+}
+
+impl<M: Instrumenter> Shards<M> {
+    fn new(order_by: OrderBy, handler: Handler<M>, jobs: Option<usize>, capacity: usize) -> Self {
+        let jobs = jobs
+            .or_else(|| {
+                std::thread::available_parallelism()
+                    .ok()
+                    .map(NonZeroUsize::get)
+            })
+            .unwrap_or(1)
+            .max(1);
+
+        let workers = (0..jobs)
+            .map(|_| {
+                let (tx, mut rx) = mpsc::channel(capacity.max(1));
+                let handler = handler.clone();
+
+                tokio::task::spawn(async move {
+                    while let Some(job) = rx.recv().await {
+                        handler.process(job).await;
+                    }
+                });
+
+                tx
+            })
+            .collect();
+
+        Self {
+            order_by,
+            handler,
+            hasher: RandomState::new(),
+            next: AtomicUsize::new(0),
+            workers,
+        }
+    }
+
+    fn key(&self, update: &SubscribeUpdate, prepared: Option<&Prepared>) -> Option<u64> {
+        let update_oneof = update.update_oneof.as_ref()?;
+
+        match self.order_by {
+            OrderBy::Account => match update_oneof {
+                UpdateOneof::Account(a) => {
+                    a.account.as_ref().map(|a| self.hasher.hash_one(&*a.pubkey))
+                },
+                _ => None,
+            },
+            OrderBy::Parser => prepared
+                .and_then(|p| p.parser_key(update))
+                .map(|k| self.hasher.hash_one(k)),
+            OrderBy::Slot => {
+                let slot = match update_oneof {
+                    UpdateOneof::Account(a) => a.slot,
+                    UpdateOneof::Transaction(t) => t.slot,
+                    UpdateOneof::BlockMeta(b) => b.slot,
+                    UpdateOneof::Slot(s) => s.slot,
+                    UpdateOneof::Block(b) => b.slot,
+                    UpdateOneof::Entry(e) => e.slot,
+                    UpdateOneof::TransactionStatus(t) => t.slot,
+                    _ => return None,
+                };
+
+                Some(self.hasher.hash_one(slot))
+            },
+        }
+    }
+
+    async fn send(&self, key: Option<u64>, job: Job) {
+        let idx = match key {
+            #[allow(clippy::cast_possible_truncation)]
+            Some(k) => k as usize % self.workers.len(),
+            None => self.next.fetch_add(1, Ordering::Relaxed) % self.workers.len(),
+        };
+
+        // Workers only stop once every sender is dropped
+        self.workers[idx]
+            .send(job)
+            .await
+            .unwrap_or_else(|_| unreachable!());
+    }
+
+    async fn push(
+        &self,
+        span: tracing::Span,
+        mut update: SubscribeUpdate,
+        in_flight: Option<InFlight>,
     ) {
+        // Parser keys are given for the update as its pipelines will receive
+        // it, so prepare it here rather than on the worker
+        let prepared = (self.order_by == OrderBy::Parser)
+            .then(|| Prepared::new(self.handler.pipelines.load(), &mut update));
+
+        let key = self.key(&update, prepared.as_ref());
+        self.send(key, Job::Update {
+            span,
+            update,
+            prepared,
+            in_flight,
+        })
+        .await;
+    }
+
+    async fn rollback(&self, rollback: Rollback) {
+        // Keyed like the updates of the slot, to be processed after them
+        let key = (self.order_by == OrderBy::Slot).then(|| self.hasher.hash_one(rollback.slot));
+        self.send(key, Job::Rollback(rollback)).await;
+    }
+}
+
+/// Dispatches updates to be processed, either in any order or ordered by a
+/// key.
+enum Dispatcher<M: Instrumenter> {
+    Unordered(Executor<Job, Nonblock<Tokio>>, Arc<Pending>),
+    Ordered(Shards<M>),
+}
+
+impl<M: Instrumenter> Dispatcher<M> {
+    fn pending(&self) -> &Arc<Pending> {
+        match self {
+            Self::Unordered(_, pending) => pending,
+            Self::Ordered(shards) => &shards.handler.pending,
+        }
+    }
+
+    async fn push(&self, update: SubscribeUpdate, in_flight: Option<InFlight>) {
         let span = tracing::trace_span!("process_update", ?update).entered();
-        if let Some(ty) = UpdateType::get(update.update_oneof.as_ref()) {
-            counters.inc_received(ty);
+        let span = span.exit();
+        self.pending().add();
+
+        match self {
+            Self::Unordered(exec, _) => exec.push(Job::Update {
+                span,
+                update,
+                prepared: None,
+                in_flight,
+            }),
+            Self::Ordered(shards) => shards.push(span, update, in_flight).await,
+        }
+    }
+
+    async fn rollback(&self, rollback: Rollback) {
+        self.pending().add();
+
+        match self {
+            Self::Unordered(exec, _) => exec.push(Job::Rollback(rollback)),
+            Self::Ordered(shards) => shards.rollback(rollback).await,
         }
-        exec.push(Job(span.exit(), update));
     }
+}
 
+/// Receives updates from the source and dispatches them to be processed.
+struct Intake<M: Instrumenter> {
+    dispatcher: Dispatcher<M>,
+    tracker: Option<Tracker>,
+    progress: Option<Arc<SlotProgress>>,
+    counters: Arc<Counters<M>>,
+    drain_timeout: Duration,
+}
+
+impl<M: Instrumenter> Intake<M> {
+    async fn dispatch(&mut self, mut update: SubscribeUpdate) {
+        let Self {
+            dispatcher,
+            tracker,
+            progress,
+            counters,
+            drain_timeout: _,
+        } = self;
+        let count = |update: &SubscribeUpdate| {
+            if let Some(ty) = UpdateType::get(update.update_oneof.as_ref()) {
+                counters.inc_received(ty);
+            }
+        };
+        let in_flight = progress.as_ref().and_then(|p| p.start(&update));
+
+        let Some(tracker) = tracker else {
+            count(&update);
+            dispatcher.push(update, in_flight).await;
+            return;
+        };
+
+        // Slot statuses requested only by the tracker are not dispatched
+        if !tracker.observe(&mut update) {
+            count(&update);
+            tracker.push(update, in_flight);
+        }
+
+        while let Some(ready) = tracker.next_ready() {
+            match ready {
+                Ready::Update(u, f) => dispatcher.push(u, f).await,
+                Ready::Rollback(r) => dispatcher.rollback(r).await,
+            }
+        }
+    }
+
+    /// Stop receiving new updates, then wait up to the drain timeout for the
+    /// updates already sent by the source to be processed, logging how many
+    /// jobs were completed and how many updates were abandoned.
+    ///
+    /// Returns the number of abandoned updates.
+    async fn drain(mut self, stream: &mut Receiver<Result<SubscribeUpdate, Status>>) -> usize {
+        let deadline = Instant::now() + self.drain_timeout;
+        let pending = Arc::clone(self.dispatcher.pending());
+        let finished = pending.finished();
+        let running = pending.count();
+        let mut flushed = 0_usize;
+        let mut abandoned = 0_usize;
+
+        stream.close();
+
+        // With no time to wait, updates not yet dispatched are not worth
+        // dispatching
+        if self.drain_timeout.is_zero() {
+            abandoned += std::iter::from_fn(|| stream.try_recv().ok()).count();
+        }
+
+        loop {
+            let update = match tokio::time::timeout_at(deadline, stream.recv()).await {
+                Ok(Some(Ok(update))) => update,
+                Ok(Some(Err(e))) => {
+                    tracing::warn!(
+                        "Yellowstone grpc stream error while draining: {:?}",
+                        e.code()
+                    );
+                    continue;
+                },
+                Ok(None) => break,
+                Err(_) => {
+                    abandoned += std::iter::from_fn(|| stream.try_recv().ok()).count();
+                    break;
+                },
+            };
+
+            // Dispatching waits for room in the worker queues.  An update
+            // whose dispatch is cut short is still counted as pending
+            if tokio::time::timeout_at(deadline, self.dispatch(update))
+                .await
+                .is_err()
+            {
+                abandoned += std::iter::from_fn(|| stream.try_recv().ok()).count();
+                break;
+            }
+
+            flushed += 1;
+        }
+
+        let Self {
+            dispatcher,
+            tracker,
+            ..
+        } = self;
+
+        // Updates still held for their commitment level are never released
+        abandoned += tracker.as_ref().map_or(0, Tracker::held);
+        drop((dispatcher, tracker));
+
+        let _ = tokio::time::timeout_at(deadline, pending.idle()).await;
+        abandoned += pending.count();
+        let completed = pending.finished() - finished;
+
+        if abandoned == 0 {
+            tracing::info!(running, flushed, completed, "Buffer drained");
+        } else {
+            tracing::warn!(
+                running,
+                flushed,
+                completed,
+                abandoned,
+                "Buffer drain timed out, abandoning remaining updates",
+            );
+        }
+
+        abandoned
+    }
+}
+
+impl Buffer {
     fn run_impl<
         M: Instrumenter,
         B: FnOnce(executor::Builder<Job, Nonblock<Tokio>>) -> executor::Builder<Job, Nonblock<Tokio>>,
-        S: FnOnce(Executor<Job, Nonblock<Tokio>>, StopRx, Arc<Counters<M>>) -> TaskHandle,
+        S: FnOnce(Intake<M>, StopRx) -> TaskHandle,
     >(
         config: BufferConfig,
-        pipelines: PipelineSets,
+        pipelines: Arc<LivePipelines>,
+        progress: Option<Arc<SlotProgress>>,
         counters: Counters<M>,
         build: B,
         spawn: S,
     ) -> Self {
         let BufferConfig {
             jobs,
-            sources_channel_size: _,
+            sources_channel_size,
+            order_by,
+            hold_until,
+            drain_timeout,
         } = config;
 
-        let pipelines = Arc::new(pipelines);
+        let tracker = {
+            let pipelines = pipelines.load();
+            pipelines
+                .track_slots
+                .then(|| Tracker::new(hold_until, !pipelines.rollback.is_empty()))
+        };
+
         let counters = Arc::new(counters);
-        let exec = build(Executor::builder(Nonblock(Tokio)).max_concurrency(jobs))
-            .build_async(Handler {
-                pipelines,
-                counters: Arc::clone(&counters),
-            })
-            .unwrap_or_else(|i| match i {});
+        let pending = Arc::new(Pending::default());
+        let handler = Handler {
+            pipelines,
+            counters: Arc::clone(&counters),
+            pending: Arc::clone(&pending),
+        };
+        let dispatcher = if let Some(order_by) = order_by {
+            Dispatcher::Ordered(Shards::new(order_by, handler, jobs, sources_channel_size))
+        } else {
+            Dispatcher::Unordered(
+                build(Executor::builder(Nonblock(Tokio)).max_concurrency(jobs))
+                    .build_async(handler)
+                    .unwrap_or_else(|i| match i {}),
+                pending,
+            )
+        };
 
         let (stop_tx, rx) = stop::channel();
 
-        let task = spawn(exec, rx, counters);
+        let task = spawn(
+            Intake {
+                dispatcher,
+                tracker,
+                progress,
+                counters,
+                drain_timeout: drain_timeout.map_or(DEFAULT_DRAIN_TIMEOUT, Duration::from_secs),
+            },
+            rx,
+        );
         Self(task, stop_tx)
     }
 
@@ -170,22 +778,24 @@ impl Buffer {
     pub fn run_yellowstone<M: Instrumenter>(
         config: BufferConfig,
         mut stream: Receiver<Result<SubscribeUpdate, Status>>,
-        pipelines: PipelineSets,
+        pipelines: Arc<LivePipelines>,
+        progress: Option<Arc<SlotProgress>>,
         counters: Counters<M>,
     ) -> Self {
         Self::run_impl(
             config,
             pipelines,
+            progress,
             counters,
             std::convert::identity,
-            |exec, mut stop_rx, counters| {
+            |mut intake, mut stop_rx| {
                 let handle = tokio::task::spawn(async move {
                     enum Event {
                         Update(Option<Result<SubscribeUpdate, Status>>),
                         Stop(StopCode),
                     }
 
-                    loop {
+                    let result = loop {
                         let event = tokio::select! {
                             u = stream.recv() => Event::Update(u),
                             c = &mut stop_rx => Event::Stop(c),
@@ -199,7 +809,7 @@ impl Buffer {
                                         "Yellowstone grpc stream error: {:?}",
                                         e.code()
                                     );
-                                    return Err(crate::Error::YellowstoneStatus(e));
+                                    break Err(crate::Error::YellowstoneStatus(e));
                                 },
                             },
                             Event::Update(None) => {
@@ -209,8 +819,12 @@ impl Buffer {
                             Event::Stop(c) => break Ok(c),
                         };
 
-                        Self::dispatch(&exec, update, &counters);
-                    }
+                        intake.dispatch(update).await;
+                    };
+
+                    intake.drain(&mut stream).await;
+
+                    result
                 });
 
                 handle
@@ -218,3 +832,235 @@ impl Buffer {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use tokio::sync::Semaphore;
+
+    use super::*;
+    use crate::{
+        commitment::RollbackHandlers,
+        metrics::NullMetrics,
+        test_util::{self, account_update, Recorder},
+    };
+
+    fn handler(pipelines: PipelineSets) -> Handler<NullMetrics> {
+        Handler {
+            pipelines: Arc::new(LivePipelines::new(pipelines)),
+            counters: Arc::new(Counters::new(&NullMetrics)),
+            pending: Arc::default(),
+        }
+    }
+
+    fn intake(dispatcher: Dispatcher<NullMetrics>, drain_timeout: Duration) -> Intake<NullMetrics> {
+        Intake {
+            dispatcher,
+            tracker: None,
+            progress: Some(Arc::default()),
+            counters: Arc::new(Counters::new(&NullMetrics)),
+            drain_timeout,
+        }
+    }
+
+    async fn assert_key_order(order_by: OrderBy) {
+        let recorder = Recorder::default();
+        let pipelines = test_util::pipelines([recorder.pipeline("accounts")]);
+        let dispatcher = Dispatcher::Ordered(Shards::new(order_by, handler(pipelines), Some(4), 2));
+
+        for slot in 0..32 {
+            for key in 0..8 {
+                dispatcher
+                    .push(account_update(key, slot, &["accounts"]), None)
+                    .await;
+            }
+        }
+
+        dispatcher.pending().idle().await;
+        let seen = recorder.seen();
+        assert_eq!(seen.len(), 32 * 8);
+
+        for key in 0..8 {
+            let slots: Vec<_> = seen
+                .iter()
+                .filter(|s| s.pubkey == Pubkey::from([key; 32]))
+                .map(|s| s.slot)
+                .collect();
+            assert_eq!(slots, (0..32).collect::<Vec<_>>(), "key {key}");
+        }
+    }
+
+    #[tokio::test]
+    async fn ordered_updates_keep_key_order() { assert_key_order(OrderBy::Account).await; }
+
+    #[tokio::test]
+    async fn parser_ordered_updates_keep_key_order() { assert_key_order(OrderBy::Parser).await; }
+
+    #[tokio::test]
+    async fn slot_ordered_rollbacks_follow_their_slot() {
+        let recorder = Recorder::default();
+        let mut rollback = RollbackHandlers::default();
+        rollback.push(recorder.clone());
+        let pipelines = PipelineSets {
+            rollback: Arc::new(rollback),
+            ..test_util::pipelines([recorder.pipeline("accounts")])
+        };
+        let dispatcher =
+            Dispatcher::Ordered(Shards::new(OrderBy::Slot, handler(pipelines), Some(4), 2));
+
+        for slot in 0..16 {
+            for key in 1..=8 {
+                dispatcher
+                    .push(account_update(key, slot, &["accounts"]), None)
+                    .await;
+            }
+
+            dispatcher.rollback(Rollback { slot }).await;
+        }
+
+        dispatcher.pending().idle().await;
+        let seen = recorder.seen();
+        assert_eq!(seen.len(), 16 * 9);
+
+        // Rollbacks are recorded with the default address
+        for slot in 0..16 {
+            let keys: Vec<_> = seen
+                .iter()
+                .filter(|s| s.slot == slot)
+                .map(|s| s.pubkey)
+                .collect();
+            let expected: Vec<_> = (1..=8)
+                .map(|k| Pubkey::from([k; 32]))
+                .chain([Pubkey::default()])
+                .collect();
+            assert_eq!(keys, expected, "slot {slot}");
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn full_worker_queue_holds_dispatch() {
+        let gate = Arc::new(Semaphore::new(0));
+        let recorder = Recorder {
+            gate: Some(Arc::clone(&gate)),
+            ..Recorder::default()
+        };
+        let pipelines = test_util::pipelines([recorder.pipeline("accounts")]);
+        let dispatcher = Dispatcher::Ordered(Shards::new(
+            OrderBy::Account,
+            handler(pipelines),
+            Some(1),
+            1,
+        ));
+
+        // The worker takes the first update and blocks on the gate, leaving
+        // the second in its queue
+        for slot in 0..2 {
+            dispatcher
+                .push(account_update(1, slot, &["accounts"]), None)
+                .await;
+        }
+
+        let mut third = std::pin::pin!(dispatcher.push(account_update(1, 2, &["accounts"]), None));
+        assert!(tokio::time::timeout(Duration::from_secs(1), &mut third)
+            .await
+            .is_err());
+
+        gate.add_permits(3);
+        third.await;
+        dispatcher.pending().idle().await;
+        assert_eq!(recorder.seen().len(), 3);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drain_counts_abandoned_updates() {
+        let gate = Arc::new(Semaphore::new(0));
+        let recorder = Recorder {
+            gate: Some(Arc::clone(&gate)),
+            ..Recorder::default()
+        };
+        let pipelines = test_util::pipelines([recorder.pipeline("accounts")]);
+        let dispatcher = Dispatcher::Ordered(Shards::new(
+            OrderBy::Account,
+            handler(pipelines),
+            Some(1),
+            1,
+        ));
+        let mut intake = intake(dispatcher, Duration::from_secs(1));
+        let (tx, mut rx) = mpsc::channel(8);
+
+        // The worker blocks on the first update, so the second fills its
+        // queue and dispatching the third waits until the deadline
+        intake.dispatch(account_update(1, 1, &["accounts"])).await;
+
+        for slot in 2..=5 {
+            tx.send(Ok(account_update(1, slot, &["accounts"])))
+                .await
+                .unwrap();
+        }
+
+        let progress = intake.progress.clone().unwrap();
+        assert_eq!(intake.drain(&mut rx).await, 5);
+        assert_eq!(progress.watermark(), Some(0));
+
+        // The dispatched updates still finish, but the checkpoint is held
+        // below the update abandoned while being dispatched
+        gate.add_permits(5);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        assert_eq!(recorder.seen().len(), 2);
+        assert_eq!(progress.watermark(), Some(2));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drain_flushes_received_updates() {
+        let recorder = Recorder::default();
+        let pipelines = test_util::pipelines([recorder.pipeline("accounts")]);
+        let dispatcher = Dispatcher::Ordered(Shards::new(
+            OrderBy::Account,
+            handler(pipelines),
+            Some(2),
+            1,
+        ));
+        let (tx, mut rx) = mpsc::channel(8);
+
+        for slot in 1..=5 {
+            tx.send(Ok(account_update(1, slot, &["accounts"])))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            intake(dispatcher, Duration::from_secs(1))
+                .drain(&mut rx)
+                .await,
+            0
+        );
+        assert_eq!(recorder.seen().len(), 5);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn drain_abandons_immediately_by_default() {
+        let recorder = Recorder::default();
+        let pipelines = test_util::pipelines([recorder.pipeline("accounts")]);
+        let dispatcher = Dispatcher::Ordered(Shards::new(
+            OrderBy::Account,
+            handler(pipelines),
+            Some(2),
+            1,
+        ));
+        let (tx, mut rx) = mpsc::channel(8);
+
+        for slot in 1..=5 {
+            tx.send(Ok(account_update(1, slot, &["accounts"])))
+                .await
+                .unwrap();
+        }
+
+        assert_eq!(
+            intake(dispatcher, DEFAULT_DRAIN_TIMEOUT)
+                .drain(&mut rx)
+                .await,
+            5
+        );
+        tokio::task::yield_now().await;
+        assert!(recorder.seen().is_empty());
+    }
+}
@@ -1,15 +1,23 @@
 //! Builder types for the Vixen runtime and stream server.
+use std::{collections::HashMap, sync::Arc};
+
 use vixen_core::{
-    instruction::InstructionUpdate, AccountUpdate, BlockMetaUpdate, SlotUpdate, TransactionUpdate,
+    instruction::InstructionUpdate, AccountUpdate, BlockMetaUpdate, BlockUpdate, EntryUpdate,
+    SlotUpdate, TransactionStatusUpdate, TransactionUpdate,
 };
 
 use crate::{
+    checkpoint::CheckpointStore,
+    commitment::{Rollback, RollbackHandlers},
     config::{MaybeDefault, VixenConfig},
+    control::{Control, WrapInstructions},
+    dead_letter::DeadLetterSink,
+    error_context::{ErrorPayload, ErrorReporter, ErrorSink},
     handler::{BoxPipeline, DynPipeline, PipelineSet, PipelineSets},
-    instruction::SingleInstructionPipeline,
-    metrics::{Counters, Metrics, MetricsFactory, NullMetrics},
+    instruction::{SingleInstructionPipeline, TransactionInstructions},
+    metrics::{Counters, InstructionCounters, Metrics, MetricsFactory, NullMetrics},
     sources::SourceTrait,
-    util, Runtime,
+    util, Handler, Runtime,
 };
 
 /// Helper trait for defining the intended use for a builder.
@@ -33,6 +41,16 @@ pub enum BuilderError {
     /// Two block meta pipelines were registered with the same parser ID.
     #[error("ID collision detected among block meta pipelines")]
     BlockMetaCollision,
+    /// Two block pipelines were registered with the same parser ID.
+    #[error("ID collision detected among block pipelines")]
+    BlockCollision,
+    /// Two entry pipelines were registered with the same parser ID.
+    #[error("ID collision detected among entry pipelines")]
+    EntryCollision,
+    /// Two transaction status pipelines were registered with the same parser
+    /// ID.
+    #[error("ID collision detected among transaction status pipelines")]
+    TransactionStatusCollision,
     /// A required field was missing from the builder.
     #[error("Missing field {0:?}")]
     MissingField(&'static str),
@@ -61,6 +79,18 @@ pub struct Builder<K: BuilderKind, M, S: SourceTrait> {
     pub block_meta: Vec<BoxPipeline<'static, BlockMetaUpdate>>,
     /// The slot pipelines.
     pub slot: Vec<BoxPipeline<'static, SlotUpdate>>,
+    /// The block pipelines.
+    pub block: Vec<BoxPipeline<'static, BlockUpdate>>,
+    /// The entry pipelines.
+    pub entry: Vec<BoxPipeline<'static, EntryUpdate>>,
+    /// The transaction status pipelines.
+    pub transaction_status: Vec<BoxPipeline<'static, TransactionStatusUpdate>>,
+    /// The error sinks and payload configuration.
+    pub errors: ErrorReporter,
+    /// The handlers receiving rollback notifications.
+    pub rollback: RollbackHandlers,
+    /// The store to save the checkpointed slot to.
+    pub checkpoint: Option<Box<dyn CheckpointStore>>,
     /// The metrics.
     pub metrics: M,
     /// The extra builder kind.
@@ -78,6 +108,12 @@ impl<K: BuilderKind, S: SourceTrait> Default for Builder<K, NullMetrics, S> {
             instruction: vec![],
             block_meta: vec![],
             slot: vec![],
+            block: vec![],
+            entry: vec![],
+            transaction_status: vec![],
+            errors: ErrorReporter::default(),
+            rollback: RollbackHandlers::default(),
+            checkpoint: None,
             metrics: NullMetrics,
             extra: K::default(),
             _source: std::marker::PhantomData,
@@ -119,6 +155,12 @@ impl<K: BuilderKind, M, S: SourceTrait> Builder<K, M, S> {
             instruction,
             block_meta,
             slot,
+            block,
+            entry,
+            transaction_status,
+            errors,
+            rollback,
+            checkpoint,
             metrics: _,
             extra,
             _source: source,
@@ -131,6 +173,12 @@ impl<K: BuilderKind, M, S: SourceTrait> Builder<K, M, S> {
             instruction,
             block_meta,
             slot,
+            block,
+            entry,
+            transaction_status,
+            errors,
+            rollback,
+            checkpoint,
             metrics,
             extra,
             _source: source,
@@ -189,12 +237,78 @@ impl<S: SourceTrait, M: MetricsFactory> RuntimeBuilder<S, M> {
         self.mutate(|s| s.slot.push(Box::new(slot)))
     }
 
+    /// Add a new block pipeline to the builder.
+    pub fn block<T: DynPipeline<BlockUpdate> + Send + Sync + 'static>(self, block: T) -> Self {
+        self.mutate(|s| s.block.push(Box::new(block)))
+    }
+
+    /// Add a new entry pipeline to the builder.
+    pub fn entry<T: DynPipeline<EntryUpdate> + Send + Sync + 'static>(self, entry: T) -> Self {
+        self.mutate(|s| s.entry.push(Box::new(entry)))
+    }
+
+    /// Add a new transaction status pipeline to the builder.
+    pub fn transaction_status<T: DynPipeline<TransactionStatusUpdate> + Send + Sync + 'static>(
+        self,
+        transaction_status: T,
+    ) -> Self {
+        self.mutate(|s| s.transaction_status.push(Box::new(transaction_status)))
+    }
+
+    /// Add a sink to receive every parse and handler error, along with the
+    /// context of the update that caused it.
+    pub fn error_sink<E: ErrorSink + 'static>(self, sink: E) -> Self {
+        self.mutate(|s| s.errors.add_sink(Box::new(sink)))
+    }
+
+    /// Attach a snippet of the raw account or instruction data to the context
+    /// of parse and handler errors.  By default no data is included.
+    pub fn error_payload(self, payload: ErrorPayload) -> Self {
+        self.mutate(|s| s.errors.set_payload(Some(payload)))
+    }
+
+    /// Send every update that fails to parse or be handled to the given
+    /// dead-letter sink, along with the ID of the failed pipeline and the
+    /// errors it raised.  If the sink is a
+    /// [`DeadLetterStore`](crate::dead_letter::DeadLetterStore) the stored
+    /// updates can be replayed with [`Runtime::replay`].
+    pub fn dead_letter_sink<D: DeadLetterSink + 'static>(self, sink: D) -> Self {
+        self.mutate(|s| s.errors.set_dead_letters(Some(Box::new(sink))))
+    }
+
+    /// Add a handler to be notified when a slot is skipped or forked out
+    /// after some of its updates were delivered.
+    ///
+    /// Registering a rollback handler enables tracking of slot statuses,
+    /// which requires a source that reports the status of every slot.
+    /// Rollbacks are dispatched like any other update, so set
+    /// [`BufferConfig::order_by`](crate::config::BufferConfig::order_by) to
+    /// [`OrderBy::Slot`](crate::config::OrderBy::Slot) to process a rollback
+    /// only after the updates of its slot.
+    pub fn rollback_handler<H: Handler<Rollback> + std::fmt::Debug + Send + Sync + 'static>(
+        self,
+        handler: H,
+    ) -> Self {
+        self.mutate(|s| s.rollback.push(handler))
+    }
+
+    /// Save the last slot whose updates were fully handled to the given
+    /// checkpoint store, and resume streaming from the slot following it
+    /// when the source connects or reconnects.
+    ///
+    /// Resuming requires a source that supports starting from a slot, see
+    /// [`SourceTrait::set_checkpoint`].
+    pub fn checkpoint_store<C: CheckpointStore + 'static>(self, store: C) -> Self {
+        self.mutate(|s| s.checkpoint = Some(Box::new(store)))
+    }
+
     /// Attempt to build a new [`Runtime`] instance from the current builder
     /// state and the provided configuration.
     ///
     /// # Errors
     /// This function returns an error if the builder or configuration are
     /// invalid.
+    #[allow(clippy::too_many_lines)]
     pub fn try_build(
         self,
         config: VixenConfig<M::Config, S::Config>,
@@ -206,6 +320,12 @@ impl<S: SourceTrait, M: MetricsFactory> RuntimeBuilder<S, M> {
             instruction,
             block_meta,
             slot,
+            block,
+            entry,
+            transaction_status,
+            errors,
+            rollback,
+            checkpoint,
             metrics,
             extra: RuntimeKind,
             _source,
@@ -227,15 +347,24 @@ impl<S: SourceTrait, M: MetricsFactory> RuntimeBuilder<S, M> {
             .create(metrics_cfg, "vixen")
             .map_err(|e| BuilderError::Metrics(e.into()))?;
 
+        let errors = Arc::new(errors);
+        let wrap_instructions: WrapInstructions = {
+            let counters = Arc::new(InstructionCounters::new(&instrumenter));
+            let errors = Arc::clone(&errors);
+
+            Box::new(move |ix| -> BoxPipeline<'static, TransactionInstructions> {
+                Box::new(SingleInstructionPipeline::with_counters(
+                    ix,
+                    Arc::clone(&counters),
+                    Arc::clone(&errors),
+                ))
+            })
+        };
         let mut ixs = PipelineSet::new();
 
         for ix in instruction {
             let id = ix.id().into_owned();
-            let pre_existent_parser = ixs.insert(
-                id.clone(),
-                Box::new(SingleInstructionPipeline::new(ix, &instrumenter))
-                    as BoxPipeline<'static, TransactionUpdate>,
-            );
+            let pre_existent_parser = ixs.insert(id.clone(), wrap_instructions(ix));
 
             if pre_existent_parser.is_some() {
                 tracing::warn!("Duplicate parser ID detected: {}", id);
@@ -246,6 +375,9 @@ impl<S: SourceTrait, M: MetricsFactory> RuntimeBuilder<S, M> {
         let transaction_len = transaction.len();
         let block_meta_len = block_meta.len();
         let slot_len = slot.len();
+        let block_len = block.len();
+        let entry_len = entry.len();
+        let transaction_status_len = transaction_status.len();
 
         let pipelines = PipelineSets {
             account: account.into_iter().collect(),
@@ -253,6 +385,15 @@ impl<S: SourceTrait, M: MetricsFactory> RuntimeBuilder<S, M> {
             instruction: ixs,
             block_meta: block_meta.into_iter().collect(),
             slot: slot.into_iter().collect(),
+            block: block.into_iter().collect(),
+            entry: entry.into_iter().collect(),
+            transaction_status: transaction_status.into_iter().collect(),
+            errors,
+            track_slots: buffer_cfg.hold_until.is_some() || !rollback.is_empty(),
+            rollback: Arc::new(rollback),
+            prefilters: HashMap::new(),
+            data_slices: Arc::default(),
+            parser_ids: Arc::default(),
         };
 
         if pipelines.account.len() != account_len {
@@ -271,11 +412,25 @@ impl<S: SourceTrait, M: MetricsFactory> RuntimeBuilder<S, M> {
             return Err(BuilderError::SlotPipelineCollision);
         }
 
+        if pipelines.block.len() != block_len {
+            return Err(BuilderError::BlockCollision);
+        }
+
+        if pipelines.entry.len() != entry_len {
+            return Err(BuilderError::EntryCollision);
+        }
+
+        if pipelines.transaction_status.len() != transaction_status_len {
+            return Err(BuilderError::TransactionStatusCollision);
+        }
+
         Ok(Runtime {
             buffer: buffer_cfg,
             source: source_cfg,
             pipelines,
             counters: Counters::new(&instrumenter),
+            control: Control::new(wrap_instructions),
+            checkpoint,
             exporter,
             _source: std::marker::PhantomData,
         })
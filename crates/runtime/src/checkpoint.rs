@@ -0,0 +1,596 @@
+//! Checkpointing of the last slot whose updates were fully handled, so the
+//! source can resume from it after a restart.
+
+use std::{
+    collections::{btree_map::Entry, BTreeMap},
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, PoisonError, Weak,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use yellowstone_grpc_proto::geyser::{subscribe_update::UpdateOneof, SubscribeUpdate};
+
+#[cfg(feature = "sqlite")]
+pub use self::sqlite::SqliteCheckpointStore;
+use crate::commitment;
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// The interval at which the checkpoint is saved to its store.
+pub(crate) const SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Persistent storage for the checkpointed slot of a runtime.
+#[async_trait]
+pub trait CheckpointStore: fmt::Debug + Send + Sync {
+    /// Load the last saved slot, if any.
+    ///
+    /// # Errors
+    /// Returns an error if the stored slot could not be read.
+    async fn load(&self) -> Result<Option<u64>, BoxedError>;
+
+    /// Save the given slot, replacing the previous one.
+    ///
+    /// # Errors
+    /// Returns an error if the slot could not be stored.
+    async fn save(&self, slot: u64) -> Result<(), BoxedError>;
+}
+
+/// A checkpoint store keeping the slot as text in a local file.
+#[derive(Debug, Clone)]
+pub struct FileCheckpointStore {
+    path: PathBuf,
+}
+
+impl FileCheckpointStore {
+    /// Create a store for the file at the given path.  The file is created
+    /// when the first checkpoint is saved.
+    #[inline]
+    #[must_use]
+    pub fn new(path: impl Into<PathBuf>) -> Self { Self { path: path.into() } }
+
+    /// The path of the file.
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &Path { &self.path }
+}
+
+#[async_trait]
+impl CheckpointStore for FileCheckpointStore {
+    async fn load(&self) -> Result<Option<u64>, BoxedError> {
+        match tokio::fs::read_to_string(&self.path).await {
+            Ok(s) => Ok(Some(s.trim().parse()?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    async fn save(&self, slot: u64) -> Result<(), BoxedError> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        // Written to a temporary file first so a partial write never replaces
+        // the previous checkpoint
+        tokio::fs::write(&tmp, format!("{slot}\n")).await?;
+        tokio::fs::rename(&tmp, &self.path).await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use std::{
+        path::Path,
+        sync::{Arc, Mutex, PoisonError},
+    };
+
+    use async_trait::async_trait;
+    use rusqlite::{Connection, OptionalExtension};
+
+    use super::{BoxedError, CheckpointStore};
+
+    /// A checkpoint store keeping the slot in a `SQLite` database.  Slots are
+    /// stored under a key, so several runtimes can share a database.
+    #[derive(Debug, Clone)]
+    pub struct SqliteCheckpointStore {
+        conn: Arc<Mutex<Connection>>,
+        key: String,
+    }
+
+    impl SqliteCheckpointStore {
+        /// Open the database at the given path, creating it and its
+        /// checkpoint table if they do not exist.
+        ///
+        /// # Errors
+        /// Returns an error if the database could not be opened or
+        /// initialized.
+        pub fn open(path: impl AsRef<Path>, key: impl Into<String>) -> rusqlite::Result<Self> {
+            let conn = Connection::open(path)?;
+            conn.execute(
+                "CREATE TABLE IF NOT EXISTS vixen_checkpoints (
+                    key TEXT PRIMARY KEY NOT NULL,
+                    slot INTEGER NOT NULL
+                )",
+                [],
+            )?;
+
+            Ok(Self {
+                conn: Arc::new(Mutex::new(conn)),
+                key: key.into(),
+            })
+        }
+
+        async fn with_conn<T: Send + 'static>(
+            &self,
+            f: impl FnOnce(&Connection, &str) -> Result<T, BoxedError> + Send + 'static,
+        ) -> Result<T, BoxedError> {
+            let conn = Arc::clone(&self.conn);
+            let key = self.key.clone();
+
+            tokio::task::spawn_blocking(move || {
+                f(&conn.lock().unwrap_or_else(PoisonError::into_inner), &key)
+            })
+            .await?
+        }
+    }
+
+    #[async_trait]
+    impl CheckpointStore for SqliteCheckpointStore {
+        async fn load(&self) -> Result<Option<u64>, BoxedError> {
+            self.with_conn(|conn, key| {
+                let slot: Option<i64> = conn
+                    .query_row(
+                        "SELECT slot FROM vixen_checkpoints WHERE key = ?1",
+                        [key],
+                        |r| r.get(0),
+                    )
+                    .optional()?;
+
+                Ok(slot.map(u64::try_from).transpose()?)
+            })
+            .await
+        }
+
+        async fn save(&self, slot: u64) -> Result<(), BoxedError> {
+            let slot = i64::try_from(slot)?;
+
+            self.with_conn(move |conn, key| {
+                conn.execute(
+                    "INSERT INTO vixen_checkpoints (key, slot) VALUES (?1, ?2)
+                    ON CONFLICT (key) DO UPDATE SET slot = excluded.slot",
+                    rusqlite::params![key, slot],
+                )?;
+
+                Ok(())
+            })
+            .await
+        }
+    }
+}
+
+/// The last slot whose updates were fully handled by a runtime, shared with
+/// its source so it can resume from the following slot when subscribing.
+#[derive(Debug, Clone, Default)]
+pub struct Checkpoint(Arc<CheckpointState>);
+
+#[derive(Debug, Default)]
+struct CheckpointState {
+    /// One past the checkpointed slot, or zero if there is none
+    slot: AtomicU64,
+    streams: Mutex<Vec<Weak<AtomicU64>>>,
+}
+
+impl Checkpoint {
+    /// Create a checkpoint at the given slot, not attached to any runtime.
+    ///
+    /// Runtimes pass their own checkpoint to their source, so this is mostly
+    /// useful to test sources resuming from a checkpoint.
+    #[must_use]
+    pub fn new(slot: Option<u64>) -> Self {
+        Self(Arc::new(CheckpointState {
+            slot: AtomicU64::new(slot.map_or(0, |s| s.saturating_add(1))),
+            streams: Mutex::default(),
+        }))
+    }
+
+    /// The last checkpointed slot, if any.
+    #[inline]
+    #[must_use]
+    pub fn slot(&self) -> Option<u64> { self.0.slot.load(Ordering::Acquire).checked_sub(1) }
+
+    /// The slot to resume streaming from, following the last checkpointed
+    /// slot.
+    #[inline]
+    #[must_use]
+    pub fn resume_slot(&self) -> Option<u64> { self.slot().map(|s| s.saturating_add(1)) }
+
+    /// Start tracking the progress of one of the streams the source
+    /// receives updates over.
+    ///
+    /// Sources receiving updates over several streams must track each of
+    /// them, since one stream may lag behind the others.  The checkpoint then
+    /// never passes the newest slot received on the slowest stream.  Sources
+    /// receiving every update over a single stream need not track it.
+    #[must_use]
+    pub fn track_stream(&self) -> StreamProgress {
+        let slot = Arc::default();
+
+        self.0
+            .streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Arc::downgrade(&slot));
+
+        StreamProgress(slot)
+    }
+
+    /// The newest slot received on the slowest tracked stream, if any stream
+    /// is tracked.
+    fn slowest_stream(&self) -> Option<u64> {
+        let mut streams = self
+            .0
+            .streams
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        streams.retain(|s| s.strong_count() > 0);
+
+        streams
+            .iter()
+            .filter_map(|s| s.upgrade()?.load(Ordering::Acquire).checked_sub(1))
+            .min()
+    }
+
+    fn set(&self, slot: u64) {
+        self.0
+            .slot
+            .fetch_max(slot.saturating_add(1), Ordering::AcqRel);
+    }
+}
+
+/// The newest slot received on one of the streams of a source, see
+/// [`Checkpoint::track_stream`].  The stream stops being tracked once every
+/// clone of its progress is dropped.
+#[derive(Debug, Clone)]
+pub struct StreamProgress(Arc<AtomicU64>);
+
+impl StreamProgress {
+    /// Record an update received on the stream, including slot status
+    /// updates.
+    pub fn observe(&self, update: &SubscribeUpdate) {
+        let slot = match update.update_oneof.as_ref() {
+            Some(UpdateOneof::Slot(s)) => Some(s.slot),
+            Some(u) => commitment::data_slot(u),
+            None => None,
+        };
+
+        if let Some(slot) = slot {
+            self.0.fetch_max(slot.saturating_add(1), Ordering::AcqRel);
+        }
+    }
+
+    /// Restart tracking the stream from the given slot, e.g. after it was
+    /// resubscribed from it.  If `slot` is `None`, the stream does not hold
+    /// back the checkpoint until it receives an update.
+    pub fn reset(&self, slot: Option<u64>) {
+        self.0
+            .store(slot.map_or(0, |s| s.saturating_add(1)), Ordering::Release);
+    }
+}
+
+#[derive(Debug, Default)]
+struct Progress {
+    /// The number of received updates not yet processed for each slot
+    in_flight: BTreeMap<u64, usize>,
+    newest: Option<u64>,
+    /// The lowest slot with an update that was abandoned before it was
+    /// processed
+    abandoned: Option<u64>,
+}
+
+/// Tracks the updates of each slot that were received but not yet processed.
+#[derive(Debug, Default)]
+pub(crate) struct SlotProgress(Mutex<Progress>);
+
+impl SlotProgress {
+    /// Record an update as received, returning a guard to mark it as
+    /// processed with.  Slot status updates are not tracked.
+    pub fn start(self: &Arc<Self>, update: &SubscribeUpdate) -> Option<InFlight> {
+        let slot = commitment::data_slot(update.update_oneof.as_ref()?)?;
+        let mut progress = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        *progress.in_flight.entry(slot).or_default() += 1;
+        progress.newest = progress.newest.max(Some(slot));
+
+        Some(InFlight {
+            progress: Arc::clone(self),
+            slot,
+            finished: false,
+        })
+    }
+
+    fn release(&self, slot: u64, finished: bool) {
+        let mut progress = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if !finished {
+            progress.abandoned = Some(progress.abandoned.map_or(slot, |s| s.min(slot)));
+        }
+
+        if let Entry::Occupied(mut e) = progress.in_flight.entry(slot) {
+            *e.get_mut() -= 1;
+
+            if *e.get() == 0 {
+                e.remove();
+            }
+        }
+    }
+
+    /// Get the highest slot for which every received update was processed.
+    /// The newest slot received is never complete, since more of its
+    /// updates may still arrive, and no slot with an abandoned update is
+    /// ever complete.
+    ///
+    /// Streams lagging behind the newest slot are accounted for by
+    /// [`Checkpointer::save`], see [`Checkpoint::track_stream`].
+    #[must_use]
+    pub fn watermark(&self) -> Option<u64> {
+        let progress = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let newest = progress.newest?;
+
+        progress
+            .in_flight
+            .keys()
+            .next()
+            .map_or(newest, |&s| s.min(newest))
+            .min(progress.abandoned.unwrap_or(u64::MAX))
+            .checked_sub(1)
+    }
+}
+
+/// A received update which has not finished processing.
+///
+/// An update whose guard is dropped without calling [`Self::finish`], e.g.
+/// because its job was cancelled on shutdown, is released as abandoned.  It
+/// is never considered processed, and holds the checkpoint below its slot.
+#[derive(Debug)]
+#[must_use]
+pub(crate) struct InFlight {
+    progress: Arc<SlotProgress>,
+    slot: u64,
+    finished: bool,
+}
+
+impl InFlight {
+    /// Mark the update as processed, or as intentionally discarded.
+    #[inline]
+    pub fn finish(mut self) { self.finished = true; }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) { self.progress.release(self.slot, self.finished); }
+}
+
+/// Periodically saves the progress of a runtime to its checkpoint store.
+#[derive(Debug)]
+pub(crate) struct Checkpointer {
+    store: Box<dyn CheckpointStore>,
+    progress: Arc<SlotProgress>,
+    checkpoint: Checkpoint,
+}
+
+impl Checkpointer {
+    /// Create a checkpointer starting from the slot saved in the given store.
+    pub async fn load(store: Box<dyn CheckpointStore>) -> Result<Self, BoxedError> {
+        let slot = store.load().await?;
+
+        if let Some(slot) = slot {
+            tracing::info!(slot, "Resuming from checkpoint");
+        }
+
+        Ok(Self {
+            store,
+            progress: Arc::default(),
+            checkpoint: Checkpoint::new(slot),
+        })
+    }
+
+    #[inline]
+    pub fn checkpoint(&self) -> Checkpoint { self.checkpoint.clone() }
+
+    #[inline]
+    pub fn progress(&self) -> Arc<SlotProgress> { Arc::clone(&self.progress) }
+
+    /// Save the current progress if it advanced past the last saved slot.
+    pub async fn save(&self) {
+        let Some(mut slot) = self.progress.watermark() else {
+            return;
+        };
+
+        // Updates of the newest slot of the slowest stream may still arrive
+        if let Some(slowest) = self.checkpoint.slowest_stream() {
+            let Some(complete) = slowest.checked_sub(1) else {
+                return;
+            };
+
+            slot = slot.min(complete);
+        }
+
+        if self.checkpoint.slot().is_some_and(|s| s >= slot) {
+            return;
+        }
+
+        match self.store.save(slot).await {
+            Ok(()) => self.checkpoint.set(slot),
+            Err(e) => tracing::error!(err = %crate::Chain(&*e), slot, "Failed to save checkpoint"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::account_update;
+
+    fn start(progress: &Arc<SlotProgress>, slot: u64) -> InFlight {
+        progress.start(&account_update(1, slot, &[])).unwrap()
+    }
+
+    fn temp_file(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("vixen-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn newest_slot_never_complete() {
+        let progress = Arc::new(SlotProgress::default());
+        assert_eq!(progress.watermark(), None);
+
+        start(&progress, 10).finish();
+        assert_eq!(progress.watermark(), Some(9));
+
+        start(&progress, 11).finish();
+        assert_eq!(progress.watermark(), Some(10));
+    }
+
+    #[test]
+    fn out_of_order_completion() {
+        let progress = Arc::new(SlotProgress::default());
+        let first = start(&progress, 10);
+        let second = [start(&progress, 11), start(&progress, 11)];
+        let third = start(&progress, 12);
+        let newest = start(&progress, 13);
+
+        third.finish();
+        let [second, rest] = second;
+        second.finish();
+        assert_eq!(progress.watermark(), Some(9));
+
+        first.finish();
+        assert_eq!(progress.watermark(), Some(10));
+
+        rest.finish();
+        assert_eq!(progress.watermark(), Some(12));
+
+        newest.finish();
+        assert_eq!(progress.watermark(), Some(12));
+    }
+
+    #[test]
+    fn abandoned_update_holds_back_watermark() {
+        let progress = Arc::new(SlotProgress::default());
+        let a = start(&progress, 10);
+        let b = start(&progress, 11);
+        let c = start(&progress, 12);
+        start(&progress, 13).finish();
+
+        a.finish();
+        drop(b);
+        c.finish();
+        assert_eq!(progress.watermark(), Some(10));
+
+        // The slot is released, so later slots still complete behind it
+        start(&progress, 14).finish();
+        assert_eq!(progress.watermark(), Some(10));
+        assert!(progress
+            .0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .in_flight
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn lagging_stream_holds_back_checkpoint() {
+        let path = temp_file("checkpoint-streams");
+        let checkpointer = Checkpointer::load(Box::new(FileCheckpointStore::new(&path)))
+            .await
+            .unwrap();
+        let checkpoint = checkpointer.checkpoint();
+        let progress = checkpointer.progress();
+        let fast = checkpoint.track_stream();
+        let slow = checkpoint.track_stream();
+
+        for (stream, slot) in [(&fast, 100), (&slow, 50), (&fast, 101)] {
+            let update = account_update(1, slot, &[]);
+            stream.observe(&update);
+            progress.start(&update).unwrap().finish();
+        }
+
+        // Updates of the slots following 50 may still arrive on the slow
+        // stream
+        checkpointer.save().await;
+        assert_eq!(checkpoint.slot(), Some(49));
+
+        slow.observe(&account_update(1, 80, &[]));
+        checkpointer.save().await;
+        assert_eq!(checkpoint.slot(), Some(79));
+
+        // A stream reset without a slot does not hold back the checkpoint
+        slow.reset(None);
+        checkpointer.save().await;
+        assert_eq!(checkpoint.slot(), Some(100));
+
+        // A stream resubscribed from an earlier slot does, until it catches up
+        fast.reset(Some(90));
+        progress
+            .start(&account_update(1, 120, &[]))
+            .unwrap()
+            .finish();
+        checkpointer.save().await;
+        assert_eq!(checkpoint.slot(), Some(100));
+
+        drop(fast);
+        checkpointer.save().await;
+        assert_eq!(checkpoint.slot(), Some(119));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "119\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn file_store_round_trip() {
+        let store = FileCheckpointStore::new(temp_file("checkpoint-file"));
+        assert_eq!(store.load().await.unwrap(), None);
+
+        store.save(5).await.unwrap();
+        assert_eq!(store.load().await.unwrap(), Some(5));
+
+        store.save(7).await.unwrap();
+        assert_eq!(store.load().await.unwrap(), Some(7));
+        assert_eq!(std::fs::read_to_string(store.path()).unwrap(), "7\n");
+
+        std::fs::remove_file(store.path()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn resume_after_load() {
+        let path = temp_file("checkpoint-resume");
+        std::fs::write(&path, "41\n").unwrap();
+
+        let checkpointer = Checkpointer::load(Box::new(FileCheckpointStore::new(&path)))
+            .await
+            .unwrap();
+        let checkpoint = checkpointer.checkpoint();
+        assert_eq!(checkpoint.slot(), Some(41));
+        assert_eq!(checkpoint.resume_slot(), Some(42));
+
+        // Progress behind the loaded checkpoint is not saved
+        let progress = checkpointer.progress();
+        start(&progress, 40).finish();
+        start(&progress, 41).finish();
+        checkpointer.save().await;
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "41\n");
+
+        start(&progress, 45).finish();
+        checkpointer.save().await;
+        assert_eq!(checkpoint.resume_slot(), Some(45));
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "44\n");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,483 @@
+//! Commitment tracking for updates received below the commitment level a
+//! consumer needs, and rollback notifications for slots that are skipped or
+//! forked out after their updates were delivered.
+
+use std::{
+    collections::{BTreeMap, HashSet, VecDeque},
+    fmt,
+    pin::Pin,
+};
+
+use futures_util::Future;
+use vixen_core::{CommitmentLevel, Prefilter, SlotPrefilter};
+use yellowstone_grpc_proto::geyser::{subscribe_update::UpdateOneof, SlotStatus, SubscribeUpdate};
+
+use crate::{checkpoint::InFlight, Handler, HandlerResult};
+
+/// The subscription filter requesting every slot status for the tracker.
+pub(crate) const STATUS_FILTER: &str = "vixen::slot-status";
+
+/// The number of slots behind the newest slot seen after which a slot that
+/// never reached the required commitment level is discarded.
+const MAX_PENDING_SLOTS: u64 = 512;
+
+/// A notification that a slot was skipped or forked out after some of its
+/// updates were delivered to handlers.
+///
+/// Rollbacks are delivered to the handlers registered with
+/// [`RuntimeBuilder::rollback_handler`](crate::builder::RuntimeBuilder::rollback_handler).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rollback {
+    /// The slot whose updates should be undone.
+    pub slot: u64,
+}
+
+trait DynRollbackHandler: fmt::Debug + Send + Sync {
+    fn handle<'h>(
+        &'h self,
+        rollback: &'h Rollback,
+    ) -> Pin<Box<dyn Future<Output = HandlerResult<()>> + Send + 'h>>;
+}
+
+impl<H: Handler<Rollback> + fmt::Debug + Send + Sync> DynRollbackHandler for H {
+    fn handle<'h>(
+        &'h self,
+        rollback: &'h Rollback,
+    ) -> Pin<Box<dyn Future<Output = HandlerResult<()>> + Send + 'h>> {
+        Box::pin(Handler::handle(self, rollback))
+    }
+}
+
+/// The handlers receiving [`Rollback`] notifications.
+#[derive(Debug, Default)]
+pub struct RollbackHandlers(Vec<Box<dyn DynRollbackHandler>>);
+
+impl RollbackHandlers {
+    pub(crate) fn push<H: Handler<Rollback> + fmt::Debug + Send + Sync + 'static>(
+        &mut self,
+        handler: H,
+    ) {
+        self.0.push(Box::new(handler));
+    }
+
+    #[inline]
+    #[must_use]
+    pub(crate) fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    pub(crate) async fn run(&self, rollback: Rollback) {
+        let rollback = &rollback;
+        let errs = futures_util::future::join_all(self.0.iter().map(|h| h.handle(rollback))).await;
+
+        for e in errs.into_iter().filter_map(Result::err) {
+            tracing::error!(err = %crate::Chain(&*e), slot = rollback.slot, "Rollback handler failed");
+        }
+    }
+}
+
+/// Get the prefilter requesting every slot status for the tracker.
+pub(crate) fn status_prefilter() -> Prefilter {
+    Prefilter {
+        slot: Some(SlotPrefilter { all_statuses: true }),
+        ..Prefilter::default()
+    }
+}
+
+/// Get the slot of an update carrying data from a slot, i.e. any update
+/// other than a slot status or ping.
+pub(crate) fn data_slot(update: &UpdateOneof) -> Option<u64> {
+    match update {
+        UpdateOneof::Account(a) => Some(a.slot),
+        UpdateOneof::Transaction(t) => Some(t.slot),
+        UpdateOneof::TransactionStatus(t) => Some(t.slot),
+        UpdateOneof::BlockMeta(b) => Some(b.slot),
+        UpdateOneof::Block(b) => Some(b.slot),
+        UpdateOneof::Entry(e) => Some(e.slot),
+        UpdateOneof::Slot(_) | UpdateOneof::Ping(_) | UpdateOneof::Pong(_) => None,
+    }
+}
+
+/// Mark the given held updates as processed, discarding them.
+fn discard(held: &mut Vec<(SubscribeUpdate, Option<InFlight>)>) {
+    for (_, in_flight) in held.drain(..) {
+        if let Some(f) = in_flight {
+            f.finish();
+        }
+    }
+}
+
+/// An item released by the tracker to be dispatched.
+#[allow(clippy::large_enum_variant)]
+pub(crate) enum Ready {
+    Update(SubscribeUpdate, Option<InFlight>),
+    Rollback(Rollback),
+}
+
+#[derive(Debug, Default)]
+struct SlotState {
+    parent: Option<u64>,
+    level: Option<CommitmentLevel>,
+    dead: bool,
+    /// Updates held until the slot reaches the required commitment level
+    held: Vec<(SubscribeUpdate, Option<InFlight>)>,
+    /// Whether any update from this slot was dispatched
+    delivered: bool,
+}
+
+/// Tracks the status of each slot from the slot updates of the
+/// [`STATUS_FILTER`] subscription, holding updates until their slot reaches
+/// the required commitment level and reporting slots that die after their
+/// updates were dispatched.
+pub(crate) struct Tracker {
+    hold_until: Option<CommitmentLevel>,
+    rollback: bool,
+    slots: BTreeMap<u64, SlotState>,
+    /// The latest finalized slot.  State for it and every slot below it has
+    /// been discarded.
+    root: Option<u64>,
+    newest: u64,
+    ready: VecDeque<Ready>,
+}
+
+impl Tracker {
+    pub fn new(hold_until: Option<CommitmentLevel>, rollback: bool) -> Self {
+        Self {
+            hold_until,
+            rollback,
+            slots: BTreeMap::new(),
+            root: None,
+            newest: 0,
+            ready: VecDeque::new(),
+        }
+    }
+
+    /// Record the slot status carried by the given update if it was sent for
+    /// the tracker, removing the tracker's filter from the update.  Returns
+    /// true if no other filter matched the update.
+    pub fn observe(&mut self, update: &mut SubscribeUpdate) -> bool {
+        let SubscribeUpdate {
+            filters,
+            update_oneof,
+            created_at: _,
+        } = update;
+        let Some(i) = filters.iter().position(|f| f == STATUS_FILTER) else {
+            return false;
+        };
+        filters.swap_remove(i);
+
+        if let Some(UpdateOneof::Slot(slot)) = update_oneof {
+            if let Ok(status) = SlotStatus::try_from(slot.status) {
+                self.status(slot.slot, slot.parent, status);
+            }
+        }
+
+        filters.is_empty()
+    }
+
+    /// Hold the given update until its slot reaches the required commitment
+    /// level, or release it immediately if it already has.
+    pub fn push(&mut self, update: SubscribeUpdate, in_flight: Option<InFlight>) {
+        // Slot notifications for pipelines are passed through untouched
+        let Some(slot) = update.update_oneof.as_ref().and_then(data_slot) else {
+            self.ready.push_back(Ready::Update(update, in_flight));
+            return;
+        };
+
+        if self.root.is_some_and(|r| slot <= r) {
+            self.ready.push_back(Ready::Update(update, in_flight));
+            return;
+        }
+
+        self.advance(slot);
+        let hold_until = self.hold_until;
+        let state = self.slots.entry(slot).or_default();
+
+        if state.dead {
+            tracing::debug!(slot, "Dropping update from dead slot");
+
+            if let Some(f) = in_flight {
+                f.finish();
+            }
+        } else if hold_until.is_some_and(|l| state.level < Some(l)) {
+            state.held.push((update, in_flight));
+        } else {
+            state.delivered = true;
+            self.ready.push_back(Ready::Update(update, in_flight));
+        }
+    }
+
+    /// Take the next update or rollback ready to be dispatched.
+    #[inline]
+    pub fn next_ready(&mut self) -> Option<Ready> { self.ready.pop_front() }
+
+    /// Get the number of updates held until their slot reaches the required
+    /// commitment level.
+    pub fn held(&self) -> usize { self.slots.values().map(|s| s.held.len()).sum() }
+
+    fn status(&mut self, slot: u64, parent: Option<u64>, status: SlotStatus) {
+        if self.root.is_some_and(|r| slot <= r) {
+            return;
+        }
+
+        self.advance(slot);
+        let state = self.slots.entry(slot).or_default();
+        state.parent = parent.or(state.parent);
+
+        match status {
+            SlotStatus::SlotProcessed => self.reach(slot, CommitmentLevel::Processed),
+            SlotStatus::SlotConfirmed => self.reach(slot, CommitmentLevel::Confirmed),
+            SlotStatus::SlotFinalized => {
+                self.reach(slot, CommitmentLevel::Finalized);
+                self.finalize(slot);
+            },
+            SlotStatus::SlotDead => self.abandon(slot),
+            _ => (),
+        }
+    }
+
+    /// Raise the commitment level of a slot and its known ancestors,
+    /// releasing their held updates in slot order once they reach the
+    /// required level.
+    fn reach(&mut self, slot: u64, level: CommitmentLevel) {
+        let mut chain = vec![];
+        let mut next = Some(slot);
+
+        while let Some(s) = next {
+            let Some(state) = self.slots.get_mut(&s).filter(|s| !s.dead) else {
+                break;
+            };
+
+            if state.level >= Some(level) {
+                break;
+            }
+
+            state.level = Some(level);
+            chain.push(s);
+            next = state.parent;
+        }
+
+        if self.hold_until.is_none_or(|l| level < l) {
+            return;
+        }
+
+        for s in chain.into_iter().rev() {
+            let state = self.slots.get_mut(&s).unwrap_or_else(|| unreachable!());
+
+            if !state.held.is_empty() {
+                state.delivered = true;
+                self.ready
+                    .extend(state.held.drain(..).map(|(u, f)| Ready::Update(u, f)));
+            }
+        }
+    }
+
+    /// Mark a slot and every known descendant of it as dead.
+    fn abandon(&mut self, slot: u64) {
+        let mut dead = HashSet::from([slot]);
+
+        for (&s, state) in self.slots.range_mut(slot..) {
+            if s != slot && !state.parent.is_some_and(|p| dead.contains(&p)) {
+                continue;
+            }
+
+            dead.insert(s);
+
+            if state.dead {
+                continue;
+            }
+
+            state.dead = true;
+
+            if !state.held.is_empty() {
+                tracing::debug!(
+                    slot = s,
+                    count = state.held.len(),
+                    "Dropping updates from dead slot"
+                );
+                discard(&mut state.held);
+            }
+
+            if self.rollback && state.delivered {
+                self.ready.push_back(Ready::Rollback(Rollback { slot: s }));
+            }
+        }
+    }
+
+    /// Abandon every slot known not to descend from or be an ancestor of a
+    /// newly finalized slot, then discard the state of every slot up to it.
+    fn finalize(&mut self, slot: u64) {
+        let mut chain = HashSet::from([slot]);
+        let mut lowest = slot;
+
+        while let Some(parent) = self
+            .slots
+            .get(&lowest)
+            .and_then(|s| s.parent)
+            .filter(|&p| p < lowest)
+        {
+            chain.insert(parent);
+            lowest = parent;
+        }
+
+        // Slots above the finalized slot branching off below it are forked
+        // out as well
+        let forked: Vec<_> = self
+            .slots
+            .range(lowest..slot)
+            .filter(|(s, _)| !chain.contains(s))
+            .chain(
+                self.slots
+                    .range(slot + 1..)
+                    .filter(|(_, state)| state.parent.is_some_and(|p| p < slot)),
+            )
+            .filter(|(_, state)| !state.dead)
+            .map(|(&s, _)| s)
+            .collect();
+
+        for s in forked {
+            self.abandon(s);
+        }
+
+        let rest = self.slots.split_off(&(slot + 1));
+        let pruned = std::mem::replace(&mut self.slots, rest);
+        self.root = Some(slot);
+
+        for (s, mut state) in pruned {
+            if !state.held.is_empty() {
+                tracing::warn!(
+                    slot = s,
+                    count = state.held.len(),
+                    "Dropping updates from slot with unknown status below finalized slot",
+                );
+                discard(&mut state.held);
+            }
+        }
+    }
+
+    /// Record the newest slot seen, discarding slots too far behind it that
+    /// never reached the required commitment level.
+    fn advance(&mut self, slot: u64) {
+        if slot <= self.newest {
+            return;
+        }
+
+        self.newest = slot;
+        let Some(min) = slot.checked_sub(MAX_PENDING_SLOTS) else {
+            return;
+        };
+
+        let rest = self.slots.split_off(&min);
+        let pruned = std::mem::replace(&mut self.slots, rest);
+
+        for (s, mut state) in pruned {
+            if !state.held.is_empty() {
+                tracing::warn!(
+                    slot = s,
+                    count = state.held.len(),
+                    "Dropping updates from slot that never reached the required commitment",
+                );
+                discard(&mut state.held);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use yellowstone_grpc_proto::geyser::SubscribeUpdateSlot;
+
+    use super::*;
+    use crate::{checkpoint::SlotProgress, test_util::account_update};
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Out {
+        Update(u64),
+        Rollback(u64),
+    }
+
+    fn status(tracker: &mut Tracker, slot: u64, parent: Option<u64>, status: SlotStatus) {
+        let mut update = SubscribeUpdate {
+            filters: vec![STATUS_FILTER.into()],
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                parent,
+                status: status as i32,
+                ..SubscribeUpdateSlot::default()
+            })),
+            created_at: None,
+        };
+
+        assert!(tracker.observe(&mut update));
+    }
+
+    fn ready(tracker: &mut Tracker) -> Vec<Out> {
+        std::iter::from_fn(|| tracker.next_ready())
+            .map(|r| match r {
+                Ready::Update(u, _) => {
+                    Out::Update(u.update_oneof.as_ref().and_then(data_slot).unwrap())
+                },
+                Ready::Rollback(r) => Out::Rollback(r.slot),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn updates_held_until_confirmed() {
+        let mut tracker = Tracker::new(Some(CommitmentLevel::Confirmed), false);
+        status(&mut tracker, 10, Some(9), SlotStatus::SlotProcessed);
+        status(&mut tracker, 11, Some(10), SlotStatus::SlotProcessed);
+        tracker.push(account_update(1, 11, &["accounts"]), None);
+        tracker.push(account_update(1, 10, &["accounts"]), None);
+
+        assert_eq!(ready(&mut tracker), []);
+        assert_eq!(tracker.held(), 2);
+
+        // Confirming a slot confirms its ancestors, released in slot order
+        status(&mut tracker, 11, Some(10), SlotStatus::SlotConfirmed);
+        assert_eq!(ready(&mut tracker), [Out::Update(10), Out::Update(11)]);
+        assert_eq!(tracker.held(), 0);
+
+        tracker.push(account_update(1, 11, &["accounts"]), None);
+        assert_eq!(ready(&mut tracker), [Out::Update(11)]);
+    }
+
+    #[test]
+    fn dead_fork_discards_held_updates() {
+        let progress = Arc::new(SlotProgress::default());
+        let mut tracker = Tracker::new(Some(CommitmentLevel::Confirmed), true);
+        status(&mut tracker, 10, Some(9), SlotStatus::SlotProcessed);
+        status(&mut tracker, 11, Some(10), SlotStatus::SlotProcessed);
+        status(&mut tracker, 12, Some(9), SlotStatus::SlotProcessed);
+
+        for slot in [10, 11, 12] {
+            let update = account_update(1, slot, &["accounts"]);
+            let in_flight = progress.start(&update);
+            tracker.push(update, in_flight);
+        }
+
+        assert_eq!(progress.watermark(), Some(9));
+
+        // Slot 11 descends from the dead slot and dies with it
+        status(&mut tracker, 10, Some(9), SlotStatus::SlotDead);
+        assert_eq!(ready(&mut tracker), []);
+        assert_eq!(tracker.held(), 1);
+        assert_eq!(progress.watermark(), Some(11));
+
+        tracker.push(account_update(1, 11, &["accounts"]), None);
+        status(&mut tracker, 12, Some(9), SlotStatus::SlotConfirmed);
+        assert_eq!(ready(&mut tracker), [Out::Update(12)]);
+    }
+
+    #[test]
+    fn dead_slot_rolls_back_delivered_updates() {
+        let mut tracker = Tracker::new(None, true);
+        status(&mut tracker, 10, Some(9), SlotStatus::SlotProcessed);
+        status(&mut tracker, 11, Some(10), SlotStatus::SlotProcessed);
+        tracker.push(account_update(1, 11, &["accounts"]), None);
+        assert_eq!(ready(&mut tracker), [Out::Update(11)]);
+
+        status(&mut tracker, 10, Some(9), SlotStatus::SlotDead);
+        assert_eq!(ready(&mut tracker), [Out::Rollback(11)]);
+    }
+}
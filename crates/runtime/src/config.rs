@@ -4,6 +4,7 @@ use clap::Args;
 #[cfg(feature = "prometheus")]
 pub use prometheus_impl::*;
 use serde::Deserialize;
+use vixen_core::CommitmentLevel;
 
 /// A helper trait for types that may or may not have a default value,
 /// determined at runtime.
@@ -76,10 +77,28 @@ pub struct BufferConfig {
     /// the number of CPUs.
     #[arg(long, env)]
     pub jobs: Option<usize>,
-    /// The maximum number of concurrent sources to run.
+    /// The maximum number of concurrent sources to run, which also bounds
+    /// the queue of each worker when `order_by` is set.
     /// Defaults to 100.
     #[arg(long, env)]
     pub sources_channel_size: usize,
+    /// Process updates sharing a key in the order they were received.  If
+    /// unset, updates are processed in any order.
+    #[arg(long, env)]
+    pub order_by: Option<OrderBy>,
+    /// Hold each update until its slot reaches the given commitment level,
+    /// dropping updates from slots that die before reaching it.  Only useful
+    /// when subscribed at a lower commitment level, and requires a source
+    /// that reports the status of every slot.
+    #[arg(long, env)]
+    pub hold_until: Option<CommitmentLevel>,
+    /// The number of seconds to wait on shutdown for updates already
+    /// received from the source to be processed before abandoning them.
+    /// Draining is opt-in: if unset, queued updates are abandoned
+    /// immediately, and are only received again on restart if the runtime
+    /// resumes from a checkpoint.
+    #[arg(long, env)]
+    pub drain_timeout: Option<u64>,
 }
 
 impl Default for BufferConfig {
@@ -87,10 +106,31 @@ impl Default for BufferConfig {
         Self {
             jobs: None,
             sources_channel_size: 100,
+            order_by: None,
+            hold_until: None,
+            drain_timeout: None,
         }
     }
 }
 
+/// The key used to order updates when processing them.
+///
+/// Updates with the same key are processed one at a time in the order they
+/// were received, while updates with different keys are processed in
+/// parallel.  Updates with no key are processed in any order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OrderBy {
+    /// Order account updates by the account pubkey.
+    Account,
+    /// Order updates by the key given by the
+    /// [`Parser::order_key`](vixen_core::Parser::order_key) of the parser
+    /// receiving them.
+    Parser,
+    /// Order all updates by their slot.
+    Slot,
+}
+
 /// Helper type for blank configuration sections.
 #[derive(
     Default,
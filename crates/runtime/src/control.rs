@@ -0,0 +1,490 @@
+//! A handle for changing the pipelines of a running runtime.
+
+use std::{fmt, sync::Arc};
+
+use tokio::sync::{mpsc, oneshot};
+use vixen_core::{
+    instruction::InstructionUpdate, AccountUpdate, BlockMetaUpdate, BlockUpdate, EntryUpdate,
+    ParserId, Prefilter, SlotUpdate, TransactionStatusUpdate, TransactionUpdate,
+};
+
+use crate::{
+    handler::{BoxPipeline, DynPipeline, LivePipelines, PipelineSet, PipelineSets},
+    instruction::TransactionInstructions,
+    sources::SourceTrait,
+};
+
+/// An error returned when a change to the pipelines of a runtime could not
+/// be applied.
+#[derive(Debug, thiserror::Error)]
+pub enum ControlError {
+    /// A pipeline is already registered with the same parser ID.
+    #[error("A pipeline with ID {0:?} is already registered")]
+    Collision(String),
+    /// No pipeline is registered with the given parser ID.
+    #[error("No pipeline with ID {0:?} is registered")]
+    NotFound(String),
+    /// The runtime has stopped.
+    #[error("The runtime is not running")]
+    Stopped,
+    /// The source failed to update its subscription.  The pipelines are
+    /// left unchanged.
+    #[error("Error updating source subscription")]
+    Source(#[source] Box<crate::Error>),
+}
+
+#[derive(Debug)]
+enum NewPipeline {
+    Account(BoxPipeline<'static, AccountUpdate>),
+    Transaction(BoxPipeline<'static, TransactionUpdate>),
+    Instruction(BoxPipeline<'static, InstructionUpdate>),
+    BlockMeta(BoxPipeline<'static, BlockMetaUpdate>),
+    Slot(BoxPipeline<'static, SlotUpdate>),
+    Block(BoxPipeline<'static, BlockUpdate>),
+    Entry(BoxPipeline<'static, EntryUpdate>),
+    TransactionStatus(BoxPipeline<'static, TransactionStatusUpdate>),
+}
+
+impl NewPipeline {
+    fn id(&self) -> String {
+        match self {
+            Self::Account(p) => p.id(),
+            Self::Transaction(p) => p.id(),
+            Self::Instruction(p) => p.id(),
+            Self::BlockMeta(p) => p.id(),
+            Self::Slot(p) => p.id(),
+            Self::Block(p) => p.id(),
+            Self::Entry(p) => p.id(),
+            Self::TransactionStatus(p) => p.id(),
+        }
+        .into_owned()
+    }
+}
+
+#[derive(Debug)]
+enum Change {
+    Add(NewPipeline),
+    Remove(String),
+    SetPrefilter(String, Option<Box<Prefilter>>),
+}
+
+#[derive(Debug)]
+pub(crate) struct Command(Change, oneshot::Sender<Result<(), ControlError>>);
+
+/// A handle for adding and removing pipelines, and replacing their
+/// prefilters, while the runtime is running.
+///
+/// Every change is applied to the runtime's pipelines and pushed to its
+/// source as an updated subscription.  Changes requested before the runtime
+/// is started are applied once it starts.
+#[derive(Debug, Clone)]
+pub struct RuntimeHandle(mpsc::UnboundedSender<Command>);
+
+macro_rules! add_methods {
+    ($($(#[$meta:meta])* $name:ident($ty:ty) => $var:ident),* $(,)?) => {
+        $(
+            $(#[$meta])*
+            ///
+            /// # Errors
+            /// Returns an error if a pipeline with the same ID is already
+            /// registered, or if the subscription of the source could not be
+            /// updated.
+            pub async fn $name<P: DynPipeline<$ty> + Send + Sync + 'static>(
+                &self,
+                pipeline: P,
+            ) -> Result<(), ControlError> {
+                self.send(Change::Add(NewPipeline::$var(Box::new(pipeline)))).await
+            }
+        )*
+    };
+}
+
+impl RuntimeHandle {
+    add_methods! {
+        /// Add a new account pipeline to the runtime.
+        add_account(AccountUpdate) => Account,
+        /// Add a new transaction pipeline to the runtime.
+        add_transaction(TransactionUpdate) => Transaction,
+        /// Add a new instruction pipeline to the runtime.
+        add_instruction(InstructionUpdate) => Instruction,
+        /// Add a new block meta pipeline to the runtime.
+        add_block_meta(BlockMetaUpdate) => BlockMeta,
+        /// Add a new slot pipeline to the runtime.
+        add_slot(SlotUpdate) => Slot,
+        /// Add a new block pipeline to the runtime.
+        add_block(BlockUpdate) => Block,
+        /// Add a new entry pipeline to the runtime.
+        add_entry(EntryUpdate) => Entry,
+        /// Add a new transaction status pipeline to the runtime.
+        add_transaction_status(TransactionStatusUpdate) => TransactionStatus,
+    }
+
+    async fn send(&self, change: Change) -> Result<(), ControlError> {
+        let (tx, rx) = oneshot::channel();
+        self.0
+            .send(Command(change, tx))
+            .map_err(|_| ControlError::Stopped)?;
+
+        rx.await.map_err(|_| ControlError::Stopped)?
+    }
+
+    /// Remove the pipeline with the given parser ID from the runtime.
+    ///
+    /// Updates already received for the pipeline may still be dispatched to
+    /// it after this returns.
+    ///
+    /// # Errors
+    /// Returns an error if no pipeline is registered with the given ID, or if
+    /// the subscription of the source could not be updated.
+    pub async fn remove(&self, id: impl Into<String>) -> Result<(), ControlError> {
+        self.send(Change::Remove(id.into())).await
+    }
+
+    /// Replace the prefilter of the pipeline with the given parser ID, or
+    /// restore the prefilter of its parser if `prefilter` is `None`.
+    ///
+    /// # Errors
+    /// Returns an error if no pipeline is registered with the given ID, or if
+    /// the subscription of the source could not be updated.
+    pub async fn set_prefilter(
+        &self,
+        id: impl Into<String>,
+        prefilter: Option<Prefilter>,
+    ) -> Result<(), ControlError> {
+        self.send(Change::SetPrefilter(id.into(), prefilter.map(Box::new)))
+            .await
+    }
+}
+
+pub(crate) type WrapInstructions = Box<
+    dyn Fn(BoxPipeline<'static, InstructionUpdate>) -> BoxPipeline<'static, TransactionInstructions>
+        + Send
+        + Sync,
+>;
+
+/// The receiving end of a [`RuntimeHandle`].
+pub(crate) struct Control {
+    rx: mpsc::UnboundedReceiver<Command>,
+    tx: mpsc::UnboundedSender<Command>,
+    wrap_instructions: WrapInstructions,
+}
+
+impl fmt::Debug for Control {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Control")
+            .field("rx", &self.rx)
+            .field("tx", &self.tx)
+            .finish_non_exhaustive()
+    }
+}
+
+#[inline]
+fn insert<P>(set: &mut PipelineSet<P>, id: String, pipeline: P) {
+    let prev = set.insert(id, pipeline);
+    debug_assert!(prev.is_none());
+}
+
+impl Control {
+    pub fn new(wrap_instructions: WrapInstructions) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        Self {
+            rx,
+            tx,
+            wrap_instructions,
+        }
+    }
+
+    #[inline]
+    pub fn handle(&self) -> RuntimeHandle { RuntimeHandle(self.tx.clone()) }
+
+    /// Wait for the next requested change.
+    #[inline]
+    pub async fn recv(&mut self) -> Option<Command> { self.rx.recv().await }
+
+    fn apply(&self, pipelines: &mut PipelineSets, change: Change) -> Result<(), ControlError> {
+        match change {
+            Change::Add(p) => {
+                let id = p.id();
+
+                if pipelines.contains(&id) {
+                    return Err(ControlError::Collision(id));
+                }
+
+                match p {
+                    NewPipeline::Account(p) => insert(&mut pipelines.account, id, p),
+                    NewPipeline::Transaction(p) => insert(&mut pipelines.transaction, id, p),
+                    NewPipeline::Instruction(p) => {
+                        insert(&mut pipelines.instruction, id, (self.wrap_instructions)(p));
+                    },
+                    NewPipeline::BlockMeta(p) => insert(&mut pipelines.block_meta, id, p),
+                    NewPipeline::Slot(p) => insert(&mut pipelines.slot, id, p),
+                    NewPipeline::Block(p) => insert(&mut pipelines.block, id, p),
+                    NewPipeline::Entry(p) => insert(&mut pipelines.entry, id, p),
+                    NewPipeline::TransactionStatus(p) => {
+                        insert(&mut pipelines.transaction_status, id, p);
+                    },
+                }
+
+                Ok(())
+            },
+            Change::Remove(id) => {
+                if pipelines.remove(&id) {
+                    Ok(())
+                } else {
+                    Err(ControlError::NotFound(id))
+                }
+            },
+            Change::SetPrefilter(id, prefilter) => {
+                if !pipelines.contains(&id) {
+                    return Err(ControlError::NotFound(id));
+                }
+
+                match prefilter {
+                    Some(p) => pipelines.prefilters.insert(id, *p),
+                    None => pipelines.prefilters.remove(&id),
+                };
+                Ok(())
+            },
+        }
+    }
+
+    /// Apply a requested change to the live pipelines and push the resulting
+    /// filters to the source, reverting the change if the source fails to
+    /// update its subscription.
+    pub async fn run<S: SourceTrait>(
+        &self,
+        Command(change, reply): Command,
+        live: &LivePipelines,
+        source: &S,
+    ) {
+        let mut pipelines = PipelineSets::clone(&live.load());
+        let res = self.apply(&mut pipelines, change);
+
+        let res = match res {
+            Ok(()) => {
+                let filters = pipelines.filters();
+                pipelines.data_slices = filters.account_data_slices().into();
+                pipelines.parser_ids = filters.parser_ids().into();
+                let old = live.store(Arc::new(pipelines));
+
+                match source.update_filters(filters).await {
+                    Ok(()) => Ok(()),
+                    Err(e) => {
+                        live.store(old);
+                        Err(ControlError::Source(Box::new(e)))
+                    },
+                }
+            },
+            Err(e) => Err(e),
+        };
+
+        if let Err(e) = &res {
+            tracing::warn!(err = %crate::Chain(e), "Failed to apply pipeline change");
+        }
+
+        let _ = reply.send(res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::HashSet,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Mutex, PoisonError,
+        },
+    };
+
+    use async_trait::async_trait;
+    use vixen_core::{Filters, Pubkey};
+    use yellowstone_grpc_proto::{geyser::SubscribeUpdate, tonic::Status};
+
+    use super::*;
+    use crate::{
+        handler::{BoxPipeline, LivePipelines},
+        test_util::{pipelines, Recorder, OWNER},
+    };
+
+    #[derive(Debug, clap::Args, serde::Deserialize)]
+    struct NoConfig {}
+
+    /// Records the filters it is updated with, failing every update while
+    /// `fail` is set.
+    #[derive(Debug, Default)]
+    struct FakeSource {
+        updates: Mutex<Vec<Filters>>,
+        fail: AtomicBool,
+    }
+
+    #[async_trait]
+    impl SourceTrait for FakeSource {
+        type Config = NoConfig;
+
+        fn new(_: NoConfig, _: Filters) -> Self { Self::default() }
+
+        async fn connect(
+            &self,
+            _: mpsc::Sender<Result<SubscribeUpdate, Status>>,
+        ) -> Result<(), crate::Error> {
+            Ok(())
+        }
+
+        async fn update_filters(&self, filters: Filters) -> Result<(), crate::Error> {
+            if self.fail.load(Ordering::Relaxed) {
+                return Err(crate::Error::UpdateFilters("unavailable".into()));
+            }
+
+            self.updates
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(filters);
+            Ok(())
+        }
+    }
+
+    impl FakeSource {
+        fn updates(&self) -> usize {
+            self.updates
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .len()
+        }
+
+        fn last_ids(&self) -> Vec<String> {
+            let updates = self.updates.lock().unwrap_or_else(PoisonError::into_inner);
+            updates.last().map(ids).unwrap_or_default()
+        }
+    }
+
+    fn ids(filters: &Filters) -> Vec<String> {
+        let mut ids: Vec<_> = filters.parsers_filters.keys().cloned().collect();
+        ids.sort();
+        ids
+    }
+
+    fn owners(filters: &Filters, id: &str) -> HashSet<Pubkey> {
+        filters.parsers_filters[id]
+            .account
+            .as_ref()
+            .unwrap()
+            .owners
+            .clone()
+    }
+
+    struct Setup {
+        control: Control,
+        live: LivePipelines,
+        source: FakeSource,
+        recorder: Recorder,
+    }
+
+    impl Setup {
+        fn new() -> Self {
+            let recorder = Recorder::default();
+            let wrap_instructions: WrapInstructions = Box::new(
+                |_: BoxPipeline<'static, InstructionUpdate>| -> BoxPipeline<'static, TransactionInstructions> {
+                    unreachable!("no instruction pipelines are added")
+                },
+            );
+
+            Self {
+                control: Control::new(wrap_instructions),
+                live: LivePipelines::new(pipelines([recorder.pipeline("a")])),
+                source: FakeSource::default(),
+                recorder,
+            }
+        }
+
+        fn add(&self, id: &'static str) -> Change {
+            Change::Add(NewPipeline::Account(self.recorder.pipeline(id)))
+        }
+
+        async fn run(&self, change: Change) -> Result<(), ControlError> {
+            let (tx, rx) = oneshot::channel();
+            self.control
+                .run(Command(change, tx), &self.live, &self.source)
+                .await;
+
+            rx.await.unwrap()
+        }
+
+        fn live_ids(&self) -> Vec<String> { ids(&self.live.load().filters()) }
+    }
+
+    #[tokio::test]
+    async fn add_and_remove_pipelines() {
+        let s = Setup::new();
+
+        s.run(s.add("b")).await.unwrap();
+        assert_eq!(s.live_ids(), ["a", "b"]);
+        assert_eq!(s.source.last_ids(), ["a", "b"]);
+
+        s.run(Change::Remove("a".into())).await.unwrap();
+        assert_eq!(s.live_ids(), ["b"]);
+        assert_eq!(s.source.last_ids(), ["b"]);
+
+        let err = s.run(Change::Remove("a".into())).await.unwrap_err();
+        assert!(matches!(err, ControlError::NotFound(id) if id == "a"));
+        assert_eq!(s.source.updates(), 2);
+    }
+
+    #[tokio::test]
+    async fn adding_existing_id_collides() {
+        let s = Setup::new();
+
+        let err = s.run(s.add("a")).await.unwrap_err();
+        assert!(matches!(err, ControlError::Collision(id) if id == "a"));
+        assert_eq!(s.live_ids(), ["a"]);
+        assert_eq!(s.source.updates(), 0);
+    }
+
+    #[tokio::test]
+    async fn set_prefilter_replaces_and_restores() {
+        let s = Setup::new();
+        let prefilter = Prefilter::builder()
+            .account_owners([[1; 32]])
+            .build()
+            .unwrap();
+
+        s.run(Change::SetPrefilter("a".into(), Some(Box::new(prefilter))))
+            .await
+            .unwrap();
+        let replaced = HashSet::from([Pubkey::from([1; 32])]);
+        assert_eq!(owners(&s.live.load().filters(), "a"), replaced);
+        assert_eq!(owners(&s.source.updates.lock().unwrap()[0], "a"), replaced);
+
+        s.run(Change::SetPrefilter("a".into(), None)).await.unwrap();
+        let parser = HashSet::from([Pubkey::from(OWNER)]);
+        assert_eq!(owners(&s.live.load().filters(), "a"), parser);
+        assert_eq!(owners(&s.source.updates.lock().unwrap()[1], "a"), parser);
+
+        let err = s
+            .run(Change::SetPrefilter("b".into(), None))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ControlError::NotFound(id) if id == "b"));
+        assert_eq!(s.source.updates(), 2);
+    }
+
+    #[tokio::test]
+    async fn source_error_reverts_change() {
+        let s = Setup::new();
+        let before = s.live.load();
+        s.source.fail.store(true, Ordering::Relaxed);
+
+        let err = s.run(s.add("b")).await.unwrap_err();
+        assert!(matches!(err, ControlError::Source(_)));
+        assert!(Arc::ptr_eq(&s.live.load(), &before));
+
+        let err = s.run(Change::Remove("a".into())).await.unwrap_err();
+        assert!(matches!(err, ControlError::Source(_)));
+        assert_eq!(s.live_ids(), ["a"]);
+
+        // The same change succeeds once the source recovers
+        s.source.fail.store(false, Ordering::Relaxed);
+        s.run(s.add("b")).await.unwrap();
+        assert_eq!(s.live_ids(), ["a", "b"]);
+    }
+}
@@ -0,0 +1,461 @@
+//! A dead-letter queue for updates that fail to parse or be handled, and
+//! replay of the stored updates through the pipelines of a runtime.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use vixen_core::DataSlice;
+use yellowstone_grpc_proto::{
+    geyser::SubscribeUpdate,
+    prost::{DecodeError, Message},
+};
+
+use crate::{
+    buffer::Prepared,
+    error_context::ErrorKind,
+    handler::PipelineSets,
+    metrics::{Counters, Instrumenter},
+};
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+/// A parse or handler error recorded in a [`DeadLetter`].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DeadLetterError {
+    /// Whether the parser or a handler failed.
+    pub kind: ErrorKind,
+    /// The message of the error followed by those of its sources.
+    pub chain: Vec<String>,
+}
+
+impl DeadLetterError {
+    pub(crate) fn new(kind: ErrorKind, error: &(dyn std::error::Error + 'static)) -> Self {
+        Self {
+            kind,
+            chain: std::iter::successors(Some(error), |e| e.source())
+                .map(ToString::to_string)
+                .collect(),
+        }
+    }
+}
+
+/// An update a pipeline failed to process, along with the errors it raised.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct DeadLetter {
+    /// The ID of the parser of the failed pipeline.
+    pub parser_id: String,
+    /// The protobuf-encoded `SubscribeUpdate` the pipeline failed on.
+    #[serde(with = "base64_bytes")]
+    pub update: Vec<u8>,
+    /// The data slices the account data of the update was requested with,
+    /// or empty if it holds the full account data.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub data_slices: Vec<DataSlice>,
+    /// The errors raised by the pipeline.
+    pub errors: Vec<DeadLetterError>,
+}
+
+impl DeadLetter {
+    /// Decode the stored update.
+    ///
+    /// # Errors
+    /// Returns an error if the stored bytes are not a valid `SubscribeUpdate`.
+    #[inline]
+    pub fn decode_update(&self) -> Result<SubscribeUpdate, DecodeError> {
+        SubscribeUpdate::decode(&*self.update)
+    }
+}
+
+mod base64_bytes {
+    use base64::{engine::general_purpose::STANDARD, Engine};
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], ser: S) -> Result<S::Ok, S::Error> {
+        ser.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(de: D) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(de)?;
+        STANDARD.decode(s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// An error raised by a pipeline, recorded to be sent to the dead-letter
+/// sink.
+#[derive(Debug)]
+pub(crate) struct Failure {
+    pub parser_id: String,
+    pub error: DeadLetterError,
+}
+
+/// Group the given failures into one letter per failed pipeline, each
+/// holding the update with its filters set to the ID of the pipeline.
+pub(crate) fn letters(
+    failures: Vec<Failure>,
+    mut update: SubscribeUpdate,
+    data_slices: &[DataSlice],
+) -> Vec<DeadLetter> {
+    let mut letters: Vec<DeadLetter> = vec![];
+
+    for Failure { parser_id, error } in failures {
+        if let Some(letter) = letters.iter_mut().find(|l| l.parser_id == parser_id) {
+            letter.errors.push(error);
+            continue;
+        }
+
+        update.filters = vec![parser_id.clone()];
+        letters.push(DeadLetter {
+            parser_id,
+            update: update.encode_to_vec(),
+            data_slices: data_slices.to_vec(),
+            errors: vec![error],
+        });
+    }
+
+    letters
+}
+
+/// A destination for updates that fail to parse or be handled.
+///
+/// Sinks are called on the worker processing the failed update once all of
+/// its pipelines have finished, and any error they return is logged.
+#[async_trait]
+pub trait DeadLetterSink: fmt::Debug + Send + Sync {
+    /// Store a dead letter.
+    ///
+    /// # Errors
+    /// Returns an error if the letter could not be stored.
+    async fn store(&self, letter: &DeadLetter) -> Result<(), BoxedError>;
+}
+
+#[async_trait]
+impl<T: DeadLetterSink + ?Sized> DeadLetterSink for Arc<T> {
+    #[inline]
+    async fn store(&self, letter: &DeadLetter) -> Result<(), BoxedError> {
+        T::store(self, letter).await
+    }
+}
+
+/// A dead-letter sink which can read back the letters stored in it, for use
+/// with [`Runtime::replay`](crate::Runtime::replay).
+#[async_trait]
+pub trait DeadLetterStore: DeadLetterSink {
+    /// Load every stored letter along with an ID identifying it in the store.
+    ///
+    /// # Errors
+    /// Returns an error if the stored letters could not be read.
+    async fn load(&self) -> Result<Vec<(String, DeadLetter)>, BoxedError>;
+
+    /// Remove the letter with the given ID from the store.
+    ///
+    /// # Errors
+    /// Returns an error if the letter could not be removed.
+    async fn remove(&self, id: &str) -> Result<(), BoxedError>;
+}
+
+/// A dead-letter store keeping each letter as a TOML file in a local
+/// directory.
+#[derive(Debug)]
+pub struct DeadLetterDir {
+    path: PathBuf,
+    next: AtomicU64,
+}
+
+impl DeadLetterDir {
+    const EXTENSION: &'static str = "toml";
+
+    /// Open the directory at the given path, creating it if it does not
+    /// exist.
+    ///
+    /// # Errors
+    /// Returns an error if the directory could not be created.
+    pub fn new(path: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let path = path.into();
+        std::fs::create_dir_all(&path)?;
+
+        Ok(Self {
+            path,
+            next: AtomicU64::new(0),
+        })
+    }
+
+    /// The path of the directory.
+    #[inline]
+    #[must_use]
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Get a unique file name for a new letter, ordered by the time it was
+    /// stored.
+    fn file_name(&self) -> String {
+        let time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let seq = self.next.fetch_add(1, Ordering::Relaxed);
+
+        format!(
+            "{time:020}-{}-{seq}.{}",
+            std::process::id(),
+            Self::EXTENSION
+        )
+    }
+}
+
+#[async_trait]
+impl DeadLetterSink for DeadLetterDir {
+    async fn store(&self, letter: &DeadLetter) -> Result<(), BoxedError> {
+        let name = self.file_name();
+        let tmp = self.path.join(format!(".{name}.tmp"));
+
+        // Written to a hidden file first so a partial letter is never loaded
+        tokio::fs::write(&tmp, toml::to_string(letter)?).await?;
+        tokio::fs::rename(&tmp, self.path.join(name)).await?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl DeadLetterStore for DeadLetterDir {
+    async fn load(&self) -> Result<Vec<(String, DeadLetter)>, BoxedError> {
+        let mut dir = tokio::fs::read_dir(&self.path).await?;
+        let mut letters = vec![];
+
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+
+            if name.starts_with('.') || path.extension().is_none_or(|e| e != Self::EXTENSION) {
+                continue;
+            }
+
+            match toml::from_str(&tokio::fs::read_to_string(&path).await?) {
+                Ok(letter) => letters.push((name.to_owned(), letter)),
+                Err(e) => {
+                    tracing::warn!(err = %crate::Chain(&e), ?path, "Skipping invalid dead letter");
+                },
+            }
+        }
+
+        letters.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(letters)
+    }
+
+    async fn remove(&self, id: &str) -> Result<(), BoxedError> {
+        tokio::fs::remove_file(self.path.join(id)).await?;
+        Ok(())
+    }
+}
+
+/// The outcome of replaying the letters of a dead-letter store.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayReport {
+    /// The number of letters processed successfully and removed from the
+    /// store.
+    pub replayed: usize,
+    /// The number of letters whose pipeline failed again.  Each was replaced
+    /// in the store by a letter recording the new errors.
+    pub failed: usize,
+    /// The number of letters left in the store because their update could
+    /// not be decoded or no pipeline is registered with their parser ID.
+    pub skipped: usize,
+}
+
+pub(crate) async fn replay<M: Instrumenter, D: DeadLetterStore + ?Sized>(
+    pipelines: &PipelineSets,
+    counters: &Counters<M>,
+    store: &D,
+) -> Result<ReplayReport, BoxedError> {
+    let mut report = ReplayReport::default();
+
+    for (id, letter) in store.load().await? {
+        let mut update = match letter.decode_update() {
+            Ok(u) => u,
+            Err(e) => {
+                tracing::warn!(err = %crate::Chain(&e), id, "Skipping undecodable dead letter");
+                report.skipped += 1;
+                continue;
+            },
+        };
+
+        if !pipelines.contains(&letter.parser_id) {
+            tracing::warn!(
+                id,
+                parser = letter.parser_id,
+                "No pipeline registered for dead letter"
+            );
+            report.skipped += 1;
+            continue;
+        }
+
+        update.filters = vec![letter.parser_id];
+
+        // Stored updates are already filtered to the failed parser
+        let pipelines = Arc::new(PipelineSets {
+            data_slices: letter.data_slices.into(),
+            parser_ids: Arc::default(),
+            ..pipelines.clone()
+        });
+
+        let span = tracing::trace_span!("replay_update", id, ?update);
+        let mut prepared = Prepared::new(pipelines, &mut update);
+        let failures = prepared.run(span, &update, counters).await;
+        let data_slices = prepared.restore(&mut update);
+
+        if failures.is_empty() {
+            report.replayed += 1;
+        } else {
+            for letter in letters(failures, update, &data_slices) {
+                store.store(&letter).await?;
+            }
+
+            report.failed += 1;
+        }
+
+        store.remove(&id).await?;
+    }
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::atomic::Ordering};
+
+    use super::*;
+    use crate::{
+        metrics::NullMetrics,
+        test_util::{self, account_update, Recorder},
+    };
+
+    fn failure(parser_id: &str, message: &str) -> Failure {
+        Failure {
+            parser_id: parser_id.into(),
+            error: DeadLetterError::new(ErrorKind::Handler, &*BoxedError::from(message)),
+        }
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("vixen-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&path);
+        path
+    }
+
+    #[test]
+    fn letters_grouped_by_parser() {
+        let slices = [DataSlice {
+            offset: 0,
+            length: 4,
+        }];
+        let letters = letters(
+            vec![
+                failure("a", "one"),
+                failure("b", "two"),
+                failure("a", "three"),
+            ],
+            account_update(1, 5, &["a", "b"]),
+            &slices,
+        );
+
+        assert_eq!(letters.len(), 2);
+
+        for (letter, (id, errors)) in letters.iter().zip([("a", 2), ("b", 1)]) {
+            assert_eq!(letter.parser_id, id);
+            assert_eq!(letter.errors.len(), errors);
+            assert_eq!(letter.data_slices, slices);
+            assert_eq!(letter.decode_update().unwrap().filters, [id]);
+        }
+
+        assert_eq!(letters[0].errors[1].chain, ["three"]);
+    }
+
+    #[tokio::test]
+    async fn dir_round_trip() {
+        let dir = DeadLetterDir::new(temp_dir("dead-letter-dir")).unwrap();
+        let letters = letters(
+            vec![failure("a", "one"), failure("b", "two")],
+            account_update(1, 5, &["a", "b"]),
+            &[],
+        );
+
+        for letter in &letters {
+            dir.store(letter).await.unwrap();
+        }
+
+        // Partially written and unrelated files are ignored
+        std::fs::write(dir.path().join(".partial.toml.tmp"), "").unwrap();
+        std::fs::write(dir.path().join("notes.txt"), "").unwrap();
+
+        let loaded = dir.load().await.unwrap();
+        assert_eq!(
+            loaded.iter().map(|(_, l)| l).collect::<Vec<_>>(),
+            letters.iter().collect::<Vec<_>>(),
+        );
+
+        dir.remove(&loaded[0].0).await.unwrap();
+        let loaded = dir.load().await.unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].1, letters[1]);
+
+        std::fs::remove_dir_all(dir.path()).unwrap();
+    }
+
+    #[tokio::test]
+    async fn replay_fanned_out_account_filter() {
+        let recorder = Recorder::default();
+        recorder.fail.store(true, Ordering::Relaxed);
+        let pipelines = PipelineSets {
+            parser_ids: Arc::new(HashMap::from([
+                ("accounts#0".into(), "accounts".into()),
+                ("accounts#1".into(), "accounts".into()),
+            ])),
+            ..test_util::pipelines([recorder.pipeline("accounts")])
+        };
+        let counters = Counters::new(&NullMetrics);
+        let dir = DeadLetterDir::new(temp_dir("dead-letter-replay")).unwrap();
+
+        // Matching both fanned-out filters fails the pipeline once
+        let mut update = account_update(1, 5, &["accounts#0", "accounts#1"]);
+        let mut prepared = Prepared::new(Arc::new(pipelines.clone()), &mut update);
+        let failures = prepared
+            .run(tracing::Span::none(), &update, &counters)
+            .await;
+        let data_slices = prepared.restore(&mut update);
+
+        for letter in letters(failures, update, &data_slices) {
+            assert_eq!(letter.parser_id, "accounts");
+            dir.store(&letter).await.unwrap();
+        }
+
+        assert_eq!(recorder.seen().len(), 1);
+
+        let report = replay(&pipelines, &counters, &dir).await.unwrap();
+        assert_eq!(report, ReplayReport {
+            failed: 1,
+            ..ReplayReport::default()
+        });
+        assert_eq!(recorder.seen().len(), 2);
+        assert_eq!(dir.load().await.unwrap().len(), 1);
+
+        recorder.fail.store(false, Ordering::Relaxed);
+        let report = replay(&pipelines, &counters, &dir).await.unwrap();
+        assert_eq!(report, ReplayReport {
+            replayed: 1,
+            ..ReplayReport::default()
+        });
+        assert_eq!(recorder.seen().len(), 3);
+        assert!(dir.load().await.unwrap().is_empty());
+
+        std::fs::remove_dir_all(dir.path()).unwrap();
+    }
+}
@@ -0,0 +1,290 @@
+//! Structured context for parse and handler errors, and sinks for reporting
+//! them.
+
+use std::fmt::{self, Write};
+
+use base64::Engine;
+use vixen_core::{
+    instruction::{InstructionPath, InstructionUpdate},
+    AccountUpdate, BlockMetaUpdate, BlockUpdate, EntryUpdate, GetSignature, Pubkey, Signature,
+    SlotUpdate, TransactionStatusUpdate, TransactionUpdate,
+};
+
+use crate::{dead_letter::DeadLetterSink, instruction::TransactionInstructions};
+
+type BoxedError = dyn std::error::Error + Send + Sync + 'static;
+
+/// Whether an error was returned by a parser or by a handler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The parser failed to parse the update.
+    Parse,
+    /// A handler returned an error for the parsed value.
+    Handler,
+}
+
+/// The encoding used for the raw payload snippet of an [`ErrorContext`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PayloadEncoding {
+    /// Lowercase hexadecimal.
+    Hex,
+    /// Standard padded base64.
+    Base64,
+}
+
+/// Configuration for attaching a snippet of the raw payload of the failed
+/// update (account data or instruction data) to its error context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ErrorPayload {
+    /// The encoding of the snippet.
+    pub encoding: PayloadEncoding,
+    /// The maximum number of bytes of the payload to include.
+    pub max_len: usize,
+}
+
+impl ErrorPayload {
+    /// Include up to `max_len` bytes of the payload as hexadecimal.
+    #[inline]
+    #[must_use]
+    pub const fn hex(max_len: usize) -> Self {
+        Self {
+            encoding: PayloadEncoding::Hex,
+            max_len,
+        }
+    }
+
+    /// Include up to `max_len` bytes of the payload as base64.
+    #[inline]
+    #[must_use]
+    pub const fn base64(max_len: usize) -> Self {
+        Self {
+            encoding: PayloadEncoding::Base64,
+            max_len,
+        }
+    }
+
+    fn encode(self, data: &[u8]) -> String {
+        let data = &data[..data.len().min(self.max_len)];
+
+        match self.encoding {
+            PayloadEncoding::Hex => {
+                data.iter()
+                    .fold(String::with_capacity(data.len() * 2), |mut s, b| {
+                        write!(s, "{b:02x}").unwrap_or_else(|_| unreachable!());
+                        s
+                    })
+            },
+            PayloadEncoding::Base64 => base64::engine::general_purpose::STANDARD.encode(data),
+        }
+    }
+}
+
+/// Information about the update that caused a parse or handler error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// The ID of the parser of the failed pipeline.
+    pub parser_id: String,
+    /// The type of update being processed, e.g. `"account"` or
+    /// `"instruction"`.
+    pub update_type: &'static str,
+    /// The slot of the update, if known.
+    pub slot: Option<u64>,
+    /// The signature of the transaction the update belongs to, if any.
+    pub signature: Option<Signature>,
+    /// The path of the failed instruction within its transaction, for
+    /// instruction updates.
+    pub instruction: Option<InstructionPath>,
+    /// The program of the failed instruction, for instruction updates.
+    pub program: Option<Pubkey>,
+    /// A snippet of the raw payload of the update, if enabled with
+    /// [`RuntimeBuilder::error_payload`](crate::builder::RuntimeBuilder::error_payload).
+    pub data: Option<String>,
+    /// The full length in bytes of the raw payload of the update, if it has
+    /// one.
+    pub data_len: Option<usize>,
+}
+
+impl ErrorContext {
+    fn new<T: ErrorSource + ?Sized>(
+        parser_id: &str,
+        value: &T,
+        payload: Option<ErrorPayload>,
+    ) -> Self {
+        let mut ctx = Self {
+            parser_id: parser_id.into(),
+            update_type: T::UPDATE_TYPE,
+            slot: None,
+            signature: None,
+            instruction: None,
+            program: None,
+            data: None,
+            data_len: None,
+        };
+
+        value.fill(&mut ctx);
+
+        if let Some(data) = value.payload() {
+            ctx.data = payload.map(|p| p.encode(data));
+            ctx.data_len = Some(data.len());
+        }
+
+        ctx
+    }
+}
+
+/// A receiver for parse and handler errors raised by the runtime, along with
+/// the context of the update that caused them.
+///
+/// Every error is logged regardless of the sinks registered, so sinks are
+/// intended for forwarding errors elsewhere, such as an alerting system.
+/// Sinks are called inline on the worker processing the update and should
+/// not block.
+pub trait ErrorSink: fmt::Debug + Send + Sync {
+    /// Report an error.
+    fn report(&self, ctx: &ErrorContext, kind: ErrorKind, error: &BoxedError);
+}
+
+impl<T: ErrorSink + ?Sized> ErrorSink for std::sync::Arc<T> {
+    #[inline]
+    fn report(&self, ctx: &ErrorContext, kind: ErrorKind, error: &BoxedError) {
+        T::report(self, ctx, kind, error);
+    }
+}
+
+/// The error sinks, payload configuration and dead-letter sink of a runtime.
+#[derive(Debug, Default)]
+pub struct ErrorReporter {
+    sinks: Vec<Box<dyn ErrorSink>>,
+    payload: Option<ErrorPayload>,
+    dead_letters: Option<Box<dyn DeadLetterSink>>,
+}
+
+impl ErrorReporter {
+    pub(crate) fn add_sink(&mut self, sink: Box<dyn ErrorSink>) { self.sinks.push(sink); }
+
+    pub(crate) fn set_payload(&mut self, payload: Option<ErrorPayload>) { self.payload = payload; }
+
+    pub(crate) fn set_dead_letters(&mut self, sink: Option<Box<dyn DeadLetterSink>>) {
+        self.dead_letters = sink;
+    }
+
+    #[inline]
+    pub(crate) fn dead_letters(&self) -> Option<&dyn DeadLetterSink> {
+        self.dead_letters.as_deref()
+    }
+
+    pub(crate) fn context<T: ErrorSource + ?Sized>(
+        &self,
+        parser_id: &str,
+        value: &T,
+    ) -> ErrorContext {
+        ErrorContext::new(parser_id, value, self.payload)
+    }
+
+    pub(crate) fn report(&self, ctx: &ErrorContext, kind: ErrorKind, error: &BoxedError) {
+        tracing::error!(
+            err = %crate::Chain(error),
+            handler = %ctx.parser_id,
+            r#type = ctx.update_type,
+            slot = ctx.slot,
+            signature = ctx.signature.as_ref().map(tracing::field::display),
+            instruction = ctx.instruction.as_ref().map(tracing::field::display),
+            program = ctx.program.as_ref().map(tracing::field::display),
+            data = ctx.data.as_deref(),
+            data_len = ctx.data_len,
+            "{}",
+            match kind {
+                ErrorKind::Parse => "Parser failed",
+                ErrorKind::Handler => "Handler failed",
+            },
+        );
+
+        for sink in &self.sinks {
+            sink.report(ctx, kind, error);
+        }
+    }
+}
+
+/// An update type that can describe itself in an [`ErrorContext`].
+pub(crate) trait ErrorSource {
+    const UPDATE_TYPE: &'static str;
+
+    fn fill(&self, ctx: &mut ErrorContext);
+
+    #[inline]
+    fn payload(&self) -> Option<&[u8]> { None }
+}
+
+macro_rules! slot_error_source {
+    ($($ty:ty => $name:literal),* $(,)?) => {
+        $(
+            impl ErrorSource for $ty {
+                const UPDATE_TYPE: &'static str = $name;
+
+                #[inline]
+                fn fill(&self, ctx: &mut ErrorContext) { ctx.slot = Some(self.slot); }
+            }
+        )*
+    };
+}
+
+slot_error_source! {
+    BlockMetaUpdate => "block_meta",
+    SlotUpdate => "slot",
+    BlockUpdate => "block",
+    EntryUpdate => "entry",
+}
+
+impl ErrorSource for AccountUpdate {
+    const UPDATE_TYPE: &'static str = "account";
+
+    fn fill(&self, ctx: &mut ErrorContext) {
+        ctx.slot = Some(self.slot);
+        ctx.signature = GetSignature::signature(self);
+    }
+
+    #[inline]
+    fn payload(&self) -> Option<&[u8]> { self.account.as_ref().map(|a| &*a.data) }
+}
+
+impl ErrorSource for TransactionUpdate {
+    const UPDATE_TYPE: &'static str = "transaction";
+
+    fn fill(&self, ctx: &mut ErrorContext) {
+        ctx.slot = Some(self.slot);
+        ctx.signature = GetSignature::signature(self);
+    }
+}
+
+impl ErrorSource for TransactionStatusUpdate {
+    const UPDATE_TYPE: &'static str = "transaction_status";
+
+    fn fill(&self, ctx: &mut ErrorContext) {
+        ctx.slot = Some(self.slot);
+        ctx.signature = GetSignature::signature(self);
+    }
+}
+
+impl ErrorSource for TransactionInstructions {
+    const UPDATE_TYPE: &'static str = "transaction";
+
+    fn fill(&self, ctx: &mut ErrorContext) {
+        ctx.slot = Some(self.slot());
+        ctx.signature = self.signature();
+    }
+}
+
+impl ErrorSource for InstructionUpdate {
+    const UPDATE_TYPE: &'static str = "instruction";
+
+    fn fill(&self, ctx: &mut ErrorContext) {
+        ctx.slot = Some(self.shared.slot);
+        ctx.signature = Some(self.shared.signature);
+        ctx.instruction = Some(self.path);
+        ctx.program = Some(self.program);
+    }
+
+    #[inline]
+    fn payload(&self) -> Option<&[u8]> { Some(&*self.data) }
+}
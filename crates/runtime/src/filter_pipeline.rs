@@ -4,7 +4,7 @@ use std::{borrow::Cow, fmt::Debug};
 
 use futures_util::{Future, StreamExt};
 use smallvec::SmallVec;
-use vixen_core::{GetPrefilter, ParseError, Parser, ParserId, Prefilter, PrefilterBuilder};
+use vixen_core::{GetPrefilter, ParseError, Parser, ParserId, Prefilter, PrefilterBuilder, Pubkey};
 
 use crate::{
     handler::{DynPipeline, PipelineErrors},
@@ -123,4 +123,7 @@ where
     > {
         Box::pin(FilterPipeline::handle_value(self, value))
     }
+
+    #[inline]
+    fn order_key(&self, value: &P::Input) -> Option<Pubkey> { self.parser.order_key(value) }
 }
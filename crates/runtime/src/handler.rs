@@ -1,17 +1,30 @@
 //! Helper types for bundling [Vixen parsers](crate::vixen_core::Parser) and
 //! handler callbacks.
 
-use std::{borrow::Cow, collections::HashMap, pin::Pin};
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    pin::Pin,
+    sync::{Arc, PoisonError, RwLock},
+};
 
 use futures_util::{Future, FutureExt, StreamExt};
 use smallvec::SmallVec;
 use tracing::{warn, Instrument, Span};
 use vixen_core::{
-    AccountUpdate, BlockMetaUpdate, GetPrefilter, ParserId, SlotUpdate, TransactionUpdate,
+    AccountUpdate, BlockMetaUpdate, BlockUpdate, DataSlice, EntryUpdate, GetPrefilter, ParserId,
+    Pubkey, SlotUpdate, TransactionStatusUpdate, TransactionUpdate,
 };
 use yellowstone_vixen_core::{Filters, ParseError, Parser, Prefilter};
 
-use crate::metrics::{Counters, Instrumenter, JobResult, Update};
+use crate::{
+    commitment::{self, RollbackHandlers},
+    dead_letter::Failure,
+    error_context::{ErrorReporter, ErrorSource},
+    instruction::TransactionInstructions,
+    metrics::{Counters, Instrumenter, JobResult, Update},
+    retry::RetryPolicy,
+};
 
 type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
 /// The result returned by a handler.
@@ -30,20 +43,29 @@ impl<T: Handler<U>, U> Handler<U> for &T {
     }
 }
 
-pub(crate) use pipeline_error::Errors as PipelineErrors;
+pub(crate) use pipeline_error::{Errors as PipelineErrors, Handled};
 
 mod pipeline_error {
     use smallvec::SmallVec;
 
     use super::BoxedError;
+    use crate::{
+        dead_letter::{DeadLetterError, Failure},
+        error_context::{ErrorKind, ErrorReporter, ErrorSource},
+    };
 
-    #[derive(Debug, Clone, Copy)]
+    /// Errors which have already been reported, recorded for the dead-letter
+    /// sink.
+    #[derive(Debug)]
     #[must_use]
-    pub struct Handled(());
+    pub struct Handled(Vec<Failure>);
 
     impl Handled {
         #[inline]
-        pub fn as_unit(self) { let Self(()) = self; }
+        pub(crate) fn into_failures(self) -> Vec<Failure> { self.0 }
+
+        #[inline]
+        pub(crate) fn merge(&mut self, other: Self) { self.0.extend(other.0); }
     }
 
     #[derive(Debug)]
@@ -60,17 +82,37 @@ mod pipeline_error {
             Self::Parse(Box::new(e))
         }
 
-        pub fn handle<T>(self, handler: &str) -> Handled {
+        /// Report each error along with the context of the value that caused
+        /// it.  The context is only built if there are errors to report.
+        /// Errors already handled are passed through unchanged.
+        pub(crate) fn handle<T: ErrorSource + ?Sized>(
+            self,
+            handler: &str,
+            value: &T,
+            reporter: &ErrorReporter,
+        ) -> Handled {
+            if let Self::AlreadyHandled(h) = self {
+                return h;
+            }
+
+            let mut ctx = None;
+            let mut failures = vec![];
+
             for e in self {
-                tracing::error!(
-                    err = %crate::Chain(&e),
-                    handler,
-                    r#type = std::any::type_name::<T>(),
-                    "Handler failed",
-                );
+                let ctx = ctx.get_or_insert_with(|| reporter.context(handler, value));
+                let (kind, e) = match e {
+                    Error::Parser(e) => (ErrorKind::Parse, e),
+                    Error::Handler(e) => (ErrorKind::Handler, e),
+                };
+
+                reporter.report(ctx, kind, &*e);
+                failures.push(Failure {
+                    parser_id: handler.into(),
+                    error: DeadLetterError::new(kind, &*e),
+                });
             }
 
-            Handled(())
+            Handled(failures)
         }
     }
 
@@ -82,7 +124,7 @@ mod pipeline_error {
             match self {
                 Errors::Parse(e) => IntoIter::Parse([e].into_iter()),
                 Errors::Handlers(v) => IntoIter::Handlers(v.into_iter()),
-                Errors::AlreadyHandled(Handled(())) => IntoIter::AlreadyHandled,
+                Errors::AlreadyHandled(_) => IntoIter::AlreadyHandled,
             }
         }
     }
@@ -117,13 +159,20 @@ mod pipeline_error {
 
 /// A parser and a set of handlers its output is passed to.
 #[derive(Debug)]
-pub struct Pipeline<P, H>(P, H);
+pub struct Pipeline<P, H>(P, H, Option<RetryPolicy>);
 
 impl<P, H> Pipeline<P, H> {
     /// Create a new pipeline from a parser and a list of handlers.
     #[inline]
     #[must_use]
-    pub fn new(parser: P, handlers: H) -> Self { Self(parser, handlers) }
+    pub fn new(parser: P, handlers: H) -> Self { Self(parser, handlers, None) }
+
+    /// Retry each handler of this pipeline according to the given policy.
+    /// Handlers wrapped in [`Retry`](crate::retry::Retry) are retried by
+    /// their own policy within each attempt of this one.
+    #[inline]
+    #[must_use]
+    pub fn retry(self, policy: RetryPolicy) -> Self { Self(self.0, self.1, Some(policy)) }
 }
 
 impl<P: ParserId, H> ParserId for Pipeline<P, H> {
@@ -156,10 +205,16 @@ where
             Err(ParseError::Other(e)) => return Err(PipelineErrors::Parse(e)),
         };
         let parsed = &parsed;
+        let retry = self.2.as_ref();
 
         let errs = (&self.1)
             .into_iter()
-            .map(|h| async move { h.handle(parsed).await })
+            .map(|h| async move {
+                match retry {
+                    Some(r) => r.run(h, parsed).await,
+                    None => h.handle(parsed).await,
+                }
+            })
             .collect::<futures_util::stream::FuturesUnordered<_>>()
             .filter_map(|r| async move { r.err() })
             .collect::<SmallVec<[_; 1]>>()
@@ -181,6 +236,15 @@ pub trait DynPipeline<T>: std::fmt::Debug + ParserId + GetPrefilter {
         &'h self,
         value: &'h T,
     ) -> Pin<Box<dyn Future<Output = Result<(), PipelineErrors>> + Send + 'h>>;
+
+    /// Get the key used to order the provided value relative to other values
+    /// when the buffer orders updates by parser key.  See
+    /// [`Parser::order_key`].
+    #[inline]
+    fn order_key(&self, value: &T) -> Option<Pubkey> {
+        let _ = value;
+        None
+    }
 }
 
 impl<T> DynPipeline<T> for std::convert::Infallible {
@@ -206,6 +270,9 @@ where
     ) -> Pin<Box<dyn Future<Output = Result<(), PipelineErrors>> + Send + 'h>> {
         Box::pin(Pipeline::handle(self, value))
     }
+
+    #[inline]
+    fn order_key(&self, value: &P::Input) -> Option<Pubkey> { self.0.order_key(value) }
 }
 
 impl<T> ParserId for BoxPipeline<'_, T> {
@@ -225,15 +292,34 @@ impl<T> DynPipeline<T> for BoxPipeline<'_, T> {
     ) -> Pin<Box<dyn Future<Output = Result<(), PipelineErrors>> + Send + 'h>> {
         <dyn DynPipeline<T>>::handle(&**self, value)
     }
+
+    #[inline]
+    fn order_key(&self, value: &T) -> Option<Pubkey> {
+        <dyn DynPipeline<T>>::order_key(&**self, value)
+    }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Default)]
 pub(crate) struct PipelineSets {
     pub account: PipelineSet<BoxPipeline<'static, AccountUpdate>>,
     pub transaction: PipelineSet<BoxPipeline<'static, TransactionUpdate>>,
-    pub instruction: PipelineSet<BoxPipeline<'static, TransactionUpdate>>,
+    pub instruction: PipelineSet<BoxPipeline<'static, TransactionInstructions>>,
     pub block_meta: PipelineSet<BoxPipeline<'static, BlockMetaUpdate>>,
     pub slot: PipelineSet<BoxPipeline<'static, SlotUpdate>>,
+    pub block: PipelineSet<BoxPipeline<'static, BlockUpdate>>,
+    pub entry: PipelineSet<BoxPipeline<'static, EntryUpdate>>,
+    pub transaction_status: PipelineSet<BoxPipeline<'static, TransactionStatusUpdate>>,
+    pub errors: Arc<ErrorReporter>,
+    pub rollback: Arc<RollbackHandlers>,
+    /// Whether to subscribe to every slot status for commitment tracking
+    pub track_slots: bool,
+    /// Prefilters replacing those of the pipelines with the given IDs
+    pub prefilters: HashMap<String, Prefilter>,
+    /// The account data slices requested by the current filters
+    pub data_slices: Arc<[DataSlice]>,
+    /// The parser IDs of the fanned-out account filters of the current
+    /// filters, keyed by filter name
+    pub parser_ids: Arc<HashMap<String, String>>,
 }
 
 impl PipelineSets {
@@ -246,13 +332,92 @@ impl PipelineSets {
                 .chain(self.instruction.filters())
                 .chain(self.block_meta.filters())
                 .chain(self.slot.filters())
+                .chain(self.block.filters())
+                .chain(self.entry.filters())
+                .chain(self.transaction_status.filters())
+                .map(|(k, v)| {
+                    let v = self.prefilters.get(&k).cloned().unwrap_or(v);
+                    (k, v)
+                })
+                .chain(self.track_slots.then(|| {
+                    (
+                        commitment::STATUS_FILTER.into(),
+                        commitment::status_prefilter(),
+                    )
+                }))
                 .collect(),
         )
     }
+
+    /// Returns true if a pipeline of any update type is registered with the
+    /// given ID.
+    #[must_use]
+    pub fn contains(&self, id: &str) -> bool {
+        self.account.contains(id)
+            || self.transaction.contains(id)
+            || self.instruction.contains(id)
+            || self.block_meta.contains(id)
+            || self.slot.contains(id)
+            || self.block.contains(id)
+            || self.entry.contains(id)
+            || self.transaction_status.contains(id)
+    }
+
+    /// Remove the pipeline registered with the given ID, returning false if
+    /// there was none.
+    pub fn remove(&mut self, id: &str) -> bool {
+        self.prefilters.remove(id);
+
+        // Non-short-circuiting to clear the ID from every set
+        self.account.remove(id)
+            | self.transaction.remove(id)
+            | self.instruction.remove(id)
+            | self.block_meta.remove(id)
+            | self.slot.remove(id)
+            | self.block.remove(id)
+            | self.entry.remove(id)
+            | self.transaction_status.remove(id)
+    }
 }
 
+/// The pipelines of a running runtime.  Changes are made to a copy of the
+/// current pipelines which then replaces it, so updates already being
+/// processed are unaffected.
 #[derive(Debug)]
-pub(crate) struct PipelineSet<P>(HashMap<String, P>);
+pub(crate) struct LivePipelines(RwLock<Arc<PipelineSets>>);
+
+impl LivePipelines {
+    #[inline]
+    #[must_use]
+    pub fn new(pipelines: PipelineSets) -> Self { Self(RwLock::new(Arc::new(pipelines))) }
+
+    #[inline]
+    #[must_use]
+    pub fn load(&self) -> Arc<PipelineSets> {
+        Arc::clone(&self.0.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    #[inline]
+    pub fn store(&self, pipelines: Arc<PipelineSets>) -> Arc<PipelineSets> {
+        std::mem::replace(
+            &mut *self.0.write().unwrap_or_else(PoisonError::into_inner),
+            pipelines,
+        )
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct PipelineSet<P>(HashMap<String, Arc<P>>);
+
+impl<P> Clone for PipelineSet<P> {
+    #[inline]
+    fn clone(&self) -> Self { Self(self.0.clone()) }
+}
+
+impl<P> Default for PipelineSet<P> {
+    #[inline]
+    fn default() -> Self { Self::new() }
+}
 
 impl<P> PipelineSet<P> {
     #[inline]
@@ -264,7 +429,16 @@ impl<P> PipelineSet<P> {
     pub fn new() -> Self { Self(HashMap::new()) }
 
     #[inline]
-    pub fn insert(&mut self, key: String, value: P) -> Option<P> { self.0.insert(key, value) }
+    pub fn insert(&mut self, key: String, value: P) -> Option<Arc<P>> {
+        self.0.insert(key, Arc::new(value))
+    }
+
+    #[inline]
+    #[must_use]
+    pub fn contains(&self, key: &str) -> bool { self.0.contains_key(key) }
+
+    #[inline]
+    pub fn remove(&mut self, key: &str) -> bool { self.0.remove(key).is_some() }
 }
 
 impl<P: GetPrefilter> PipelineSet<P> {
@@ -281,11 +455,35 @@ impl<P: GetPrefilter> PipelineSet<P> {
 
 impl<P> PipelineSet<P> {
     pub(crate) fn get_handlers<I>(&self, it: I) -> Pipelines<P, I> { Pipelines(self, it) }
+
+    /// Returns true if any of the given filter names matches a pipeline in
+    /// this set.
+    pub(crate) fn matches_any<I: IntoIterator>(&self, it: I) -> bool
+    where I::Item: AsRef<str> {
+        it.into_iter().any(|f| self.0.contains_key(f.as_ref()))
+    }
+
+    /// Get the order key of the given value from the pipelines matching the
+    /// given filter names.  If several pipelines give a key, the first one is
+    /// used.
+    pub(crate) fn order_key<T, I: IntoIterator>(&self, it: I, value: &T) -> Option<Pubkey>
+    where
+        I::Item: AsRef<str>,
+        P: DynPipeline<T>,
+    {
+        it.into_iter()
+            .filter_map(|f| self.0.get(f.as_ref()))
+            .find_map(|p| p.order_key(value))
+    }
 }
 
 impl<P: ParserId> FromIterator<P> for PipelineSet<P> {
     fn from_iter<I: IntoIterator<Item = P>>(iter: I) -> Self {
-        Self(iter.into_iter().map(|i| (i.id().into_owned(), i)).collect())
+        Self(
+            iter.into_iter()
+                .map(|i| (i.id().into_owned(), Arc::new(i)))
+                .collect(),
+        )
     }
 }
 
@@ -297,24 +495,28 @@ where I::Item: AsRef<str> + Send + 'm
 {
     fn get_pipelines(self) -> impl Iterator<Item = (I::Item, &'m H)> {
         let Self(pipelines, it) = self;
-        it.into_iter().filter_map(|f| {
+
+        it.into_iter().filter_map(move |f| {
             let filter = f.as_ref();
-            let pipeline = pipelines.0.get(filter);
+            let pipeline = pipelines.0.get(filter).map(|p| &**p);
 
             if pipeline.is_none() {
                 warn!(filter, "No pipeline matched filter on incoming update");
             }
 
-            pipeline.map(|p| (f, p))
+            Some((f, pipeline?))
         })
     }
 
-    pub fn run<'h, T: Update, M: Instrumenter>(
+    /// Pass the value to every matched pipeline, returning the errors they
+    /// raised.
+    pub fn run<'h, T: Update + ErrorSource + Sync, M: Instrumenter>(
         self,
         span: Span,
         value: &'h T,
         metrics: &'h Counters<M>,
-    ) -> impl Future<Output = ()> + Send + 'h
+        errors: &'h ErrorReporter,
+    ) -> impl Future<Output = Vec<Failure>> + Send + 'h
     where
         H: DynPipeline<T>,
         'm: 'h,
@@ -327,12 +529,12 @@ where I::Item: AsRef<str> + Send + 'm
                         metrics.inc_processed(T::TYPE, r);
                     }
                     match r {
-                        Ok(()) => (),
-                        Err(v) => v.handle::<T>(f.as_ref()).as_unit(),
+                        Ok(()) => vec![],
+                        Err(v) => v.handle(f.as_ref(), value, errors).into_failures(),
                     }
                 })
                 .in_current_span()
         }))
-        .map(move |v| v.into_iter().collect())
+        .map(move |v| v.into_iter().flatten().collect())
     }
 }
@@ -1,19 +1,82 @@
 //! Helper types for parsing and dispatching instructions from transaction
 //! updates.
 
-use std::fmt::{self, Debug};
+use std::{
+    fmt::{self, Debug},
+    sync::Arc,
+};
 
-use vixen_core::{instruction::InstructionUpdate, GetPrefilter, ParserId, TransactionUpdate};
+use vixen_core::{
+    instruction::{InstructionUpdate, ParseError},
+    GetPrefilter, GetSignature, ParserId, Pubkey, Signature, TransactionUpdate,
+};
 
 use crate::{
-    handler::{BoxPipeline, DynPipeline, PipelineErrors},
+    error_context::ErrorReporter,
+    handler::{BoxPipeline, DynPipeline, Handled, PipelineErrors},
     metrics::{InstructionCounters, Instrumenter, JobResult},
 };
 
+/// The instructions of a transaction update, decomposed once per update and
+/// shared by every instruction pipeline it is dispatched to.
+#[derive(Debug)]
+pub struct TransactionInstructions {
+    slot: u64,
+    signature: Option<Signature>,
+    ixs: Result<Vec<InstructionUpdate>, ParseError>,
+}
+
+impl TransactionInstructions {
+    /// Parse the instructions of a transaction update, which they borrow
+    /// their data from.
+    #[inline]
+    #[must_use]
+    pub fn parse(txn: &Arc<TransactionUpdate>) -> Self {
+        Self {
+            slot: txn.slot,
+            signature: txn.signature(),
+            ixs: InstructionUpdate::parse_from_shared_txn(txn),
+        }
+    }
+
+    /// The slot of the transaction.
+    #[inline]
+    #[must_use]
+    pub fn slot(&self) -> u64 { self.slot }
+
+    /// The signature of the transaction, if it is well-formed.
+    #[inline]
+    #[must_use]
+    pub fn signature(&self) -> Option<Signature> { self.signature }
+
+    /// Get the outer instructions of the transaction, or the error
+    /// encountered while parsing them.
+    ///
+    /// # Errors
+    /// Returns an error if the transaction update could not be parsed.
+    #[inline]
+    pub fn outer(&self) -> Result<&[InstructionUpdate], ParseError> {
+        self.ixs.as_deref().map_err(|e| *e)
+    }
+
+    fn visit_all(&self) -> Result<impl Iterator<Item = &InstructionUpdate>, PipelineErrors> {
+        let ixs = self.outer().map_err(PipelineErrors::parse)?;
+
+        Ok(ixs.iter().flat_map(|i| i.visit_all()).map(|(_, i)| i))
+    }
+
+    /// Get the first order key given by the pipeline for any instruction of
+    /// the transaction.
+    fn order_key(&self, pipe: &BoxPipeline<'static, InstructionUpdate>) -> Option<Pubkey> {
+        self.visit_all().ok()?.find_map(|i| pipe.order_key(i))
+    }
+}
+
 /// A pipeline for dispatching instruction updates given a transaction update.
 pub struct InstructionPipeline<M: Instrumenter>(
     Box<[BoxPipeline<'static, InstructionUpdate>]>,
     InstructionCounters<M>,
+    Arc<ErrorReporter>,
 );
 
 impl<M: Instrumenter> fmt::Debug for InstructionPipeline<M> {
@@ -21,6 +84,7 @@ impl<M: Instrumenter> fmt::Debug for InstructionPipeline<M> {
         f.debug_tuple("InstructionPipeline")
             .field(&self.0)
             .field(&self.1)
+            .field(&self.2)
             .finish()
     }
 }
@@ -31,6 +95,7 @@ impl<M: Instrumenter> InstructionPipeline<M> {
     pub fn new(
         pipelines: Vec<BoxPipeline<'static, InstructionUpdate>>,
         instrumenter: &M,
+        errors: Arc<ErrorReporter>,
     ) -> Option<Self> {
         if pipelines.is_empty() {
             return None;
@@ -39,29 +104,32 @@ impl<M: Instrumenter> InstructionPipeline<M> {
         Some(Self(
             pipelines.into_boxed_slice(),
             InstructionCounters::new(instrumenter),
+            errors,
         ))
     }
 
-    /// Handle a transaction update by dispatching its instruction updates to
+    /// Handle the instructions of a transaction update by dispatching them to
     /// the sub-pipelines.
     ///
     /// # Errors
     /// Returns an error if any of the sub-pipelines return an error.
-    pub async fn handle(&self, txn: &TransactionUpdate) -> Result<(), PipelineErrors> {
-        let mut err = None;
-        let ixs = InstructionUpdate::parse_from_txn(txn).map_err(PipelineErrors::parse)?;
+    pub async fn handle(&self, ixs: &TransactionInstructions) -> Result<(), PipelineErrors> {
+        let mut err: Option<Handled> = None;
         // TODO: how should sub-pipeline delegation be handled for instruction trees?
-        for insn in ixs.iter().flat_map(|i| i.visit_all()) {
+        for insn in ixs.visit_all()? {
             for pipe in &*self.0 {
                 // TODO: run these concurrently?
                 let res = pipe.handle(insn).await;
                 if let Some(r) = JobResult::from_pipeline(&res) {
                     self.1.inc_processed(r);
                 }
-                match res {
-                    Ok(()) => (),
-                    Err(PipelineErrors::AlreadyHandled(h)) => h.as_unit(),
-                    Err(e) => err = Some(e.handle::<InstructionUpdate>(&pipe.id())),
+                if let Err(e) = res {
+                    let handled = e.handle(&pipe.id(), insn, &self.2);
+
+                    match &mut err {
+                        Some(h) => h.merge(handled),
+                        None => err = Some(handled),
+                    }
                 }
             }
         }
@@ -84,53 +152,73 @@ impl<M: Instrumenter> GetPrefilter for InstructionPipeline<M> {
     }
 }
 
-impl<M: Instrumenter> DynPipeline<TransactionUpdate> for InstructionPipeline<M> {
+impl<M: Instrumenter> DynPipeline<TransactionInstructions> for InstructionPipeline<M> {
     fn handle<'h>(
         &'h self,
-        value: &'h TransactionUpdate,
+        value: &'h TransactionInstructions,
     ) -> std::pin::Pin<Box<dyn futures_util::Future<Output = Result<(), PipelineErrors>> + Send + 'h>>
     {
         Box::pin(InstructionPipeline::handle(self, value))
     }
+
+    fn order_key(&self, value: &TransactionInstructions) -> Option<Pubkey> {
+        self.0.iter().find_map(|p| value.order_key(p))
+    }
 }
 
 /// A pipeline for dispatching instruction updates for a single parser given a transaction update.
 pub struct SingleInstructionPipeline<M: Instrumenter>(
     BoxPipeline<'static, InstructionUpdate>,
-    InstructionCounters<M>,
+    Arc<InstructionCounters<M>>,
+    Arc<ErrorReporter>,
 );
 
 impl<M: Instrumenter> SingleInstructionPipeline<M> {
     /// Create a new instruction pipeline from a single sub-pipeline.
     #[must_use]
-    pub fn new(pipeline: BoxPipeline<'static, InstructionUpdate>, instrumenter: &M) -> Self {
-        Self(pipeline, InstructionCounters::new(instrumenter))
+    pub fn new(
+        pipeline: BoxPipeline<'static, InstructionUpdate>,
+        instrumenter: &M,
+        errors: Arc<ErrorReporter>,
+    ) -> Self {
+        Self::with_counters(
+            pipeline,
+            Arc::new(InstructionCounters::new(instrumenter)),
+            errors,
+        )
+    }
+
+    /// Create a new instruction pipeline sharing its counters with other
+    /// instruction pipelines.
+    #[inline]
+    #[must_use]
+    pub(crate) fn with_counters(
+        pipeline: BoxPipeline<'static, InstructionUpdate>,
+        counters: Arc<InstructionCounters<M>>,
+        errors: Arc<ErrorReporter>,
+    ) -> Self {
+        Self(pipeline, counters, errors)
     }
 
-    /// Handle a transaction update by dispatching its instruction updates to
+    /// Handle the instructions of a transaction update by dispatching them to
     /// its sub-pipeline.
     ///
     /// # Errors
     /// Returns an error if the inner pipeline fails.
-    pub async fn handle(&self, txn: &TransactionUpdate) -> Result<(), PipelineErrors> {
-        let ixs = InstructionUpdate::parse_from_txn(txn).map_err(PipelineErrors::parse)?;
+    pub async fn handle(&self, ixs: &TransactionInstructions) -> Result<(), PipelineErrors> {
         let pipe = &self.0;
 
-        for insn in ixs.iter().flat_map(|i| i.visit_all()) {
+        for insn in ixs.visit_all()? {
             let res = pipe.handle(insn).await;
 
             if let Some(r) = JobResult::from_pipeline(&res) {
                 self.1.inc_processed(r);
             }
 
-            match res {
-                Ok(()) => (),
-                Err(PipelineErrors::AlreadyHandled(h)) => h.as_unit(),
-                Err(e) => {
-                    let handled = e.handle::<InstructionUpdate>(&pipe.id());
+            if let Err(e) = res {
+                let handled = e.handle(&pipe.id(), insn, &self.2);
 
-                    return Err(PipelineErrors::AlreadyHandled(handled));
-                },
+                return Err(PipelineErrors::AlreadyHandled(handled));
             }
         }
 
@@ -151,16 +239,22 @@ impl<M: Instrumenter> Debug for SingleInstructionPipeline<M> {
         f.debug_tuple("SingleInstructionPipeline")
             .field(&self.0)
             .field(&self.1)
+            .field(&self.2)
             .finish()
     }
 }
 
-impl<M: Instrumenter> DynPipeline<TransactionUpdate> for SingleInstructionPipeline<M> {
+impl<M: Instrumenter> DynPipeline<TransactionInstructions> for SingleInstructionPipeline<M> {
     fn handle<'h>(
         &'h self,
-        value: &'h TransactionUpdate,
+        value: &'h TransactionInstructions,
     ) -> std::pin::Pin<Box<dyn futures_util::Future<Output = Result<(), PipelineErrors>> + Send + 'h>>
     {
         Box::pin(SingleInstructionPipeline::handle(self, value))
     }
+
+    #[inline]
+    fn order_key(&self, value: &TransactionInstructions) -> Option<Pubkey> {
+        value.order_key(&self.0)
+    }
 }
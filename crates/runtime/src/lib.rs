@@ -12,9 +12,12 @@
 //! Vixen provides a simple API for requesting, parsing, and consuming data
 //! from Yellowstone.
 
-use std::marker::PhantomData;
+use std::{marker::PhantomData, sync::Arc};
 
+use checkpoint::{CheckpointStore, Checkpointer};
 use config::BufferConfig;
+use control::RuntimeHandle;
+use dead_letter::{DeadLetterStore, ReplayReport};
 use futures_util::future::OptionFuture;
 use metrics::{Counters, Exporter, MetricsFactory, NullMetrics};
 use stop::{StopCode, StopTx};
@@ -31,11 +34,19 @@ pub use vixen_core::bs58;
 
 mod buffer;
 pub mod builder;
+pub mod checkpoint;
+pub mod commitment;
 pub mod config;
+pub mod control;
+pub mod dead_letter;
+pub mod error_context;
 pub mod handler;
 pub mod instruction;
 pub mod metrics;
+pub mod retry;
 pub mod sources;
+#[cfg(test)]
+mod test_util;
 
 /// Utility functions for the Vixen runtime.
 pub mod util;
@@ -76,6 +87,17 @@ pub enum Error {
     /// An error occurring when a datasource is not configured correctly.
     #[error("Yellowstone stream config error")]
     ConfigError,
+    /// An error occurring when a source does not support changing its
+    /// filters while connected.
+    #[error("Source does not support updating its filters")]
+    UpdateFiltersUnsupported,
+    /// An error occurring when a source fails to update its subscription.
+    #[error("Error updating source subscription")]
+    UpdateFilters(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
+    /// An error occurring when the checkpoint store fails to load the last
+    /// checkpoint.
+    #[error("Error loading checkpoint")]
+    Checkpoint(#[source] Box<dyn std::error::Error + Send + Sync + 'static>),
 }
 
 /// The main runtime for Vixen.
@@ -85,6 +107,8 @@ pub struct Runtime<M: MetricsFactory, S: SourceTrait> {
     source: S::Config,
     pipelines: handler::PipelineSets,
     counters: Counters<M::Instrumenter>,
+    control: control::Control,
+    checkpoint: Option<Box<dyn CheckpointStore>>,
     exporter: Option<M::Exporter>,
     _source: PhantomData<S>,
 }
@@ -94,6 +118,36 @@ impl<S: SourceTrait> Runtime<NullMetrics, S> {
     pub fn builder() -> RuntimeBuilder<S> { RuntimeBuilder::<S>::default() }
 }
 impl<M: MetricsFactory, S: SourceTrait> Runtime<M, S> {
+    /// Get a handle for changing the pipelines of this runtime while it is
+    /// running.
+    #[inline]
+    #[must_use]
+    pub fn handle(&self) -> RuntimeHandle { self.control.handle() }
+
+    /// Pass the updates stored in a dead-letter store through the pipelines
+    /// of this runtime, e.g. after shipping a fix for a failing parser.
+    ///
+    /// Each letter is passed only to the pipeline that failed on it, and is
+    /// removed from the store once processed without errors.  Letters whose
+    /// pipeline fails again are replaced by letters recording the new
+    /// errors.  The source is not connected, so this is intended to be
+    /// called before the runtime is started.
+    ///
+    /// Replay is at-least-once: a letter is only removed after its pipeline
+    /// has run, so a letter whose removal fails or is interrupted is passed
+    /// to its pipeline again by the next replay.  Handlers receiving replayed
+    /// updates should be idempotent.
+    ///
+    /// # Errors
+    /// Returns an error if the store fails to load, store or remove a
+    /// letter.
+    pub async fn replay<D: DeadLetterStore + ?Sized>(
+        &self,
+        store: &D,
+    ) -> Result<ReplayReport, Box<dyn std::error::Error + Send + Sync + 'static>> {
+        dead_letter::replay(&self.pipelines, &self.counters, store).await
+    }
+
     /// Create a new Tokio runtime and run the Vixen runtime within it,
     /// terminating the current process if the runtime crashes.
     ///
@@ -152,6 +206,13 @@ impl<M: MetricsFactory, S: SourceTrait> Runtime<M, S> {
     /// can use the [`Self::run`] method instead, which takes care of creating
     /// a tokio Runtime for you.
     ///
+    /// When the runtime stops, on a shutdown signal or an error, it stops
+    /// receiving from the source, then waits up to
+    /// [`BufferConfig::drain_timeout`](config::BufferConfig::drain_timeout)
+    /// for the updates already received and the running handlers to finish
+    /// before saving its final checkpoint.  Draining is opt-in: by default,
+    /// updates not yet processed are abandoned immediately.
+    ///
     /// # Example
     ///
     /// ```ignore
@@ -187,6 +248,7 @@ impl<M: MetricsFactory, S: SourceTrait> Runtime<M, S> {
     /// # Errors
     /// This function returns an error if the runtime crashes.
     #[tracing::instrument("Runtime::run", skip(self))]
+    #[allow(clippy::too_many_lines)]
     pub async fn try_run_async(self) -> Result<(), Box<Error>> {
         enum StopType<S, X> {
             Signal(S),
@@ -197,12 +259,34 @@ impl<M: MetricsFactory, S: SourceTrait> Runtime<M, S> {
         let (tx, updates_rx) =
             mpsc::channel::<Result<SubscribeUpdate, Status>>(self.buffer.sources_channel_size);
 
-        let filters = self.pipelines.filters();
+        let mut pipelines = self.pipelines;
+        let filters = pipelines.filters();
+        pipelines.data_slices = filters.account_data_slices().into();
+        pipelines.parser_ids = filters.parser_ids().into();
+        let pipelines = Arc::new(handler::LivePipelines::new(pipelines));
+
+        let checkpointer = match self.checkpoint {
+            Some(store) => Some(
+                Checkpointer::load(store)
+                    .await
+                    .map_err(|e| Box::new(Error::Checkpoint(e)))?,
+            ),
+            None => None,
+        };
+
+        let mut source = S::new(self.source, filters);
 
-        let source = S::new(self.source, filters);
+        if let Some(c) = &checkpointer {
+            source.set_checkpoint(c.checkpoint());
+        }
+
+        let source = Arc::new(source);
 
-        tokio::spawn(async move {
-            let _ = source.connect(tx).await;
+        let source_task = tokio::spawn({
+            let source = Arc::clone(&source);
+            async move {
+                let _ = source.connect(tx).await;
+            }
         });
 
         let (stop_exporter, rx) = stop::channel();
@@ -251,19 +335,40 @@ impl<M: MetricsFactory, S: SourceTrait> Runtime<M, S> {
                 .map_err(Into::into);
         }
 
-        let mut buffer =
-            buffer::Buffer::run_yellowstone(self.buffer, updates_rx, self.pipelines, self.counters);
-
-        let stop_ty = tokio::select! {
-            s = signal => StopType::Signal(s),
-            b = buffer.wait_for_stop() => StopType::Buffer(b),
-            Some(x) = &mut exporter => StopType::Exporter(x),
+        let mut buffer = buffer::Buffer::run_yellowstone(
+            self.buffer,
+            updates_rx,
+            Arc::clone(&pipelines),
+            checkpointer.as_ref().map(Checkpointer::progress),
+            self.counters,
+        );
+
+        let mut control = self.control;
+        let mut signal = std::pin::pin!(signal);
+        let mut save_checkpoint = tokio::time::interval(checkpoint::SAVE_INTERVAL);
+
+        let stop_ty = loop {
+            break tokio::select! {
+                s = &mut signal => StopType::Signal(s),
+                b = buffer.wait_for_stop() => StopType::Buffer(b),
+                Some(x) = &mut exporter => StopType::Exporter(x),
+                Some(c) = control.recv() => {
+                    control.run(c, &pipelines, &*source).await;
+                    continue;
+                },
+                _ = save_checkpoint.tick(), if checkpointer.is_some() => {
+                    if let Some(c) = &checkpointer {
+                        c.save().await;
+                    }
+                    continue;
+                },
+            };
         };
 
         let should_stop_buffer = !matches!(stop_ty, StopType::Buffer(..));
         let should_stop_exporter = !matches!(stop_ty, StopType::Exporter(..));
 
-        match stop_ty {
+        let result = match stop_ty {
             StopType::Signal(Ok(Some(s))) => {
                 tracing::warn!("{s:?} received, shutting down...");
                 Ok(())
@@ -280,17 +385,27 @@ impl<M: MetricsFactory, S: SourceTrait> Runtime<M, S> {
             },
             StopType::Exporter(Ok(Err(e))) => Err(Error::MetricsExporter(e.into())),
             StopType::Exporter(Err(e)) => Err(Error::MetricsExporter(e.into())),
-        }?;
+        };
+
+        // Stop receiving before draining, so the source does not keep
+        // reconnecting or pushing updates while the buffer winds down
+        source_task.abort();
 
+        // Updates are drained and the checkpoint saved even if the runtime
+        // is stopping because of an error
         if should_stop_buffer {
             Self::stop_buffer(buffer).await;
         }
 
+        if let Some(c) = &checkpointer {
+            c.save().await;
+        }
+
         if should_stop_exporter {
             Self::stop_exporter(exporter, stop_exporter).await;
         }
 
-        Ok(())
+        result.map_err(Box::new)
     }
 
     async fn stop_buffer(buffer: buffer::Buffer) {
@@ -6,6 +6,7 @@ use std::{
     error::Error,
     fmt,
     future::Future,
+    sync::Arc,
 };
 
 #[cfg(feature = "opentelemetry")]
@@ -342,6 +343,10 @@ impl Update for vixen_core::TransactionUpdate {
     const TYPE: UpdateType = UpdateType::Transaction;
 }
 
+impl Update for crate::instruction::TransactionInstructions {
+    const TYPE: UpdateType = UpdateType::Transaction;
+}
+
 impl Update for vixen_core::BlockMetaUpdate {
     const TYPE: UpdateType = UpdateType::BlockMeta;
 }
@@ -350,6 +355,18 @@ impl Update for vixen_core::SlotUpdate {
     const TYPE: UpdateType = UpdateType::Slot;
 }
 
+impl Update for vixen_core::BlockUpdate {
+    const TYPE: UpdateType = UpdateType::Block;
+}
+
+impl Update for vixen_core::EntryUpdate {
+    const TYPE: UpdateType = UpdateType::Entry;
+}
+
+impl Update for vixen_core::TransactionStatusUpdate {
+    const TYPE: UpdateType = UpdateType::TransactionStatus;
+}
+
 /// Tuple of `(singular, plural)`
 #[derive(Clone, Copy)]
 struct Noun(&'static str, &'static str);
@@ -369,12 +386,15 @@ pub(crate) enum UpdateType {
     Transaction,
     BlockMeta,
     Slot,
+    Block,
+    Entry,
+    TransactionStatus,
 }
 
 impl UpdateType {
     pub fn get(update: Option<&UpdateOneof>) -> Option<Self> {
         match update {
-            Some(UpdateOneof::Account(vixen_core::AccountUpdate { .. })) => Some(Self::Account),
+            Some(UpdateOneof::Account(_)) => Some(Self::Account),
             Some(UpdateOneof::Transaction(vixen_core::TransactionUpdate { .. })) => {
                 Some(Self::Transaction)
             },
@@ -382,6 +402,11 @@ impl UpdateType {
                 Some(Self::BlockMeta)
             },
             Some(UpdateOneof::Slot(vixen_core::SlotUpdate { .. })) => Some(Self::Slot),
+            Some(UpdateOneof::Block(vixen_core::BlockUpdate { .. })) => Some(Self::Block),
+            Some(UpdateOneof::Entry(vixen_core::EntryUpdate { .. })) => Some(Self::Entry),
+            Some(UpdateOneof::TransactionStatus(vixen_core::TransactionStatusUpdate {
+                ..
+            })) => Some(Self::TransactionStatus),
             _ => None,
         }
     }
@@ -392,6 +417,9 @@ impl UpdateType {
             UpdateType::Transaction => Noun("transaction", "transactions"),
             UpdateType::BlockMeta => Noun("block_meta", "block_metas"),
             UpdateType::Slot => Noun("slot", "slots"),
+            UpdateType::Block => Noun("block", "blocks"),
+            UpdateType::Entry => Noun("entry", "entries"),
+            UpdateType::TransactionStatus => Noun("transaction_status", "transaction_statuses"),
         }
     }
 }
@@ -401,6 +429,9 @@ struct UpdateCounters<B: Instrumenter> {
     transaction: B::Counter,
     block_meta: B::Counter,
     slot: B::Counter,
+    block: B::Counter,
+    entry: B::Counter,
+    transaction_status: B::Counter,
 }
 
 impl<B: Instrumenter> UpdateCounters<B> {
@@ -411,6 +442,9 @@ impl<B: Instrumenter> UpdateCounters<B> {
             transaction: f(UpdateType::Transaction),
             block_meta: f(UpdateType::BlockMeta),
             slot: f(UpdateType::Slot),
+            block: f(UpdateType::Block),
+            entry: f(UpdateType::Entry),
+            transaction_status: f(UpdateType::TransactionStatus),
         }
     }
 
@@ -420,6 +454,9 @@ impl<B: Instrumenter> UpdateCounters<B> {
             UpdateType::Transaction => &self.transaction,
             UpdateType::BlockMeta => &self.block_meta,
             UpdateType::Slot => &self.slot,
+            UpdateType::Block => &self.block,
+            UpdateType::Entry => &self.entry,
+            UpdateType::TransactionStatus => &self.transaction_status,
         }
     }
 }
@@ -473,10 +510,47 @@ impl<C> ResultCounters<C> {
     }
 }
 
+/// Counters for handler retries and timeouts, which are incremented from
+/// within handler calls rather than by the runtime directly.
+trait RetryMetrics: Send + Sync {
+    fn inc_retries(&self);
+
+    fn inc_timeouts(&self);
+}
+
+struct RetryCounters<C> {
+    retries: C,
+    timeouts: C,
+}
+
+impl<C: Counter> RetryMetrics for RetryCounters<C> {
+    #[inline]
+    fn inc_retries(&self) { self.retries.inc(); }
+
+    #[inline]
+    fn inc_timeouts(&self) { self.timeouts.inc(); }
+}
+
+tokio::task_local! {
+    /// The retry counters of the runtime processing the current update.
+    static RETRY_METRICS: Arc<dyn RetryMetrics>;
+}
+
+/// Record a handler retry with the runtime processing the current update, if
+/// any.
+#[inline]
+pub(crate) fn inc_handler_retries() { RETRY_METRICS.try_with(|m| m.inc_retries()).ok(); }
+
+/// Record a handler timeout with the runtime processing the current update,
+/// if any.
+#[inline]
+pub(crate) fn inc_handler_timeouts() { RETRY_METRICS.try_with(|m| m.inc_timeouts()).ok(); }
+
 // TODO: this should probably use datapoint attributes rather than weird name formatting
 pub(crate) struct Counters<B: Instrumenter> {
     updates_recvd: UpdateCounters<B>,
     update_results: ResultCounters<UpdateCounters<B>>,
+    retries: Arc<RetryCounters<B::Counter>>,
 }
 
 impl<B: Instrumenter> Counters<B> {
@@ -491,8 +565,25 @@ impl<B: Instrumenter> Counters<B> {
             update_results: ResultCounters::new(|c, d| {
                 UpdateCounters::new(|n| metrics.make_counter(c(n), d(n)))
             }),
+            retries: Arc::new(RetryCounters {
+                retries: metrics.make_counter(
+                    "handler_retries",
+                    "Number of handler calls retried after an error",
+                ),
+                timeouts: metrics.make_counter(
+                    "handler_timeouts",
+                    "Number of handler calls that exceeded their timeout or deadline",
+                ),
+            }),
         }
     }
+
+    /// Run the given future with handler retries and timeouts recorded to
+    /// these counters.
+    pub fn scope<F: Future>(&self, fut: F) -> impl Future<Output = F::Output> {
+        let retries: Arc<dyn RetryMetrics> = Arc::<RetryCounters<_>>::clone(&self.retries);
+        RETRY_METRICS.scope(retries, fut)
+    }
 }
 
 impl<B: Instrumenter> fmt::Debug for Counters<B> {
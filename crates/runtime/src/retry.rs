@@ -0,0 +1,346 @@
+//! Retry and timeout policies for handlers.
+
+use std::{
+    fmt,
+    hash::{BuildHasher, RandomState},
+    sync::Arc,
+    time::Duration,
+};
+
+use futures_util::Future;
+use tokio::time::Instant;
+
+use crate::{metrics, Handler, HandlerResult};
+
+type ErrorRef<'a> = &'a (dyn std::error::Error + Send + Sync + 'static);
+type Retryable = Arc<dyn Fn(ErrorRef) -> bool + Send + Sync>;
+
+/// The error returned for a handler attempt that did not finish within the
+/// timeout or deadline of its [`RetryPolicy`].
+#[derive(Debug, Clone, Copy, thiserror::Error)]
+#[error("Handler timed out after {0:?}")]
+pub struct TimedOut(pub Duration);
+
+/// A policy for retrying failed handler calls and bounding how long they may
+/// run.
+///
+/// The default policy calls a handler once with no timeout.  Between
+/// attempts the policy waits for an exponentially growing backoff, with
+/// jitter reducing each wait by up to half to spread out retries of
+/// concurrent updates.
+///
+/// Policies can be applied to every handler of a pipeline with
+/// [`Pipeline::retry`](crate::Pipeline::retry), or to a single handler with
+/// [`Retry`].
+#[derive(Clone)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+    jitter: bool,
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    retryable: Option<Retryable>,
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("max_backoff", &self.max_backoff)
+            .field("jitter", &self.jitter)
+            .field("timeout", &self.timeout)
+            .field("deadline", &self.deadline)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: true,
+            timeout: None,
+            deadline: None,
+            retryable: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Create a policy calling a handler up to `max_attempts` times, with a
+    /// backoff starting at 100ms and capped at 10s.
+    #[must_use]
+    pub fn new(max_attempts: u32) -> Self { Self::default().max_attempts(max_attempts) }
+
+    /// Set the maximum number of times a handler is called for a value,
+    /// including the first attempt.
+    #[must_use]
+    pub fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set the wait before the first retry, which doubles after every retry
+    /// up to `max`.
+    #[must_use]
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Enable or disable jitter on the backoff.  Enabled by default.
+    #[must_use]
+    pub fn jitter(mut self, jitter: bool) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Fail each attempt that runs longer than `timeout` with a [`TimedOut`]
+    /// error.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Limit the total time spent on a value across all attempts and
+    /// backoffs.  An attempt still running at the deadline fails with a
+    /// [`TimedOut`] error, and no retry is started that would begin after
+    /// it.
+    #[must_use]
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Only retry errors for which the given predicate returns true.  By
+    /// default every error, including [`TimedOut`], is retried.
+    #[must_use]
+    pub fn retry_if<F: Fn(ErrorRef) -> bool + Send + Sync + 'static>(mut self, f: F) -> Self {
+        self.retryable = Some(Arc::new(f));
+        self
+    }
+
+    fn is_retryable(&self, err: ErrorRef) -> bool { self.retryable.as_ref().is_none_or(|f| f(err)) }
+
+    /// Get the wait before the next retry given the current backoff.
+    fn delay(&self, backoff: Duration) -> Duration {
+        if !self.jitter {
+            return backoff;
+        }
+
+        let nanos = u64::try_from(backoff.as_nanos()).unwrap_or(u64::MAX);
+        let half = nanos / 2;
+        let rand = RandomState::new().hash_one(nanos);
+
+        Duration::from_nanos(half + rand % (nanos - half).saturating_add(1))
+    }
+
+    /// Call the given handler until it succeeds, returns an error that should
+    /// not be retried, or runs out of attempts or time.
+    pub(crate) async fn run<T, H: Handler<T>>(&self, handler: H, value: &T) -> HandlerResult<()> {
+        let start = Instant::now();
+        let deadline = self.deadline.map(|d| start + d);
+        let mut backoff = self.backoff;
+        let mut attempts = 1;
+
+        loop {
+            let limit = match (self.timeout, deadline) {
+                (Some(t), Some(d)) => Some(t.min(d.saturating_duration_since(Instant::now()))),
+                (t, d) => t.or_else(|| d.map(|d| d.saturating_duration_since(Instant::now()))),
+            };
+
+            let res = match limit {
+                Some(limit) => tokio::time::timeout(limit, handler.handle(value))
+                    .await
+                    .unwrap_or_else(|_| {
+                        metrics::inc_handler_timeouts();
+                        Err(Box::new(TimedOut(limit)))
+                    }),
+                None => handler.handle(value).await,
+            };
+
+            let Err(err) = res else { return Ok(()) };
+
+            if attempts >= self.max_attempts || !self.is_retryable(&*err) {
+                return Err(err);
+            }
+
+            let delay = self.delay(backoff);
+
+            if deadline.is_some_and(|d| Instant::now() + delay >= d) {
+                return Err(err);
+            }
+
+            tracing::debug!(err = %crate::Chain(&*err), attempts, ?delay, "Retrying handler");
+            metrics::inc_handler_retries();
+            tokio::time::sleep(delay).await;
+
+            backoff = backoff.saturating_mul(2).min(self.max_backoff);
+            attempts += 1;
+        }
+    }
+}
+
+/// A handler retried according to a [`RetryPolicy`].
+#[derive(Debug, Clone)]
+pub struct Retry<H>(H, RetryPolicy);
+
+impl<H> Retry<H> {
+    /// Wrap a handler with the given retry policy.
+    #[inline]
+    #[must_use]
+    pub fn new(handler: H, policy: RetryPolicy) -> Self { Self(handler, policy) }
+}
+
+impl<T: Sync, H: Handler<T> + Sync> Handler<T> for Retry<H> {
+    fn handle(&self, value: &T) -> impl Future<Output = HandlerResult<()>> + Send {
+        self.1.run(&self.0, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    /// Fails its first `failures` calls with the given message, after
+    /// sleeping for `sleep` on every call.
+    #[derive(Debug, Default)]
+    struct Flaky {
+        calls: AtomicU32,
+        failures: u32,
+        message: &'static str,
+        sleep: Duration,
+    }
+
+    impl Flaky {
+        fn calls(&self) -> u32 { self.calls.load(Ordering::Relaxed) }
+    }
+
+    impl Handler<()> for Flaky {
+        async fn handle(&self, (): &()) -> HandlerResult<()> {
+            let calls = self.calls.fetch_add(1, Ordering::Relaxed) + 1;
+            tokio::time::sleep(self.sleep).await;
+
+            if calls > self.failures {
+                Ok(())
+            } else {
+                Err(self.message.into())
+            }
+        }
+    }
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy::new(max_attempts)
+            .jitter(false)
+            .backoff(Duration::from_millis(100), Duration::from_secs(10))
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_until_success() {
+        let handler = Flaky {
+            failures: 2,
+            ..Flaky::default()
+        };
+        let start = Instant::now();
+
+        policy(5).run(&handler, &()).await.unwrap();
+        assert_eq!(handler.calls(), 3);
+        assert_eq!(start.elapsed(), Duration::from_millis(100 + 200));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_after_max_attempts() {
+        let handler = Flaky {
+            failures: u32::MAX,
+            message: "failed",
+            ..Flaky::default()
+        };
+
+        let err = policy(3).run(&handler, &()).await.unwrap_err();
+        assert_eq!(err.to_string(), "failed");
+        assert_eq!(handler.calls(), 3);
+
+        let handler = Flaky {
+            failures: u32::MAX,
+            ..Flaky::default()
+        };
+        RetryPolicy::default().run(&handler, &()).await.unwrap_err();
+        assert_eq!(handler.calls(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retry_if_stops_on_permanent_errors() {
+        let policy = policy(5).retry_if(|e| e.to_string() != "permanent");
+        let handler = Flaky {
+            failures: u32::MAX,
+            message: "permanent",
+            ..Flaky::default()
+        };
+
+        policy.run(&handler, &()).await.unwrap_err();
+        assert_eq!(handler.calls(), 1);
+
+        let handler = Flaky {
+            failures: 1,
+            message: "transient",
+            ..Flaky::default()
+        };
+
+        policy.run(&handler, &()).await.unwrap();
+        assert_eq!(handler.calls(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn deadline_cuts_off_retries() {
+        let handler = Flaky {
+            failures: u32::MAX,
+            ..Flaky::default()
+        };
+        let start = Instant::now();
+
+        // Attempts start at 0ms, 100ms and 300ms, and the next would start
+        // at 700ms
+        policy(10)
+            .deadline(Duration::from_millis(350))
+            .run(&handler, &())
+            .await
+            .unwrap_err();
+        assert_eq!(handler.calls(), 3);
+        assert_eq!(start.elapsed(), Duration::from_millis(300));
+
+        let handler = Flaky {
+            sleep: Duration::from_secs(1),
+            ..Flaky::default()
+        };
+        let start = Instant::now();
+
+        let err = policy(10)
+            .timeout(Duration::from_millis(400))
+            .deadline(Duration::from_millis(250))
+            .run(&handler, &())
+            .await
+            .unwrap_err();
+        assert!(err.downcast_ref::<TimedOut>().is_some());
+        assert_eq!(handler.calls(), 1);
+        assert_eq!(start.elapsed(), Duration::from_millis(250));
+    }
+
+    #[test]
+    fn jitter_shortens_backoff_by_up_to_half() {
+        let policy = RetryPolicy::default();
+        let backoff = Duration::from_millis(100);
+
+        for _ in 0..100 {
+            let delay = policy.delay(backoff);
+            assert!(delay >= backoff / 2 && delay <= backoff, "{delay:?}");
+        }
+    }
+}
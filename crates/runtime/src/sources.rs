@@ -8,6 +8,8 @@ use tokio::sync::mpsc::Sender;
 use vixen_core::Filters;
 use yellowstone_grpc_proto::{geyser::SubscribeUpdate, tonic::Status};
 
+use crate::checkpoint::Checkpoint;
+
 /// # SourceTrait
 ///
 /// This trait defines the behavior for data sources that can be used to connect to it and
@@ -65,8 +67,15 @@ use yellowstone_grpc_proto::{geyser::SubscribeUpdate, tonic::Status};
 ///
 /// * `connect` - Establishes connection to the data source and streams updates
 /// * `new` - Creates a new instance of the source with the given configuration and filters
+///
+/// # Provided Methods
+///
+/// * `update_filters` - Replaces the filters of a connected source, used when
+///   pipelines are changed through a [`RuntimeHandle`](crate::control::RuntimeHandle)
+/// * `set_checkpoint` - Passes the checkpoint of the runtime to the source,
+///   used to resume streaming from the last handled slot
 #[async_trait]
-pub trait SourceTrait: std::fmt::Debug + Send + 'static {
+pub trait SourceTrait: std::fmt::Debug + Send + Sync + 'static {
     /// The configuration for the source.
     type Config: serde::de::DeserializeOwned + clap::Args + std::fmt::Debug;
 
@@ -78,4 +87,30 @@ pub trait SourceTrait: std::fmt::Debug + Send + 'static {
         &self,
         tx: Sender<Result<SubscribeUpdate, Status>>,
     ) -> Result<(), crate::Error>;
+
+    /// Replace the filters of the connected source with `filters`, which
+    /// hold the filters of every pipeline of the runtime.  Updates matching
+    /// the new filters should be sent to the channel passed to
+    /// [`connect`](Self::connect).
+    ///
+    /// The default implementation does not support changing filters.
+    ///
+    /// # Errors
+    /// This function returns an error if the source cannot update its
+    /// subscription.
+    async fn update_filters(&self, filters: Filters) -> Result<(), crate::Error> {
+        let _ = filters;
+        Err(crate::Error::UpdateFiltersUnsupported)
+    }
+
+    /// Receive the checkpoint of the runtime before the source is connected.
+    /// When subscribing, including after a reconnect, sources able to start
+    /// from a given slot should start from
+    /// [`Checkpoint::resume_slot`](crate::checkpoint::Checkpoint::resume_slot)
+    /// if it is set.
+    ///
+    /// This is only called if the runtime was built with a
+    /// [`checkpoint_store`](crate::builder::RuntimeBuilder::checkpoint_store).
+    /// The default implementation ignores the checkpoint.
+    fn set_checkpoint(&mut self, checkpoint: Checkpoint) { let _ = checkpoint; }
 }
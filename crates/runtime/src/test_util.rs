@@ -0,0 +1,154 @@
+//! Parsers, handlers and updates shared by the tests of the runtime.
+
+use std::{
+    borrow::Cow,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use tokio::sync::Semaphore;
+use vixen_core::{AccountUpdate, ParseError, ParseResult, Parser, Prefilter, Pubkey};
+use yellowstone_grpc_proto::geyser::{
+    subscribe_update::UpdateOneof, SubscribeUpdate, SubscribeUpdateAccount,
+    SubscribeUpdateAccountInfo,
+};
+
+use crate::{
+    commitment::Rollback,
+    handler::{BoxPipeline, PipelineSets},
+    Handler, HandlerResult, Pipeline,
+};
+
+/// The owner of the accounts of [`account_update`].
+pub const OWNER: [u8; 32] = [0xff; 32];
+
+/// An account update as seen by a [`Recorder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Seen {
+    pub pubkey: Pubkey,
+    pub slot: u64,
+}
+
+/// Parses account updates into their address and slot, keyed by address.
+#[derive(Debug)]
+pub struct AccountParser(pub &'static str);
+
+impl Parser for AccountParser {
+    type Input = AccountUpdate;
+    type Output = Seen;
+
+    fn id(&self) -> Cow<str> { self.0.into() }
+
+    fn prefilter(&self) -> Prefilter {
+        Prefilter::builder()
+            .account_owners([Pubkey::from(OWNER)])
+            .build()
+            .unwrap()
+    }
+
+    fn order_key(&self, value: &AccountUpdate) -> Option<Pubkey> {
+        value.account.as_ref()?.pubkey.as_slice().try_into().ok()
+    }
+
+    async fn parse(&self, value: &AccountUpdate) -> ParseResult<Seen> {
+        let pubkey = value
+            .account
+            .as_ref()
+            .and_then(|a| a.pubkey.as_slice().try_into().ok())
+            .ok_or(ParseError::Filtered)?;
+
+        Ok(Seen {
+            pubkey,
+            slot: value.slot,
+        })
+    }
+}
+
+/// Records every value it handles, optionally waiting for a permit from a
+/// gate first and failing afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct Recorder {
+    pub seen: Arc<Mutex<Vec<Seen>>>,
+    pub fail: Arc<AtomicBool>,
+    pub gate: Option<Arc<Semaphore>>,
+}
+
+impl Recorder {
+    pub fn seen(&self) -> Vec<Seen> {
+        self.seen
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn pipeline(&self, id: &'static str) -> BoxPipeline<'static, AccountUpdate> {
+        Box::new(Pipeline::new(AccountParser(id), [self.clone()]))
+    }
+}
+
+impl Handler<Seen> for Recorder {
+    async fn handle(&self, value: &Seen) -> HandlerResult<()> {
+        if let Some(gate) = &self.gate {
+            gate.acquire().await?.forget();
+        }
+
+        // Yield a varying number of times so concurrent jobs interleave
+        for _ in 0..value.slot % 3 {
+            tokio::task::yield_now().await;
+        }
+
+        self.seen
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(*value);
+
+        if self.fail.load(Ordering::Relaxed) {
+            return Err("recorder failed".into());
+        }
+
+        Ok(())
+    }
+}
+
+/// Records rollbacks as an update of the default address at the rolled back
+/// slot.
+impl Handler<Rollback> for Recorder {
+    async fn handle(&self, value: &Rollback) -> HandlerResult<()> {
+        Handler::handle(self, &Seen {
+            pubkey: Pubkey::default(),
+            slot: value.slot,
+        })
+        .await
+    }
+}
+
+/// Create a set of pipelines holding the given account pipelines.
+pub fn pipelines(
+    account: impl IntoIterator<Item = BoxPipeline<'static, AccountUpdate>>,
+) -> PipelineSets {
+    PipelineSets {
+        account: account.into_iter().collect(),
+        ..PipelineSets::default()
+    }
+}
+
+/// Create an update for the account with every byte of its address set to
+/// `key`, matching the given filters.
+pub fn account_update(key: u8, slot: u64, filters: &[&str]) -> SubscribeUpdate {
+    SubscribeUpdate {
+        filters: filters.iter().map(ToString::to_string).collect(),
+        update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+            account: Some(SubscribeUpdateAccountInfo {
+                pubkey: vec![key; 32],
+                owner: OWNER.to_vec(),
+                data: vec![key; 8],
+                ..SubscribeUpdateAccountInfo::default()
+            }),
+            slot,
+            ..SubscribeUpdateAccount::default()
+        })),
+        created_at: None,
+    }
+}
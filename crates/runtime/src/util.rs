@@ -51,9 +51,9 @@ impl From<PrivateString> for String {
 }
 
 #[derive(Debug, Clone, Copy)]
-pub(crate) struct Chain<'a, E>(pub &'a E);
+pub(crate) struct Chain<'a, E: ?Sized>(pub &'a E);
 
-impl<E: Error> fmt::Display for Chain<'_, E> {
+impl<E: Error + ?Sized> fmt::Display for Chain<'_, E> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         use fmt::Write;
 
@@ -106,7 +106,7 @@ impl<E: Error> fmt::Display for Chain<'_, E> {
             }
         }
 
-        let Self(err) = *self;
+        let Self(err) = self;
 
         write!(f, "{err}")?;
 
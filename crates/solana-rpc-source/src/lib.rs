@@ -1,4 +1,4 @@
-use std::time::Duration;
+use std::{sync::Arc, time::Duration};
 
 use async_trait::async_trait;
 use solana_account_decoder_client_types::UiAccountEncoding;
@@ -17,7 +17,7 @@ use yellowstone_grpc_proto::{
     tonic::Status,
 };
 use yellowstone_vixen::{sources::SourceTrait, CommitmentLevel, Error as VixenError};
-use yellowstone_vixen_core::Filters;
+use yellowstone_vixen_core::{DataSlice, Filters};
 
 /// A `Source` implementation for the Solana Accounts RPC API.
 #[derive(Debug)]
@@ -68,6 +68,10 @@ impl SourceTrait for SolanaAccountsRpcSource {
 
         let mut tasks_set = JoinSet::new();
 
+        // Slice account data the same way Yellowstone would so the runtime
+        // receives it in the layout it requested
+        let data_slices: Arc<[DataSlice]> = filters.account_data_slices().into();
+
         for (filter_id, prefilter) in &filters.parsers_filters {
             if let Some(account_prefilter) = &prefilter.account {
                 for program in &account_prefilter.owners {
@@ -75,6 +79,7 @@ impl SourceTrait for SolanaAccountsRpcSource {
                     let config = config.clone();
                     let tx = tx.clone();
                     let filter_id = filter_id.clone();
+                    let data_slices = Arc::clone(&data_slices);
 
                     let client = RpcClient::new_with_timeout_and_commitment(
                         config.endpoint.clone(),
@@ -132,7 +137,11 @@ impl SourceTrait for SolanaAccountsRpcSource {
                                         owner: account.owner.as_array().to_vec(),
                                         executable: account.executable,
                                         rent_epoch: account.rent_epoch,
-                                        data: account.data,
+                                        data: if data_slices.is_empty() {
+                                            account.data
+                                        } else {
+                                            DataSlice::slice(&data_slices, &account.data)
+                                        },
                                         write_version: 0,
                                         txn_signature: None,
                                     }),
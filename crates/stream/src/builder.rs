@@ -180,9 +180,15 @@ impl<'a, M: MetricsFactory + 'a, S: SourceTrait> StreamBuilder<'a, S, M> {
             transaction,
             instruction,
             block_meta,
+            errors,
+            rollback,
+            checkpoint,
             metrics,
             extra: StreamKind(desc_sets, channels),
             slot,
+            block,
+            entry,
+            transaction_status,
             _source,
         } = self.0;
         let () = err?;
@@ -203,9 +209,15 @@ impl<'a, M: MetricsFactory + 'a, S: SourceTrait> StreamBuilder<'a, S, M> {
             transaction,
             instruction,
             block_meta,
+            errors,
+            rollback,
+            checkpoint,
             metrics,
             extra: RuntimeKind,
             slot,
+            block,
+            entry,
+            transaction_status,
             _source,
         }
         .try_build(runtime_cfg)?;
@@ -1,15 +1,34 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt,
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+};
 
 use async_trait::async_trait;
-use futures_util::StreamExt;
-use tokio::{sync::mpsc::Sender, task::JoinSet};
+use futures_util::{future::BoxFuture, Sink, SinkExt, Stream, StreamExt};
+use tokio::{
+    sync::{
+        mpsc::{Sender, WeakSender},
+        watch, Mutex,
+    },
+    task::JoinSet,
+};
 use yellowstone_grpc_client::GeyserGrpcClient;
 use yellowstone_grpc_proto::{
-    geyser::{SubscribeRequest, SubscribeUpdate},
-    tonic::{transport::ClientTlsConfig, Status},
+    geyser::{
+        SubscribeRequest, SubscribeRequestAccountsDataSlice, SubscribeRequestFilterSlots,
+        SubscribeUpdate,
+    },
+    tonic::{transport::ClientTlsConfig, Code, Status},
+};
+use yellowstone_vixen::{
+    checkpoint::{Checkpoint, StreamProgress},
+    sources::SourceTrait,
+    CommitmentLevel, Error as VixenError,
 };
-use yellowstone_vixen::{sources::SourceTrait, CommitmentLevel, Error as VixenError};
-use yellowstone_vixen_core::Filters;
+use yellowstone_vixen_core::{DataSlice, Filters, Prefilter};
 
 /// Yellowstone connection configuration.
 #[derive(Debug, clap::Args, serde::Deserialize, Clone)]
@@ -33,65 +52,751 @@ pub struct YellowstoneGrpcConfig {
 }
 
 /// A `Source` implementation for the Yellowstone gRPC API.
+///
+/// Each parser is subscribed to over its own stream.  When the filters of the
+/// source are updated, the new filters of each parser are pushed over its
+/// existing stream, and the streams of removed parsers are kept open to be
+/// reused for parsers added later.
+///
+/// Every stream requests the account data slices of the whole subscription,
+/// since the runtime reads the account data of every update in that layout.
+///
+/// Streams failing with a transient error are reopened after a delay.  Errors
+/// that would recur on every attempt, such as a rejected token, are forwarded
+/// to the runtime instead, stopping it.
+///
+/// If the runtime is checkpointed, subscriptions start from the slot
+/// following the last checkpoint rather than the configured `from_slot`,
+/// including when a stream is reopened or added by a filter update.  Each
+/// stream also receives the slot statuses of its commitment level, so the
+/// checkpoint can follow the progress of every stream even if it receives
+/// few updates.
 #[derive(Debug)]
 pub struct YellowstoneGrpcSource {
+    config: YellowstoneGrpcConfig,
+    checkpoint: Option<Checkpoint>,
+    connector: Connector,
+    subscriptions: Mutex<Subscriptions>,
+}
+
+/// The filter each stream receives slot statuses under to track its
+/// progress.  Updates matching only this filter are not sent to the runtime.
+const PROGRESS_FILTER: &str = "vixen::stream-progress";
+
+const MIN_RECONNECT_DELAY: Duration = Duration::from_secs(1);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+
+type UpdateTx = Sender<Result<SubscribeUpdate, Status>>;
+type SubscribeSink = Pin<Box<dyn Sink<SubscribeRequest, Error = VixenError> + Send>>;
+type UpdateStream = Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send>>;
+
+struct Connection {
+    sink: SubscribeSink,
+    updates: UpdateStream,
+}
+
+type Connect = dyn Fn(
+        YellowstoneGrpcConfig,
+        SubscribeRequest,
+    ) -> BoxFuture<'static, Result<Connection, VixenError>>
+    + Send
+    + Sync;
+
+/// Opens a subscription to the Yellowstone server
+#[derive(Clone)]
+struct Connector(Arc<Connect>);
+
+impl fmt::Debug for Connector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result { f.write_str("Connector") }
+}
+
+async fn connect_grpc(
+    config: YellowstoneGrpcConfig,
+    request: SubscribeRequest,
+) -> Result<Connection, VixenError> {
+    let timeout = Duration::from_secs(config.timeout);
+
+    let mut client = GeyserGrpcClient::build_from_shared(config.endpoint)?
+        .x_token(config.x_token)?
+        .connect_timeout(timeout)
+        .timeout(timeout)
+        .tls_config(ClientTlsConfig::new().with_native_roots())?
+        .connect()
+        .await?;
+
+    let (sub_tx, updates) = client.subscribe_with_request(Some(request)).await?;
+
+    Ok(Connection {
+        sink: Box::pin(sub_tx.sink_map_err(|e| VixenError::UpdateFilters(e.into()))),
+        updates: Box::pin(updates),
+    })
+}
+
+/// Whether reopening a stream failing with the given status would fail again
+fn is_permanent(status: &Status) -> bool {
+    matches!(
+        status.code(),
+        Code::InvalidArgument
+            | Code::Unauthenticated
+            | Code::PermissionDenied
+            | Code::Unimplemented
+    )
+}
+
+/// The requests of a stream, the default request meaning the stream is idle
+type RequestTx = watch::Sender<SubscribeRequest>;
+
+struct Subscriptions {
     filters: Filters,
+    tx: Option<WeakSender<Result<SubscribeUpdate, Status>>>,
+    active: HashMap<String, RequestTx>,
+    idle: Vec<RequestTx>,
+}
+
+impl fmt::Debug for Subscriptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscriptions")
+            .field("filters", &self.filters)
+            .field("active", &self.active.keys().collect::<Vec<_>>())
+            .field("idle", &self.idle.len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// A stream forwarding updates to the runtime, reopened whenever it fails
+struct GrpcStream {
     config: YellowstoneGrpcConfig,
+    connector: Connector,
+    checkpoint: Option<Checkpoint>,
+    progress: Option<StreamProgress>,
+    requests: watch::Receiver<SubscribeRequest>,
+    tx: UpdateTx,
+}
+
+impl GrpcStream {
+    /// The latest request of the stream, starting from the given slot
+    fn request(&mut self, from_slot: Option<u64>) -> SubscribeRequest {
+        let mut request = self.requests.borrow_and_update().clone();
+
+        // Idle streams have nothing to replay
+        if request != SubscribeRequest::default() {
+            request.from_slot = from_slot;
+        }
+
+        request
+    }
+
+    async fn connect(&mut self, from_slot: Option<u64>) -> Result<Connection, VixenError> {
+        let request = self.request(from_slot);
+        let from_slot = request.from_slot;
+        let connection = (self.connector.0)(self.config.clone(), request).await?;
+
+        if let Some(progress) = &self.progress {
+            progress.reset(from_slot);
+        }
+
+        Ok(connection)
+    }
+
+    async fn run(mut self, mut connection: Option<Connection>) {
+        let mut delay = MIN_RECONNECT_DELAY;
+
+        loop {
+            let connection = match connection.take() {
+                Some(c) => c,
+                None => {
+                    let from_slot = self.checkpoint.as_ref().and_then(Checkpoint::resume_slot);
+
+                    match self.connect(from_slot).await {
+                        Ok(c) => {
+                            delay = MIN_RECONNECT_DELAY;
+                            c
+                        },
+                        Err(e) => {
+                            tracing::warn!(err = ?e, ?delay, "Error reconnecting Yellowstone stream");
+
+                            if self.wait(&mut delay).await {
+                                continue;
+                            }
+
+                            return;
+                        },
+                    }
+                },
+            };
+
+            match self.forward(connection).await {
+                Ok(()) => return,
+                Err(e) => {
+                    tracing::warn!(err = ?e, ?delay, "Yellowstone stream failed, reconnecting");
+                },
+            }
+
+            if !self.wait(&mut delay).await {
+                return;
+            }
+        }
+    }
+
+    /// Forward the updates of a connection until it fails, returning `Ok` if
+    /// the stream should not be reopened.
+    async fn forward(&mut self, connection: Connection) -> Result<(), VixenError> {
+        let Connection {
+            mut sink,
+            mut updates,
+        } = connection;
+
+        loop {
+            tokio::select! {
+                // The buffer closes its channel once it starts draining
+                () = self.tx.closed() => {
+                    tracing::debug!("Buffer closed, stopping subscription");
+                    return Ok(());
+                },
+                changed = self.requests.changed() => {
+                    // The source was dropped
+                    if changed.is_err() {
+                        return Ok(());
+                    }
+
+                    let request = self.request(None);
+
+                    if request == SubscribeRequest::default()
+                        && let Some(progress) = &self.progress
+                    {
+                        progress.reset(None);
+                    }
+
+                    sink.send(request).await?;
+                },
+                update = updates.next() => {
+                    let update = match update {
+                        Some(Ok(mut update)) => {
+                            if let Some(progress) = &self.progress {
+                                progress.observe(&update);
+                            }
+
+                            if update.filters.iter().any(|f| f == PROGRESS_FILTER) {
+                                update.filters.retain(|f| f != PROGRESS_FILTER);
+
+                                if update.filters.is_empty() {
+                                    continue;
+                                }
+                            }
+
+                            Ok(update)
+                        },
+                        Some(Err(status)) if is_permanent(&status) => Err(status),
+                        Some(Err(status)) => return Err(status.into()),
+                        None => return Err(VixenError::ServerHangup),
+                    };
+                    let stop = update.is_err();
+
+                    // The buffer closes its channel once it starts draining
+                    if self.tx.send(update).await.is_err() {
+                        tracing::debug!("Buffer closed, stopping subscription");
+                        return Ok(());
+                    }
+
+                    if stop {
+                        return Ok(());
+                    }
+                },
+            }
+        }
+    }
+
+    /// Wait before reconnecting, returning `false` if the buffer closed
+    /// meanwhile.
+    async fn wait(&self, delay: &mut Duration) -> bool {
+        let closed = tokio::select! {
+            () = tokio::time::sleep(*delay) => false,
+            () = self.tx.closed() => true,
+        };
+
+        *delay = (*delay * 2).min(MAX_RECONNECT_DELAY);
+
+        !closed
+    }
+}
+
+impl YellowstoneGrpcSource {
+    fn request(
+        &self,
+        filter_id: String,
+        prefilter: Prefilter,
+        data_slices: &[DataSlice],
+    ) -> SubscribeRequest {
+        let filter = Filters::new(HashMap::from([(filter_id, prefilter)]));
+
+        let mut subscribe_request: SubscribeRequest = filter.into();
+        if let Some(commitment_level) = self.config.commitment_level {
+            subscribe_request.commitment = Some(commitment_level as i32);
+        }
+
+        subscribe_request.accounts_data_slice = data_slices
+            .iter()
+            .map(
+                |&DataSlice { offset, length }| SubscribeRequestAccountsDataSlice {
+                    offset,
+                    length,
+                },
+            )
+            .collect();
+
+        subscribe_request
+            .slots
+            .insert(PROGRESS_FILTER.to_owned(), SubscribeRequestFilterSlots {
+                filter_by_commitment: Some(true),
+                ..SubscribeRequestFilterSlots::default()
+            });
+
+        subscribe_request
+    }
+
+    fn start_slot(&self) -> Option<u64> {
+        self.checkpoint
+            .as_ref()
+            .and_then(Checkpoint::resume_slot)
+            .or(self.config.from_slot)
+    }
+
+    fn stream(&self, request: SubscribeRequest, tx: UpdateTx) -> (RequestTx, GrpcStream) {
+        let (request_tx, requests) = watch::channel(request);
+
+        let stream = GrpcStream {
+            config: self.config.clone(),
+            connector: self.connector.clone(),
+            checkpoint: self.checkpoint.clone(),
+            progress: self.checkpoint.as_ref().map(Checkpoint::track_stream),
+            requests,
+            tx,
+        };
+
+        (request_tx, stream)
+    }
 }
 
 #[async_trait]
 impl SourceTrait for YellowstoneGrpcSource {
     type Config = YellowstoneGrpcConfig;
 
-    fn new(config: Self::Config, filters: Filters) -> Self { Self { config, filters } }
+    fn new(config: Self::Config, filters: Filters) -> Self {
+        Self {
+            config,
+            checkpoint: None,
+            connector: Connector(Arc::new(|config, request| {
+                Box::pin(connect_grpc(config, request))
+            })),
+            subscriptions: Mutex::new(Subscriptions {
+                filters,
+                tx: None,
+                active: HashMap::new(),
+                idle: vec![],
+            }),
+        }
+    }
 
     async fn connect(&self, tx: Sender<Result<SubscribeUpdate, Status>>) -> Result<(), VixenError> {
-        let filters = self.filters.clone();
-        let config = self.config.clone();
+        let mut tasks_set = JoinSet::new();
 
-        let timeout = Duration::from_secs(config.timeout);
+        {
+            // Filter updates wait until every initial subscription is open
+            let mut subs = self.subscriptions.lock().await;
+            subs.tx = Some(tx.downgrade());
+            let data_slices = subs.filters.account_data_slices();
 
-        let mut tasks_set = JoinSet::new();
+            for (filter_id, prefilter) in subs.filters.parsers_filters.clone() {
+                let request = self.request(filter_id.clone(), prefilter, &data_slices);
+                let (request_tx, mut stream) = self.stream(request, tx.clone());
+
+                // Fail early if the server cannot be reached at all
+                let connection = stream.connect(self.start_slot()).await?;
+                tasks_set.spawn(stream.run(Some(connection)));
+
+                subs.active.insert(filter_id, request_tx);
+            }
+        }
+
+        tasks_set.join_all().await;
+
+        Ok(())
+    }
+
+    fn set_checkpoint(&mut self, checkpoint: Checkpoint) { self.checkpoint = Some(checkpoint); }
+
+    async fn update_filters(&self, filters: Filters) -> Result<(), VixenError> {
+        let mut subs = self.subscriptions.lock().await;
 
-        for (filter_id, prefilter) in filters.parsers_filters {
-            let filter = Filters::new(HashMap::from([(filter_id, prefilter)]));
+        let Some(weak_tx) = &subs.tx else {
+            // Not connected yet, so just subscribe with the new filters
+            subs.filters = filters;
+            return Ok(());
+        };
+        let tx = weak_tx.upgrade().ok_or(VixenError::ClientHangup)?;
 
-            let tx = tx.clone();
+        // Build every request before changing any stream, so that the
+        // subscription is either fully updated or left unchanged
+        let data_slices = filters.account_data_slices();
+        let requests: HashMap<_, _> = filters
+            .parsers_filters
+            .into_iter()
+            .map(|(filter_id, prefilter)| {
+                let request = self.request(filter_id.clone(), prefilter, &data_slices);
+                (filter_id, request)
+            })
+            .collect();
 
-            let mut client = GeyserGrpcClient::build_from_shared(config.endpoint.clone())?
-                .x_token(config.x_token.clone())?
-                .connect_timeout(timeout)
-                .timeout(timeout)
-                .tls_config(ClientTlsConfig::new().with_native_roots())?
-                .connect()
-                .await?;
+        let Subscriptions { active, idle, .. } = &mut *subs;
 
-            let mut subscribe_request: SubscribeRequest = filter.into();
-            if let Some(from_slot) = config.from_slot {
-                subscribe_request.from_slot = Some(from_slot);
+        // Unsubscribe the streams of removed parsers, keeping them for reuse
+        let removed: Vec<_> = active
+            .keys()
+            .filter(|k| !requests.contains_key(*k))
+            .cloned()
+            .collect();
+
+        for filter_id in removed {
+            if let Some(request_tx) = active.remove(&filter_id) {
+                request_tx.send_replace(SubscribeRequest::default());
+                idle.push(request_tx);
             }
-            if let Some(commitment_level) = config.commitment_level {
-                subscribe_request.commitment = Some(commitment_level as i32);
+        }
+
+        for (filter_id, request) in requests {
+            match active.entry(filter_id) {
+                Entry::Occupied(o) => {
+                    o.get().send_if_modified(|r| {
+                        let modified = *r != request;
+                        *r = request;
+                        modified
+                    });
+                },
+                Entry::Vacant(v) => {
+                    let request_tx = if let Some(request_tx) = idle.pop() {
+                        request_tx.send_replace(request);
+                        request_tx
+                    } else {
+                        // Opened in the background, starting from the checkpoint
+                        let (request_tx, stream) = self.stream(request, tx.clone());
+                        tokio::spawn(stream.run(None));
+                        request_tx
+                    };
+
+                    v.insert(request_tx);
+                },
             }
+        }
 
-            let (_sub_tx, stream) = client
-                .subscribe_with_request(Some(subscribe_request))
-                .await?;
+        Ok(())
+    }
+}
 
-            tasks_set.spawn(async move {
-                let mut stream = std::pin::pin!(stream);
+#[cfg(test)]
+mod tests {
+    use tokio::sync::mpsc;
+    use yellowstone_grpc_proto::geyser::{
+        subscribe_update::UpdateOneof, SlotStatus, SubscribeUpdateSlot,
+    };
 
-                while let Some(update) = stream.next().await {
-                    let res = tx.send(update).await;
-                    if res.is_err() {
-                        tracing::error!("Failed to send update to buffer");
-                    }
-                }
+    use super::*;
+
+    fn config() -> YellowstoneGrpcConfig {
+        YellowstoneGrpcConfig {
+            endpoint: String::new(),
+            x_token: None,
+            timeout: 120,
+            commitment_level: None,
+            from_slot: None,
+        }
+    }
+
+    fn source(filters: Filters) -> YellowstoneGrpcSource {
+        YellowstoneGrpcSource::new(config(), filters)
+    }
+
+    fn filters<const N: usize>(ids: [&str; N]) -> Filters {
+        let prefilter = Prefilter::builder()
+            .account_owners([[1; 32]])
+            .build()
+            .unwrap();
+
+        Filters::new(
+            ids.into_iter()
+                .map(|id| (id.to_owned(), prefilter.clone()))
+                .collect(),
+        )
+    }
+
+    fn slot_update<const N: usize>(
+        slot: u64,
+        filters: [&str; N],
+    ) -> Result<SubscribeUpdate, Status> {
+        Ok(SubscribeUpdate {
+            filters: filters.map(str::to_owned).into(),
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                status: SlotStatus::SlotConfirmed as i32,
+                ..SubscribeUpdateSlot::default()
+            })),
+            ..SubscribeUpdate::default()
+        })
+    }
+
+    /// A subscription opened on the fake server
+    struct FakeConnection {
+        request: SubscribeRequest,
+        requests: mpsc::UnboundedReceiver<SubscribeRequest>,
+        updates: mpsc::UnboundedSender<Result<SubscribeUpdate, Status>>,
+    }
+
+    /// A source connecting to a fake server, which hands out each opened
+    /// subscription.
+    fn fake_source(
+        config: YellowstoneGrpcConfig,
+        filters: Filters,
+    ) -> (
+        YellowstoneGrpcSource,
+        mpsc::UnboundedReceiver<FakeConnection>,
+    ) {
+        let (server_tx, server) = mpsc::unbounded_channel();
+        let mut source = YellowstoneGrpcSource::new(config, filters);
+
+        source.connector = Connector(Arc::new(move |_, request| {
+            let (requests_tx, requests) = mpsc::unbounded_channel();
+            let (updates, updates_rx) = mpsc::unbounded_channel();
+            let opened = server_tx.send(FakeConnection {
+                request,
+                requests,
+                updates,
             });
+
+            Box::pin(async move {
+                opened.map_err(|_| VixenError::ServerHangup)?;
+
+                let sink = futures_util::sink::unfold(requests_tx, |tx, request| async move {
+                    tx.send(request).map_err(|_| VixenError::ServerHangup)?;
+                    Ok(tx)
+                });
+                let updates = futures_util::stream::unfold(updates_rx, |mut rx| async move {
+                    rx.recv().await.map(|u| (u, rx))
+                });
+
+                Ok(Connection {
+                    sink: Box::pin(sink),
+                    updates: Box::pin(updates),
+                })
+            })
+        }));
+
+        (source, server)
+    }
+
+    type UpdateRx = mpsc::Receiver<Result<SubscribeUpdate, Status>>;
+
+    fn connect(
+        source: &Arc<YellowstoneGrpcSource>,
+    ) -> (tokio::task::JoinHandle<Result<(), VixenError>>, UpdateRx) {
+        let (tx, rx) = mpsc::channel(16);
+        let source = Arc::clone(source);
+
+        (tokio::spawn(async move { source.connect(tx).await }), rx)
+    }
+
+    fn requests(filters: &Filters) -> HashMap<String, Vec<DataSlice>> {
+        let source = source(filters.clone());
+        let data_slices = filters.account_data_slices();
+
+        filters
+            .parsers_filters
+            .iter()
+            .map(|(id, prefilter)| {
+                let request = source.request(id.clone(), prefilter.clone(), &data_slices);
+                let slices = request
+                    .accounts_data_slice
+                    .iter()
+                    .map(|s| DataSlice::new(s.offset, s.length))
+                    .collect();
+
+                (id.clone(), slices)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn streams_request_every_data_slice() {
+        let header = Prefilter::builder()
+            .account_owners([[1; 32]])
+            .account_data_slice(0, 8)
+            .build()
+            .unwrap();
+        let tail = Prefilter::builder()
+            .account_owners([[2; 32]])
+            .account_data_slice(32, 8)
+            .build()
+            .unwrap();
+        let filters =
+            Filters::new([("header".into(), header.clone()), ("tail".into(), tail)].into());
+
+        // Each stream receives data laid out as the runtime reads it
+        let requested = requests(&filters);
+        let slices = [DataSlice::new(0, 8), DataSlice::new(32, 8)];
+        assert_eq!(requested["header"], slices);
+        assert_eq!(requested["tail"], slices);
+        assert_eq!(filters.account_data_slices(), slices);
+
+        let full = Prefilter::builder()
+            .account_owners([[3; 32]])
+            .build()
+            .unwrap();
+        let filters = Filters::new([("header".into(), header), ("full".into(), full)].into());
+        assert!(requests(&filters).values().all(Vec::is_empty));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn streams_reconnect_from_checkpoint() {
+        let config = YellowstoneGrpcConfig {
+            from_slot: Some(10),
+            ..config()
+        };
+        let (mut source, mut server) = fake_source(config, filters(["a"]));
+        source.set_checkpoint(Checkpoint::new(Some(41)));
+        let source = Arc::new(source);
+        let (_task, mut rx) = connect(&source);
+
+        // The checkpoint takes precedence over the configured slot
+        let conn = server.recv().await.unwrap();
+        assert_eq!(conn.request.from_slot, Some(42));
+
+        conn.updates
+            .send(slot_update(42, ["a", PROGRESS_FILTER]))
+            .unwrap();
+        conn.updates
+            .send(slot_update(43, [PROGRESS_FILTER]))
+            .unwrap();
+        conn.updates
+            .send(Err(Status::new(Code::Unavailable, "restarting")))
+            .unwrap();
+
+        // Reopened after a transient error or a hangup
+        let conn = server.recv().await.unwrap();
+        assert_eq!(conn.request.from_slot, Some(42));
+        drop(conn);
+        let conn = server.recv().await.unwrap();
+        assert_eq!(conn.request.from_slot, Some(42));
+
+        // Streams of added parsers also start from the checkpoint
+        source.update_filters(filters(["a", "b"])).await.unwrap();
+        let added = server.recv().await.unwrap();
+        assert_eq!(added.request.from_slot, Some(42));
+        assert!(added.request.accounts.contains_key("b"));
+
+        // Only updates matching a parser reach the runtime
+        let update = rx.recv().await.unwrap().unwrap();
+        assert_eq!(update.filters, ["a"]);
+        assert!(rx.try_recv().is_err());
+        drop(conn);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn configured_slot_applies_to_first_subscribe() {
+        let config = YellowstoneGrpcConfig {
+            from_slot: Some(10),
+            ..config()
+        };
+        let (source, mut server) = fake_source(config, filters(["a"]));
+        let source = Arc::new(source);
+        let (_task, _rx) = connect(&source);
+
+        let conn = server.recv().await.unwrap();
+        assert_eq!(conn.request.from_slot, Some(10));
+        drop(conn);
+
+        let conn = server.recv().await.unwrap();
+        assert_eq!(conn.request.from_slot, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn permanent_errors_stop_the_runtime() {
+        let (source, mut server) = fake_source(config(), filters(["a"]));
+        let source = Arc::new(source);
+        let (task, mut rx) = connect(&source);
+
+        let conn = server.recv().await.unwrap();
+        conn.updates
+            .send(Err(Status::new(Code::Unauthenticated, "bad token")))
+            .unwrap();
+
+        let status = rx.recv().await.unwrap().unwrap_err();
+        assert_eq!(status.code(), Code::Unauthenticated);
+        task.await.unwrap().unwrap();
+        assert!(server.try_recv().is_err());
+    }
+
+    /// Let every stream task process its pending requests
+    async fn settle() { tokio::time::sleep(Duration::from_millis(1)).await; }
+
+    fn subscribed(request: &SubscribeRequest) -> Vec<&str> {
+        request.accounts.keys().map(String::as_str).collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn filter_updates_reuse_streams() {
+        let (source, mut server) = fake_source(config(), filters(["a", "b"]));
+        let source = Arc::new(source);
+        let (_task, _rx) = connect(&source);
+
+        let mut conns = HashMap::new();
+        for _ in 0..2 {
+            let conn = server.recv().await.unwrap();
+            conns.insert(subscribed(&conn.request)[0].to_owned(), conn);
         }
+        let mut a = conns.remove("a").unwrap();
+        let mut b = conns.remove("b").unwrap();
 
-        tasks_set.join_all().await;
+        // The stream of a removed parser is unsubscribed, unchanged streams
+        // receive nothing
+        source.update_filters(filters(["a"])).await.unwrap();
+        assert_eq!(
+            b.requests.recv().await.unwrap(),
+            SubscribeRequest::default()
+        );
+        settle().await;
+        assert!(a.requests.try_recv().is_err());
 
-        Ok(())
+        // An added parser takes over the idle stream
+        source.update_filters(filters(["a", "c"])).await.unwrap();
+        assert_eq!(subscribed(&b.requests.recv().await.unwrap()), ["c"]);
+
+        // A changed prefilter is pushed over the existing stream
+        let owner = Prefilter::builder()
+            .account_owners([[2; 32]])
+            .build()
+            .unwrap();
+        let mut changed = filters(["a", "c"]);
+        changed.parsers_filters.insert("a".into(), owner);
+        source.update_filters(changed).await.unwrap();
+        let request = a.requests.recv().await.unwrap();
+        assert_eq!(request.accounts["a"].owner, [
+            yellowstone_vixen_core::Pubkey::from([2; 32]).to_string()
+        ]);
+
+        settle().await;
+        assert!(b.requests.try_recv().is_err());
+        assert!(server.try_recv().is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn filter_updates_before_connect_replace_filters() {
+        let (source, mut server) = fake_source(config(), filters(["a"]));
+        let source = Arc::new(source);
+
+        source.update_filters(filters(["b"])).await.unwrap();
+        let (_task, _rx) = connect(&source);
+
+        let conn = server.recv().await.unwrap();
+        assert_eq!(subscribed(&conn.request), ["b"]);
+        settle().await;
+        assert!(server.try_recv().is_err());
     }
 }
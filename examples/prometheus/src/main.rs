@@ -53,10 +53,7 @@ impl vixen::Handler<InstructionUpdateOutput<RaydiumAmmV4ProgramIx>> for RaydiumA
                     "4xxM4cdb6MEsCxM52xvYqkNbzvdeWWsPDZrBcTqVGUar",
                 ];
                 if !accounts_expected.contains(&accounts.amm.to_string().as_str()) {
-                    tracing::info!(
-                        "Not expected tx sig: {}",
-                        vixen::bs58::encode(&value.shared_data.signature).into_string()
-                    );
+                    tracing::info!("Not expected tx sig: {}", value.shared_data.signature);
                 }
             },
             RaydiumAmmV4ProgramIx::SwapBaseOut(accounts, _data) => {
@@ -65,10 +62,7 @@ impl vixen::Handler<InstructionUpdateOutput<RaydiumAmmV4ProgramIx>> for RaydiumA
                     "4xxM4cdb6MEsCxM52xvYqkNbzvdeWWsPDZrBcTqVGUar",
                 ];
                 if !accounts_expected.contains(&accounts.amm.to_string().as_str()) {
-                    tracing::info!(
-                        "Not expected tx sig: {}",
-                        vixen::bs58::encode(&value.shared_data.signature).into_string()
-                    );
+                    tracing::info!("Not expected tx sig: {}", value.shared_data.signature);
                 }
             },
             _ => {},